@@ -15,16 +15,17 @@ use northstar_runtime::{
     api::{
         codec,
         model::{
-            ConnectNack, Container, ContainerData, InspectResult, InstallResult, Message,
-            MountResult, Notification, RepositoryId, Request, Response, Token, UmountResult,
-            VerificationResult,
+            AttachResult, ConnectNack, Container, ContainerData, InspectResult, InstallResult,
+            KillResult, ListEntry, Message, MountResult, Notification, RepositoryId, Request,
+            ResizeResult, Response, Seccomp, SeccompResult, Selector, StartResult, Token,
+            UmountResult, VerificationResult,
         },
     },
     common::non_nul_string::NonNulString,
 };
 use tokio::{
     fs,
-    io::{self, AsyncRead, AsyncWrite, BufWriter},
+    io::{self, AsyncRead, AsyncWrite, BufWriter, ReadBuf},
     time,
 };
 
@@ -67,6 +68,56 @@ pub struct Client<T> {
 /// Northstar console connection
 pub type Connection<T> = codec::Framed<T>;
 
+/// Connection attached to the terminal of a container. Bytes read are the output of
+/// the terminal and bytes written are the input. Dropping the connection detaches.
+pub struct Attached<T> {
+    /// Connection to the runtime
+    io: T,
+    /// Terminal output received before the connection was switched to raw mode
+    buffer: Vec<u8>,
+}
+
+impl<T: AsyncRead + Unpin> AsyncRead for Attached<T> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        if self.buffer.is_empty() {
+            Pin::new(&mut self.io).poll_read(cx, buf)
+        } else {
+            let n = buf.remaining().min(self.buffer.len());
+            buf.put_slice(&self.buffer[..n]);
+            self.buffer.drain(..n);
+            Poll::Ready(Ok(()))
+        }
+    }
+}
+
+impl<T: AsyncWrite + Unpin> AsyncWrite for Attached<T> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.io).poll_write(cx, buf)
+    }
+
+    fn poll_flush(
+        mut self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.io).poll_flush(cx)
+    }
+
+    fn poll_shutdown(
+        mut self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.io).poll_shutdown(cx)
+    }
+}
+
 /// Connect and return a raw stream and sink interface. See codec for details
 ///
 /// # Arguments
@@ -190,7 +241,7 @@ impl<'a, T: AsyncRead + AsyncWrite + Unpin> Client<T> {
     /// # #[tokio::main(flavor = "current_thread")]
     /// # async fn main() {
     /// #   let mut client = Client::new(tokio::net::TcpStream::connect("localhost:4200").await.unwrap(), None, Duration::from_secs(10)).await.unwrap();
-    /// let response = client.request(List).await.expect("failed to request container list");
+    /// let response = client.request(List { selector: None }).await.expect("failed to request container list");
     /// println!("{:?}", response);
     /// # }
     /// ```
@@ -262,8 +313,39 @@ impl<'a, T: AsyncRead + AsyncWrite + Unpin> Client<T> {
     /// # }
     /// ```
     pub async fn list(&mut self) -> Result<Vec<Container>, error::RequestError> {
-        match self.request(Request::List).await? {
-            Response::List(containers) => Ok(containers),
+        match self.request(Request::List { selector: None }).await? {
+            Response::List(entries) => Ok(entries.into_iter().map(|e| e.container).collect()),
+            Response::PermissionDenied(_) => Err(RequestError::PermissionDenied),
+            _ => unreachable!("response on containers should be containers"),
+        }
+    }
+
+    /// Request a list of installed containers and their labels that match `selector`.
+    /// Use [`Client::list`] to list all containers.
+    ///
+    /// ```no_run
+    /// # use futures::StreamExt;
+    /// # use tokio::time::Duration;
+    /// # use northstar_client::Client;
+    /// #
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// #   let mut client = Client::new(tokio::net::TcpStream::connect("localhost:4200").await.unwrap(), None, Duration::from_secs(10)).await.unwrap();
+    /// let containers = client.list_selector("tier=background").await.expect("failed to request container list");
+    /// println!("{:#?}", containers);
+    /// # }
+    /// ```
+    pub async fn list_selector<S>(
+        &mut self,
+        selector: S,
+    ) -> Result<Vec<ListEntry>, error::RequestError>
+    where
+        S: TryInto<Selector>,
+        S::Error: std::error::Error + Send + Sync + 'static,
+    {
+        let selector = Some(selector.try_into().context("invalid selector")?);
+        match self.request(Request::List { selector }).await? {
+            Response::List(entries) => Ok(entries),
             Response::PermissionDenied(_) => Err(RequestError::PermissionDenied),
             _ => unreachable!("response on containers should be containers"),
         }
@@ -368,6 +450,67 @@ impl<'a, T: AsyncRead + AsyncWrite + Unpin> Client<T> {
         args: impl IntoIterator<Item = A>,
         env: impl IntoIterator<Item = (A, A)>,
     ) -> Result<(), error::RequestError>
+    where
+        C: TryInto<Container>,
+        C::Error: std::error::Error + Send + Sync + 'static,
+        A: TryInto<NonNulString>,
+        A::Error: std::error::Error + Send + Sync + 'static,
+    {
+        self.start_with_args_env_deadline(container, args, env, None)
+            .await
+    }
+
+    /// Start container name and stop it after `deadline`. The deadline overrides
+    /// the `max_runtime` of the manifest.
+    ///
+    /// ```no_run
+    /// # use futures::StreamExt;
+    /// # use std::time::Duration;
+    /// # use northstar_client::Client;
+    /// #
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// #   let mut client = Client::new(tokio::net::TcpStream::connect("localhost:4200").await.unwrap(), None, Duration::from_secs(10)).await.unwrap();
+    /// client.start_with_deadline("hello:0.0.1", Duration::from_secs(60)).await.expect("failed to start \"hello\"");
+    /// // Print start notification
+    /// println!("{:#?}", client.next().await);
+    /// # }
+    /// ```
+    pub async fn start_with_deadline<C>(
+        &mut self,
+        container: C,
+        deadline: time::Duration,
+    ) -> Result<(), error::RequestError>
+    where
+        C: TryInto<Container>,
+        C::Error: std::error::Error + Send + Sync + 'static,
+    {
+        self.start_with_args_env_deadline(container, empty::<&str>(), empty(), Some(deadline))
+            .await
+    }
+
+    /// Start container name, pass args, set additional env variables and stop the
+    /// container after an optional `deadline`
+    ///
+    /// ```no_run
+    /// # use futures::StreamExt;
+    /// # use std::time::Duration;
+    /// # use northstar_client::Client;
+    /// #
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// #   let mut client = Client::new(tokio::net::TcpStream::connect("localhost:4200").await.unwrap(), None, Duration::from_secs(10)).await.unwrap();
+    /// let deadline = Some(Duration::from_secs(60));
+    /// client.start_with_args_env_deadline("hello:0.0.1", ["--dump"], [("FOO", "blah")], deadline).await.expect("failed to start \"hello\"");
+    /// # }
+    /// ```
+    pub async fn start_with_args_env_deadline<C, A>(
+        &mut self,
+        container: C,
+        args: impl IntoIterator<Item = A>,
+        env: impl IntoIterator<Item = (A, A)>,
+        deadline: Option<time::Duration>,
+    ) -> Result<(), error::RequestError>
     where
         C: TryInto<Container>,
         C::Error: std::error::Error + Send + Sync + 'static,
@@ -394,22 +537,64 @@ impl<'a, T: AsyncRead + AsyncWrite + Unpin> Client<T> {
 
         let arguments = args_converted;
         let environment = env_converted;
+        let deadline = deadline.map(|deadline| deadline.as_millis().try_into().unwrap_or(u64::MAX));
         let request = Request::Start {
-            container,
+            containers: vec![container],
+            selector: None,
             arguments,
             environment,
+            deadline,
         };
 
         match self.request(request).await? {
-            Response::Start(model::StartResult::Ok { .. }) => Ok(()),
-            Response::Start(model::StartResult::Error { error, .. }) => {
-                Err(RequestError::Runtime(error))
-            }
+            Response::Start(mut result) => match result.pop() {
+                Some(StartResult::Ok { .. }) => Ok(()),
+                Some(StartResult::Error { error, .. }) => Err(RequestError::Runtime(error)),
+                None => unreachable!("response on start should contain a result"),
+            },
             Response::PermissionDenied(_) => Err(RequestError::PermissionDenied),
             _ => unreachable!("response on start should be ok or error"),
         }
     }
 
+    /// Start all containers with labels matching `selector`. Containers that are already
+    /// started and resource containers are skipped.
+    ///
+    /// ```no_run
+    /// # use futures::StreamExt;
+    /// # use std::time::Duration;
+    /// # use northstar_client::Client;
+    /// #
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// #   let mut client = Client::new(tokio::net::TcpStream::connect("localhost:4200").await.unwrap(), None, Duration::from_secs(10)).await.unwrap();
+    /// let result = client.start_selector("tier=background").await.expect("failed to start");
+    /// println!("{:#?}", result);
+    /// # }
+    /// ```
+    pub async fn start_selector<S>(
+        &mut self,
+        selector: S,
+    ) -> Result<Vec<StartResult>, error::RequestError>
+    where
+        S: TryInto<Selector>,
+        S::Error: std::error::Error + Send + Sync + 'static,
+    {
+        let request = Request::Start {
+            containers: vec![],
+            selector: Some(selector.try_into().context("invalid selector")?),
+            arguments: vec![],
+            environment: HashMap::new(),
+            deadline: None,
+        };
+
+        match self.request(request).await? {
+            Response::Start(result) => Ok(result),
+            Response::PermissionDenied(_) => Err(RequestError::PermissionDenied),
+            _ => unreachable!("response on start should be start"),
+        }
+    }
+
     /// Kill container with name
     ///
     /// ```no_run
@@ -431,16 +616,57 @@ impl<'a, T: AsyncRead + AsyncWrite + Unpin> Client<T> {
         C::Error: std::error::Error + Send + Sync + 'static,
     {
         let container = container.try_into().context("invalid container")?;
-        match self.request(Request::Kill { container, signal }).await? {
-            Response::Kill(model::KillResult::Ok { .. }) => Ok(()),
-            Response::Kill(model::KillResult::Error { error, .. }) => {
-                Err(RequestError::Runtime(error))
-            }
+        let request = Request::Kill {
+            containers: vec![container],
+            selector: None,
+            signal,
+        };
+        match self.request(request).await? {
+            Response::Kill(mut result) => match result.pop() {
+                Some(KillResult::Ok { .. }) => Ok(()),
+                Some(KillResult::Error { error, .. }) => Err(RequestError::Runtime(error)),
+                None => unreachable!("response on kill should contain a result"),
+            },
             Response::PermissionDenied(_) => Err(RequestError::PermissionDenied),
             _ => unreachable!("response on kill should be ok or error"),
         }
     }
 
+    /// Send `signal` to all started containers with labels matching `selector`
+    ///
+    /// ```no_run
+    /// # use futures::StreamExt;
+    /// # use tokio::time::Duration;
+    /// # use northstar_client::Client;
+    /// #
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// #   let mut client = Client::new(tokio::net::TcpStream::connect("localhost:4200").await.unwrap(), None, Duration::from_secs(10)).await.unwrap();
+    /// let result = client.kill_selector("tier=background", 15).await.expect("failed to kill");
+    /// println!("{:#?}", result);
+    /// # }
+    /// ```
+    pub async fn kill_selector<S>(
+        &mut self,
+        selector: S,
+        signal: i32,
+    ) -> Result<Vec<KillResult>, error::RequestError>
+    where
+        S: TryInto<Selector>,
+        S::Error: std::error::Error + Send + Sync + 'static,
+    {
+        let request = Request::Kill {
+            containers: vec![],
+            selector: Some(selector.try_into().context("invalid selector")?),
+            signal,
+        };
+        match self.request(request).await? {
+            Response::Kill(result) => Ok(result),
+            Response::PermissionDenied(_) => Err(RequestError::PermissionDenied),
+            _ => unreachable!("response on kill should be kill"),
+        }
+    }
+
     /// Install a npk from path
     ///
     /// ```no_run
@@ -643,13 +869,52 @@ impl<'a, T: AsyncRead + AsyncWrite + Unpin> Client<T> {
             result.push(container);
         }
 
-        match self.request(Request::Mount { containers: result }).await? {
+        if result.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let request = Request::Mount {
+            containers: result,
+            selector: None,
+        };
+        match self.request(request).await? {
             Response::Mount(result) => Ok(result),
             Response::PermissionDenied(_) => Err(RequestError::PermissionDenied),
             _ => unreachable!("response on umount_all should be mount"),
         }
     }
 
+    /// Mount all containers with labels matching `selector` that are not mounted
+    /// ```no_run
+    /// # use northstar_client::Client;
+    /// # use std::time::Duration;
+    /// #
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// let mut client = Client::new(tokio::net::TcpStream::connect("localhost:4200").await.unwrap(), None, Duration::from_secs(10)).await.unwrap();
+    /// client.mount_selector("tier=background").await.expect("failed to mount");
+    /// # }
+    /// ```
+    pub async fn mount_selector<S>(
+        &mut self,
+        selector: S,
+    ) -> Result<Vec<MountResult>, error::RequestError>
+    where
+        S: TryInto<Selector>,
+        S::Error: std::error::Error + Send + Sync + 'static,
+    {
+        self.fused()?;
+        let request = Request::Mount {
+            containers: vec![],
+            selector: Some(selector.try_into().context("invalid selector")?),
+        };
+        match self.request(request).await? {
+            Response::Mount(result) => Ok(result),
+            Response::PermissionDenied(_) => Err(RequestError::PermissionDenied),
+            _ => unreachable!("response on mount should be mount"),
+        }
+    }
+
     /// Umount a mounted container
     ///
     /// ```no_run
@@ -702,7 +967,47 @@ impl<'a, T: AsyncRead + AsyncWrite + Unpin> Client<T> {
             result.push(container);
         }
 
-        match self.request(Request::Umount { containers: result }).await? {
+        if result.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let request = Request::Umount {
+            containers: result,
+            selector: None,
+        };
+        match self.request(request).await? {
+            Response::Umount(result) => Ok(result),
+            Response::PermissionDenied(_) => Err(RequestError::PermissionDenied),
+            _ => unreachable!("response on umount should be umount"),
+        }
+    }
+
+    /// Umount all mounted containers with labels matching `selector`
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # use northstar_client::Client;
+    /// #
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// # let mut client = Client::new(tokio::net::TcpStream::connect("localhost:4200").await.unwrap(), None, Duration::from_secs(10)).await.unwrap();
+    /// client.umount_selector("tier=background").await.expect("failed to unmount");
+    /// # }
+    /// ```
+    pub async fn umount_selector<S>(
+        &mut self,
+        selector: S,
+    ) -> Result<Vec<UmountResult>, error::RequestError>
+    where
+        S: TryInto<Selector>,
+        S::Error: std::error::Error + Send + Sync + 'static,
+    {
+        self.fused()?;
+        let request = Request::Umount {
+            containers: vec![],
+            selector: Some(selector.try_into().context("invalid selector")?),
+        };
+        match self.request(request).await? {
             Response::Umount(result) => Ok(result),
             Response::PermissionDenied(_) => Err(RequestError::PermissionDenied),
             _ => unreachable!("response on umount should be umount"),
@@ -738,6 +1043,118 @@ impl<'a, T: AsyncRead + AsyncWrite + Unpin> Client<T> {
         }
     }
 
+    /// Attach to the terminal of a container started with `io.tty`. The connection is
+    /// switched to raw mode and cannot be used for further requests.
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # use northstar_client::Client;
+    /// # use tokio::io;
+    /// #
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// # let client = Client::new(tokio::net::TcpStream::connect("localhost:4200").await.unwrap(), None, Duration::from_secs(10)).await.unwrap();
+    /// let mut attached = client.attach("hello:0.0.1").await.unwrap();
+    /// io::copy(&mut attached, &mut io::stdout()).await.unwrap();
+    /// # }
+    /// ```
+    pub async fn attach<C>(mut self, container: C) -> Result<Attached<T>, error::RequestError>
+    where
+        C: TryInto<Container>,
+        C::Error: std::error::Error + Send + Sync + 'static,
+    {
+        let container = container.try_into().context("invalid container")?;
+        match self.request(Request::Attach { container }).await? {
+            Response::Attach(AttachResult::Ok { container: _ }) => {
+                let parts = self.connection.into_parts();
+                Ok(Attached {
+                    io: parts.io,
+                    buffer: parts.read_buf.to_vec(),
+                })
+            }
+            Response::Attach(AttachResult::Error {
+                container: _,
+                error,
+            }) => Err(error::RequestError::Runtime(error)),
+            Response::PermissionDenied(_) => Err(RequestError::PermissionDenied),
+            _ => unreachable!("response on attach should be attach"),
+        }
+    }
+
+    /// Set the window size of the terminal of a container started with `io.tty`
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # use northstar_client::Client;
+    /// #
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// # let mut client = Client::new(tokio::net::TcpStream::connect("localhost:4200").await.unwrap(), None, Duration::from_secs(10)).await.unwrap();
+    /// client.resize("hello:0.0.1", 24, 80).await.unwrap();
+    /// # }
+    /// ```
+    pub async fn resize<C>(
+        &mut self,
+        container: C,
+        rows: u16,
+        cols: u16,
+    ) -> Result<(), error::RequestError>
+    where
+        C: TryInto<Container>,
+        C::Error: std::error::Error + Send + Sync + 'static,
+    {
+        let container = container.try_into().context("invalid container")?;
+        match self
+            .request(Request::Resize {
+                container,
+                rows,
+                cols,
+            })
+            .await?
+        {
+            Response::Resize(ResizeResult::Ok { container: _ }) => Ok(()),
+            Response::Resize(ResizeResult::Error {
+                container: _,
+                error,
+            }) => Err(error::RequestError::Runtime(error)),
+            Response::PermissionDenied(_) => Err(RequestError::PermissionDenied),
+            _ => unreachable!("response on resize should be resize"),
+        }
+    }
+
+    /// Seccomp configuration of a container with the system calls recorded in seccomp
+    /// learn mode
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # use northstar_client::Client;
+    /// #
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// # let mut client = Client::new(tokio::net::TcpStream::connect("localhost:4200").await.unwrap(), None, Duration::from_secs(10)).await.unwrap();
+    /// println!("{:?}", client.seccomp("hello:0.0.1").await.unwrap());
+    /// # }
+    /// ```
+    pub async fn seccomp<C>(&mut self, container: C) -> Result<Seccomp, error::RequestError>
+    where
+        C: TryInto<Container>,
+        C::Error: std::error::Error + Send + Sync + 'static,
+    {
+        let container = container.try_into().context("invalid container")?;
+        match self.request(Request::Seccomp { container }).await? {
+            Response::Seccomp(SeccompResult::Ok {
+                container: _,
+                seccomp,
+            }) => Ok(seccomp),
+            Response::Seccomp(SeccompResult::Error {
+                container: _,
+                error,
+            }) => Err(error::RequestError::Runtime(error)),
+            Response::PermissionDenied(_) => Err(RequestError::PermissionDenied),
+            _ => unreachable!("response on seccomp should be seccomp"),
+        }
+    }
+
     /// Create a token
     ///
     /// The `target` parameter must be the container name (without version) of the container that
@@ -0,0 +1,109 @@
+use anyhow::{Context, Result};
+use nix::{
+    errno::Errno,
+    libc,
+    sys::termios::{self, SetArg, Termios},
+    unistd,
+};
+use northstar_client::{model::Container, Client};
+use tokio::{
+    io::{self, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    select,
+    signal::unix::{signal, SignalKind},
+    time,
+};
+
+/// Ctrl-]
+const DETACH: u8 = 0x1d;
+
+/// Attach to the terminal of `container`. The local terminal is put into raw mode and
+/// window size changes are sent to the runtime with a second connection.
+pub async fn attach<T>(
+    mut client: Client<T>,
+    container: &Container,
+    url: &url::Url,
+    timeout: time::Duration,
+) -> Result<()>
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+{
+    let tty = unistd::isatty(libc::STDIN_FILENO) == Ok(true);
+    if tty {
+        let (rows, cols) = window_size()?;
+        client.resize(container, rows, cols).await?;
+    }
+
+    let attached = client.attach(container).await?;
+    let (mut reader, mut writer) = io::split(attached);
+    let _raw = if tty { Some(RawMode::enable()?) } else { None };
+
+    let mut stdin = io::stdin();
+    let mut stdout = io::stdout();
+    let mut window_change = signal(SignalKind::window_change())?;
+    let mut input = [0u8; 1024];
+    let mut output = [0u8; 16 * 1024];
+
+    loop {
+        select! {
+            n = stdin.read(&mut input) => {
+                let input = &input[..n?];
+                match input.iter().position(|b| tty && *b == DETACH) {
+                    Some(n) => {
+                        writer.write_all(&input[..n]).await?;
+                        break;
+                    }
+                    None if input.is_empty() => break,
+                    None => writer.write_all(input).await?,
+                }
+            }
+            n = reader.read(&mut output) => match n? {
+                0 => break,
+                n => {
+                    stdout.write_all(&output[..n]).await?;
+                    stdout.flush().await?;
+                }
+            },
+            _ = window_change.recv() => {
+                let (rows, cols) = window_size()?;
+                let io = super::connect(url, timeout).await?;
+                let mut client = Client::new(io, None, timeout).await?;
+                client.resize(container, rows, cols).await?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Rows and columns of the local terminal
+fn window_size() -> Result<(u16, u16)> {
+    let mut size = libc::winsize {
+        ws_row: 0,
+        ws_col: 0,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    };
+    let result = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) };
+    Errno::result(result).context("failed to get window size")?;
+    Ok((size.ws_row, size.ws_col))
+}
+
+/// Raw mode of the local terminal. The previous settings are restored on drop.
+struct RawMode(Termios);
+
+impl RawMode {
+    fn enable() -> Result<RawMode> {
+        let termios = termios::tcgetattr(libc::STDIN_FILENO).context("failed to get termios")?;
+        let mut raw = termios.clone();
+        termios::cfmakeraw(&mut raw);
+        termios::tcsetattr(libc::STDIN_FILENO, SetArg::TCSANOW, &raw)
+            .context("failed to set raw mode")?;
+        Ok(RawMode(termios))
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        termios::tcsetattr(libc::STDIN_FILENO, SetArg::TCSANOW, &self.0).ok();
+    }
+}
@@ -19,6 +19,7 @@ use tokio::{
 use tokio_util::either::Either;
 use trace::Trace;
 
+mod attach;
 mod completion;
 mod pretty;
 mod seccomp;
@@ -37,41 +38,78 @@ fn about() -> &'static str {
 enum Subcommand {
     /// List available containers
     #[clap(alias = "ls")]
-    List,
+    List {
+        /// Only list containers with labels matching the selector, e.g. tier=background
+        #[clap(short = 'l', long)]
+        selector: Option<String>,
+    },
     /// List configured repositories
     #[clap(alias = "repos")]
     Repositories,
     /// Mount a container
     Mount {
         /// Container name and optional version
-        #[clap(value_name = "name[:version]")]
+        #[clap(value_name = "name[:version]", required_unless_present = "selector")]
         containers: Vec<String>,
+        /// Mount containers with labels matching the selector
+        #[clap(short = 'l', long)]
+        selector: Option<String>,
     },
     /// Umount a container
     Umount {
         /// Container name and optional version
-        #[clap(value_name = "name[:version]")]
+        #[clap(value_name = "name[:version]", required_unless_present = "selector")]
         containers: Vec<String>,
+        /// Umount containers with labels matching the selector
+        #[clap(short = 'l', long)]
+        selector: Option<String>,
     },
     /// Start a container
     Start {
         /// Container name and optional version
-        #[clap(value_name = "name[:version]")]
-        container: String,
+        #[clap(
+            value_name = "name[:version]",
+            required_unless_present = "selector",
+            conflicts_with = "selector"
+        )]
+        container: Option<String>,
+        /// Start containers with labels matching the selector
+        #[clap(short = 'l', long)]
+        selector: Option<String>,
         /// Command line arguments
         #[clap(short, long)]
         args: Option<Vec<String>>,
         /// Environment variables in KEY=VALUE format
         #[clap(short, long)]
         env: Option<Vec<String>>,
+        /// Stop the container after this duration, e.g. 10m. Overrides max_runtime.
+        #[clap(short, long, conflicts_with = "selector", parse(try_from_str = humantime::parse_duration))]
+        deadline: Option<time::Duration>,
     },
     /// Stop a container
     Kill {
         /// Container name and optional version
-        #[clap(value_name = "name[:version]")]
-        container: String,
+        #[clap(
+            value_name = "name[:version]",
+            required_unless_present = "selector",
+            conflicts_with = "selector"
+        )]
+        container: Option<String>,
         /// Signal
+        #[clap(value_name = "signal", conflicts_with = "signal-option")]
         signal: Option<i32>,
+        /// Signal. Defaults to SIGTERM
+        #[clap(short, long = "signal", id = "signal-option", value_name = "signal")]
+        signal_option: Option<i32>,
+        /// Signal containers with labels matching the selector
+        #[clap(short = 'l', long)]
+        selector: Option<String>,
+    },
+    /// Attach to the terminal of a container started with `io.tty`. Detach with Ctrl-]
+    Attach {
+        /// Container name and optional version
+        #[clap(value_name = "name[:version]")]
+        container: String,
     },
     /// Install a npk
     Install {
@@ -130,13 +168,17 @@ enum Subcommand {
     },
     /// Identification
     Ident,
-    /// Generate seccomp profile from strace
+    /// Generate seccomp profile from strace or from a container run in seccomp learn mode
     Seccomp {
         /// Path to strace log file
-        input: PathBuf,
+        #[clap(required_unless_present = "from-container")]
+        input: Option<PathBuf>,
         /// Whether or not to allow the syscalls defined by the default profile
         #[clap(long)]
         no_default_profile: bool,
+        /// Container name and optional version of a container run in seccomp learn mode
+        #[clap(long, value_name = "name[:version]", conflicts_with = "input")]
+        from_container: Option<String>,
     },
 }
 
@@ -194,6 +236,30 @@ where
     Ok(container)
 }
 
+/// Connect to the runtime at `url`
+async fn connect(url: &url::Url, timeout: time::Duration) -> Result<Either<TcpStream, UnixStream>> {
+    match url.scheme() {
+        "tcp" => {
+            let addresses = url.socket_addrs(|| Some(4200))?;
+            let address = addresses
+                .first()
+                .ok_or_else(|| anyhow!("failed to resolve {}", url))?;
+            let stream = time::timeout(timeout, TcpStream::connect(address))
+                .await
+                .context("failed to connect")??;
+
+            Ok(Either::Left(stream))
+        }
+        "unix" => {
+            let stream = time::timeout(timeout, UnixStream::connect(url.path()))
+                .await
+                .context("failed to connect")??;
+            Ok(Either::Right(stream))
+        }
+        _ => Err(anyhow!("invalid url")),
+    }
+}
+
 #[tokio::main(flavor = "current_thread")]
 async fn main() -> Result<()> {
     let opt = Opt::parse();
@@ -206,8 +272,9 @@ async fn main() -> Result<()> {
             process::exit(0);
         }
         Subcommand::Seccomp {
-            input,
+            input: Some(input),
             no_default_profile,
+            ..
         } => {
             seccomp::seccomp(input, no_default_profile)?;
             process::exit(0);
@@ -215,26 +282,7 @@ async fn main() -> Result<()> {
         _ => (),
     }
 
-    let io = match opt.url.scheme() {
-        "tcp" => {
-            let addresses = opt.url.socket_addrs(|| Some(4200))?;
-            let address = addresses
-                .first()
-                .ok_or_else(|| anyhow!("failed to resolve {}", opt.url))?;
-            let stream = time::timeout(timeout, TcpStream::connect(address))
-                .await
-                .context("failed to connect")??;
-
-            Either::Left(stream)
-        }
-        "unix" => {
-            let stream = time::timeout(timeout, UnixStream::connect(opt.url.path()))
-                .await
-                .context("failed to connect")??;
-            Either::Right(stream)
-        }
-        _ => return Err(anyhow!("invalid url")),
-    };
+    let io = connect(&opt.url, timeout).await?;
 
     let notifications = if let Subcommand::Notifications { .. } = &opt.command {
         Some(100)
@@ -242,8 +290,9 @@ async fn main() -> Result<()> {
         None
     };
 
-    // Wrap the io in a tracing struct if the json option is set on the cli
-    let io = if opt.json {
+    // Wrap the io in a tracing struct if the json option is set on the cli. The seccomp
+    // command prints the learned profile as json instead of the raw payload.
+    let io = if opt.json && !matches!(opt.command, Subcommand::Seccomp { .. }) {
         Either::Left(Trace::new(io, std::io::stdout()))
     } else {
         Either::Right(io)
@@ -255,9 +304,18 @@ async fn main() -> Result<()> {
         Subcommand::Ident => {
             println!("{}", client.ident().await?);
         }
-        Subcommand::List => {
+        Subcommand::List { selector } => {
+            let selected = match selector {
+                Some(selector) => client
+                    .list_selector(selector.as_str())
+                    .await?
+                    .into_iter()
+                    .map(|entry| entry.container)
+                    .collect(),
+                None => client.list().await?,
+            };
             let mut containers = HashMap::new();
-            for container in client.list().await? {
+            for container in selected {
                 let inspect = Client::inspect(&mut client, &container).await?;
                 containers.insert(container, inspect);
             }
@@ -271,31 +329,63 @@ async fn main() -> Result<()> {
                 pretty::repositories(&repositories);
             }
         }
-        Subcommand::Mount { containers } => {
+        Subcommand::Mount {
+            containers,
+            selector,
+        } => {
             let mut converted = Vec::with_capacity(containers.len());
             for container in containers {
                 converted.push(resolve_container(&container, &mut client).await?);
             }
-            let result = client.mount_all(&converted).await?;
+            let mut result = if converted.is_empty() {
+                vec![]
+            } else {
+                client.mount_all(&converted).await?
+            };
+            if let Some(selector) = selector {
+                result.extend(client.mount_selector(selector.as_str()).await?);
+            }
             if !opt.json {
                 pretty::mounts(&result);
             }
         }
-        Subcommand::Umount { containers } => {
+        Subcommand::Umount {
+            containers,
+            selector,
+        } => {
             let mut converted = Vec::with_capacity(containers.len());
             for container in containers {
                 converted.push(resolve_container(&container, &mut client).await?);
             }
-            let result = client.umount_all(&converted).await?;
+            let mut result = if converted.is_empty() {
+                vec![]
+            } else {
+                client.umount_all(&converted).await?
+            };
+            if let Some(selector) = selector {
+                result.extend(client.umount_selector(selector.as_str()).await?);
+            }
             if !opt.json {
                 pretty::umounts(&result);
             }
         }
+        Subcommand::Start {
+            selector: Some(selector),
+            ..
+        } => {
+            let result = client.start_selector(selector.as_str()).await?;
+            if !opt.json {
+                pretty::starts(&result);
+            }
+        }
         Subcommand::Start {
             container,
             args,
             env,
+            deadline,
+            ..
         } => {
+            let container = container.expect("missing container");
             let container = resolve_container(&container, &mut client).await?;
             let args = args.unwrap_or_default();
             let env = env.unwrap_or_default();
@@ -304,19 +394,44 @@ async fn main() -> Result<()> {
                 .map(|s| s.split_once('=').expect("invalid env. use key=value"))
                 .map(|(k, v)| (k.to_string(), v.to_string()))
                 .collect::<Vec<_>>();
-            client.start_with_args_env(&container, args, env).await?;
+            client
+                .start_with_args_env_deadline(&container, args, env, deadline)
+                .await?;
             if !opt.json {
                 println!("started {}", container);
             }
         }
-        Subcommand::Kill { container, signal } => {
+        Subcommand::Kill {
+            signal_option,
+            selector: Some(selector),
+            ..
+        } => {
+            let signal = signal_option.unwrap_or(15);
+            let result = client.kill_selector(selector.as_str(), signal).await?;
+            if !opt.json {
+                pretty::kills(&result);
+            }
+        }
+        Subcommand::Kill {
+            container,
+            signal,
+            signal_option,
+            ..
+        } => {
+            let container = container.expect("missing container");
             let container = resolve_container(&container, &mut client).await?;
-            let signal = signal.unwrap_or(15);
+            let signal = signal.or(signal_option).unwrap_or(15);
             client.kill(&container, signal).await?;
             if !opt.json {
                 println!("signalled {} with signal {}", container, signal);
             }
         }
+        Subcommand::Attach { container } => {
+            let container = resolve_container(&container, &mut client).await?;
+            attach::attach(client, &container, &opt.url, opt.timeout).await?;
+            // Do not wait for the pending read on stdin
+            process::exit(0);
+        }
         Subcommand::Install { npk, repository } => {
             client.install_file(&npk, &repository).await?;
             if !opt.json {
@@ -343,6 +458,18 @@ async fn main() -> Result<()> {
                 println!("{}", serde_json::to_string_pretty(&inspect)?);
             }
         }
+        Subcommand::Seccomp {
+            from_container: Some(container),
+            ..
+        } => {
+            let container = resolve_container(&container, &mut client).await?;
+            let seccomp = client.seccomp(container).await?;
+            if opt.json {
+                println!("{}", serde_json::to_string_pretty(&seccomp)?);
+            } else {
+                println!("{}", serde_yaml::to_string(&seccomp)?);
+            }
+        }
         Subcommand::Token { target, shared } => {
             let target = Name::try_from(target)?;
             let shared = shared.as_bytes().to_vec();
@@ -1,7 +1,7 @@
 use itertools::Itertools;
 use northstar_client::model::{
-    self, Container, ContainerData, ExitStatus, MountResult, Notification, RepositoryId,
-    UmountResult,
+    self, Container, ContainerData, ExitStatus, KillResult, MountResult, Notification,
+    RepositoryId, StartResult, UmountResult,
 };
 use prettytable::{format, Attr, Cell, Row, Table};
 use std::collections::{HashMap, HashSet};
@@ -15,18 +15,29 @@ pub fn notification(notification: &Notification) {
         Notification::Exit(container, status) => println!(
             "container {} exited with status {}",
             container,
-            match status {
-                ExitStatus::Exit { code } => format!("exit code {}", code),
-                ExitStatus::Signalled { signal } => format!("signalled {}", signal),
-            }
+            exit_status(status)
         ),
         Notification::Install(container) => println!("installed {}", container),
+        Notification::Persist(container, usage) => println!(
+            "container {} uses {} of {} bytes of its persist mount",
+            container, usage.used, usage.size
+        ),
         Notification::Uninstall(container) => println!("uninstalled {}", container),
         Notification::Started(container) => println!("started {}", container),
         Notification::Shutdown => println!("shutting down"),
     }
 }
 
+fn exit_status(status: &ExitStatus) -> String {
+    match status {
+        ExitStatus::Exit { code } => format!("exit code {}", code),
+        ExitStatus::Signalled { signal } => format!("signalled {}", signal),
+        ExitStatus::Deadline { status } => {
+            format!("{} after exceeding its deadline", exit_status(status))
+        }
+    }
+}
+
 pub fn list(containers: &HashMap<Container, ContainerData>) {
     let titles = [
         "Name",
@@ -36,6 +47,7 @@ pub fn list(containers: &HashMap<Container, ContainerData>) {
         "Mounted",
         "PID",
         "Uptime",
+        "Labels",
     ];
 
     let rows = containers
@@ -76,6 +88,15 @@ pub fn list(containers: &HashMap<Container, ContainerData>) {
                         })
                         .unwrap_or_default(),
                 ),
+                Cell::new(
+                    &data
+                        .manifest
+                        .labels
+                        .iter()
+                        .sorted()
+                        .map(|(k, v)| format!("{}={}", k, v))
+                        .join(","),
+                ),
             ]
         });
 
@@ -118,6 +139,34 @@ pub fn umounts(mounts: &[UmountResult]) {
     print_table(["Name", "Result"], iter);
 }
 
+pub fn starts(starts: &[StartResult]) {
+    let iter = starts.iter().map(|r| match r {
+        StartResult::Ok { container } => [
+            Cell::new(&container.to_string()).with_style(Attr::Bold),
+            Cell::new("ok"),
+        ],
+        StartResult::Error { container, error } => [
+            Cell::new(&container.to_string()).with_style(Attr::Bold),
+            Cell::new(&format_err(error)),
+        ],
+    });
+    print_table(["Name", "Result"], iter);
+}
+
+pub fn kills(kills: &[KillResult]) {
+    let iter = kills.iter().map(|r| match r {
+        KillResult::Ok { container } => [
+            Cell::new(&container.to_string()).with_style(Attr::Bold),
+            Cell::new("ok"),
+        ],
+        KillResult::Error { container, error } => [
+            Cell::new(&container.to_string()).with_style(Attr::Bold),
+            Cell::new(&format_err(error)),
+        ],
+    });
+    print_table(["Name", "Result"], iter);
+}
+
 fn format_err(err: &model::Error) -> String {
     match err {
         model::Error::Configuration { context } => format!("invalid configuration: {}", context),
@@ -160,12 +209,25 @@ fn format_err(err: &model::Error) -> String {
             format!(
                 "critical container {} exited with: {}",
                 container,
-                match status {
-                    ExitStatus::Exit { code } => format!("exit code {}", code),
-                    ExitStatus::Signalled { signal } => format!("signaled {}", signal),
-                }
+                exit_status(status)
             )
         }
+        model::Error::ArchMismatch {
+            container,
+            arch,
+            host,
+        } => format!(
+            "container {} is built for {} but the runtime runs on {}",
+            container, arch, host
+        ),
+        model::Error::RuntimeVersionMismatch {
+            container,
+            required,
+            runtime,
+        } => format!(
+            "container {} requires runtime version {} but runtime version is {}",
+            container, required, runtime
+        ),
         model::Error::Unexpected { error } => error.to_string(),
     }
 }
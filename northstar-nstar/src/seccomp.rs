@@ -32,6 +32,13 @@ pub fn seccomp(path: PathBuf, no_default_profile: bool) -> Result<()> {
     let profile = (!no_default_profile).then(|| Profile::Default);
     let allow = (!syscalls.is_empty()).then(|| syscalls);
 
-    println!("{}", &serde_yaml::to_string(&Seccomp { profile, allow })?);
+    println!(
+        "{}",
+        &serde_yaml::to_string(&Seccomp {
+            profile,
+            allow,
+            ..Default::default()
+        })?
+    );
     Ok(())
 }
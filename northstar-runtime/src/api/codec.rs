@@ -99,13 +99,23 @@ mod tests {
     fn mk_message() -> impl Strategy<Value = Message> {
         prop_oneof![
             Just(Message::Request {
-                request: Request::List
+                request: Request::List { selector: None }
             }),
             Just(Message::Request {
                 request: Request::Shutdown
             }),
             Just(Message::Request {
-                request: Request::Mount { containers: vec!() },
+                request: Request::Mount {
+                    containers: vec!(),
+                    selector: None
+                },
+            }),
+            Just(Message::Request {
+                request: Request::Kill {
+                    containers: vec!(),
+                    selector: Some("tier=background".parse().expect("invalid selector")),
+                    signal: 15,
+                },
             }),
             Just(Message::Notification {
                 notification: Notification::Shutdown
@@ -6,7 +6,15 @@ pub mod codec;
 /// API model
 pub mod model;
 
-/// API version
+/// API version derived from the crate version. The runtime rejects clients with an older
+/// major or minor version. Wire incompatible changes since 0.6.0 that require a minor
+/// version bump of the crate with the next release:
+/// - container labels and label selectors for list, mount, umount, start and kill
+/// - `Error::ArchMismatch` and `Error::RuntimeVersionMismatch`
+/// - `Request::Seccomp`
+/// - `Notification::Persist` and the persist usage in `ContainerData`
+/// - `Request::Attach` and `Request::Resize`
+/// - start deadline and `ExitStatus::Deadline`
 pub const VERSION: Version = Version::new(
     pkg_version_major!(),
     pkg_version_minor!(),
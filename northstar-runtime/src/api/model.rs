@@ -3,6 +3,8 @@ use std::collections::{HashMap, HashSet};
 
 /// Container name
 pub type Name = crate::common::name::Name;
+/// CPU architecture
+pub type Arch = crate::npk::manifest::arch::Arch;
 /// Console configuration
 pub type ConsoleConfiguration = crate::npk::manifest::console::Configuration;
 /// Console permission entity
@@ -11,6 +13,8 @@ pub type ConsolePermission = crate::npk::manifest::console::Permission;
 pub type Container = crate::common::container::Container;
 /// Container exit code
 pub type ExitCode = i32;
+/// Container labels
+pub type Labels = crate::common::selector::Labels;
 /// Manifest
 pub type Manifest = crate::npk::manifest::Manifest;
 /// String that never contains a null byte
@@ -19,6 +23,10 @@ pub type NonNulString = crate::common::non_nul_string::NonNulString;
 pub type Pid = u32;
 /// Repository id
 pub type RepositoryId = String;
+/// Seccomp configuration
+pub type Seccomp = crate::seccomp::Seccomp;
+/// Label selector
+pub type Selector = crate::common::selector::Selector;
 /// Unix signal
 pub type Signal = u32;
 /// Version
@@ -47,6 +55,7 @@ pub enum Notification {
     CGroup(Container, CgroupNotification),
     Exit(Container, ExitStatus),
     Install(Container),
+    Persist(Container, PersistUsage),
     Shutdown,
     Started(Container),
     Uninstall(Container),
@@ -72,6 +81,16 @@ pub struct MemoryNotification {
     pub oom_kill: Option<u64>,
 }
 
+/// Usage of a size limited persist mount
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct PersistUsage {
+    /// Size in bytes
+    pub size: u64,
+    /// Used bytes
+    pub used: u64,
+}
+
 /// Connect
 #[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
@@ -105,6 +124,9 @@ pub enum ConnectNack {
 #[serde(rename_all = "snake_case")]
 #[allow(missing_docs)]
 pub enum Request {
+    Attach {
+        container: Container,
+    },
     Inspect {
         container: Container,
     },
@@ -114,19 +136,43 @@ pub enum Request {
         size: u64,
     },
     Kill {
-        container: Container,
+        #[serde(default)]
+        containers: Vec<Container>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        selector: Option<Selector>,
         signal: i32,
     },
-    List,
+    List {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        selector: Option<Selector>,
+    },
     Mount {
+        #[serde(default)]
         containers: Vec<Container>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        selector: Option<Selector>,
     },
     Repositories,
+    Resize {
+        container: Container,
+        rows: u16,
+        cols: u16,
+    },
+    Seccomp {
+        container: Container,
+    },
     Shutdown,
     Start {
-        container: Container,
+        #[serde(default)]
+        containers: Vec<Container>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        selector: Option<Selector>,
         arguments: Vec<NonNulString>,
         environment: HashMap<NonNulString, NonNulString>,
+        /// Stop the containers after this number of milliseconds. Overrides the
+        /// `max_runtime` of the manifests.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        deadline: Option<u64>,
     },
     TokenCreate {
         target: Name,
@@ -140,7 +186,10 @@ pub enum Request {
         shared: Vec<u8>,
     },
     Umount {
+        #[serde(default)]
         containers: Vec<Container>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        selector: Option<Selector>,
     },
     Uninstall {
         container: Container,
@@ -189,6 +238,20 @@ pub struct ContainerData {
     pub mounted: bool,
     /// Process if the container is started
     pub process: Option<Process>,
+    /// Usage of the size limited persist mount if the container is started
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub persist: Option<PersistUsage>,
+}
+
+/// Container list entry
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct ListEntry {
+    /// Container
+    pub container: Container,
+    /// Labels from the container manifest
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub labels: Labels,
 }
 
 /// Process information
@@ -203,6 +266,15 @@ pub struct Process {
     pub statistics: ContainerStats,
 }
 
+/// Attach result
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[allow(missing_docs)]
+pub enum AttachResult {
+    Ok { container: Container },
+    Error { container: Container, error: Error },
+}
+
 /// Mount result
 #[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
@@ -272,21 +344,48 @@ pub enum InspectResult {
     },
 }
 
+/// Resize result
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[allow(missing_docs)]
+pub enum ResizeResult {
+    Ok { container: Container },
+    Error { container: Container, error: Error },
+}
+
+/// Seccomp result
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[allow(missing_docs)]
+pub enum SeccompResult {
+    Ok {
+        container: Container,
+        seccomp: Seccomp,
+    },
+    Error {
+        container: Container,
+        error: Error,
+    },
+}
+
 /// Response
 #[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 #[allow(missing_docs)]
 pub enum Response {
+    Attach(AttachResult),
     Ident(Container),
     Inspect(InspectResult),
     Install(InstallResult),
-    Kill(KillResult),
-    List(Vec<Container>),
+    Kill(Vec<KillResult>),
+    List(Vec<ListEntry>),
     Mount(Vec<MountResult>),
     PermissionDenied(Request),
     Repositories(HashSet<RepositoryId>),
+    Resize(ResizeResult),
+    Seccomp(SeccompResult),
     Shutdown,
-    Start(StartResult),
+    Start(Vec<StartResult>),
     Token(Token),
     TokenVerification(VerificationResult),
     Umount(Vec<UmountResult>),
@@ -307,6 +406,11 @@ pub enum ExitStatus {
         /// Signal
         signal: Signal,
     },
+    /// Process was stopped by the runtime because it exceeded its deadline
+    Deadline {
+        /// Exit status of the process
+        status: Box<ExitStatus>,
+    },
 }
 
 /// API error
@@ -360,6 +464,16 @@ pub enum Error {
         container: Container,
         status: ExitStatus,
     },
+    ArchMismatch {
+        container: Container,
+        arch: Arch,
+        host: Arch,
+    },
+    RuntimeVersionMismatch {
+        container: Container,
+        required: Version,
+        runtime: Version,
+    },
     Unexpected {
         error: String,
     },
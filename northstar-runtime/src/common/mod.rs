@@ -7,5 +7,12 @@ pub mod name;
 /// String without null characters
 pub mod non_nul_string;
 
+/// Container labels and label selectors
+pub mod selector;
+
 /// Container version
 pub mod version;
+
+/// Stable serialization of hashed collections
+#[cfg(feature = "npk")]
+pub(crate) mod ordered;
@@ -0,0 +1,25 @@
+//! Serialization of hashed collections in a stable order. The iteration order of `HashMap`
+//! and `HashSet` differs between processes which leads to different serialized manifests
+//! for the same input.
+
+use serde::{Serialize, Serializer};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+/// Serialize `map` with sorted keys
+pub(crate) fn map<K, V, S>(map: &HashMap<K, V>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    K: Ord + Serialize,
+    V: Serialize,
+    S: Serializer,
+{
+    serializer.collect_map(map.iter().collect::<BTreeMap<_, _>>())
+}
+
+/// Serialize `set` with sorted values
+pub(crate) fn set<T, S>(set: &HashSet<T>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: Ord + Serialize,
+    S: Serializer,
+{
+    serializer.collect_seq(set.iter().collect::<BTreeSet<_>>())
+}
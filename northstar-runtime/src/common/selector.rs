@@ -0,0 +1,304 @@
+use anyhow::{anyhow, bail, Result};
+use serde::{de::Visitor, Deserialize, Serialize, Serializer};
+use std::{
+    collections::HashMap,
+    convert::{TryFrom, TryInto},
+    fmt::{self, Formatter},
+    str::FromStr,
+};
+use thiserror::Error;
+
+use super::non_nul_string::NonNulString;
+
+/// Maximum length allowed for a label key or value
+const MAX_LENGTH: usize = 256;
+
+/// Container labels
+pub type Labels = HashMap<NonNulString, NonNulString>;
+
+/// Label selector. A selector is a comma separated list of requirements that all
+/// must be fulfilled by the labels of a container. Supported requirements are
+/// `key=value` (or `key==value`), `key!=value`, `key` (label is present) and `!key`
+/// (label is not present). A selector contains at least one requirement.
+#[derive(Clone, Eq, PartialEq, Hash)]
+pub struct Selector(Vec<Requirement>);
+
+/// Single requirement of a selector
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub enum Requirement {
+    /// Label `key` is present and has the value `value`
+    Equal(String, String),
+    /// Label `key` is not present or has a different value than `value`
+    NotEqual(String, String),
+    /// Label `key` is present
+    Exists(String),
+    /// Label `key` is not present
+    NotExists(String),
+}
+
+impl Selector {
+    /// Returns true if all requirements of this selector are fulfilled by `labels`
+    pub fn matches(&self, labels: &Labels) -> bool {
+        self.0.iter().all(|requirement| requirement.matches(labels))
+    }
+
+    /// Requirements of this selector
+    pub fn requirements(&self) -> &[Requirement] {
+        &self.0
+    }
+}
+
+impl Requirement {
+    fn matches(&self, labels: &Labels) -> bool {
+        let get = |key: &str| {
+            labels
+                .iter()
+                .find(|(k, _)| k.as_str() == key)
+                .map(|(_, v)| v)
+        };
+        match self {
+            Requirement::Equal(key, value) => get(key).map(|v| v.as_str()) == Some(value),
+            Requirement::NotEqual(key, value) => get(key).map(|v| v.as_str()) != Some(value),
+            Requirement::Exists(key) => get(key).is_some(),
+            Requirement::NotExists(key) => get(key).is_none(),
+        }
+    }
+}
+
+/// Selector parse error
+#[derive(Error, Debug)]
+#[error(transparent)]
+pub struct InvalidSelectorError(#[from] anyhow::Error);
+
+/// Validate a label key. Keys must not be empty, are limited to 256 characters and
+/// may contain: '0'..='9' | 'A'..='Z' | 'a'..='z' | '.' | '_' | '-' | '/'.
+pub fn validate_key(key: &str) -> Result<()> {
+    if key.is_empty() {
+        bail!("label key is empty");
+    }
+    validate(key)
+}
+
+/// Validate a label value. Values are limited to 256 characters and may contain
+/// the same characters as keys. Values can be empty.
+pub fn validate_value(value: &str) -> Result<()> {
+    validate(value)
+}
+
+fn validate(s: &str) -> Result<()> {
+    if s.len() > MAX_LENGTH {
+        bail!("label {} exceeds the maximum length of {}", s, MAX_LENGTH);
+    }
+    if let Some(c) = s
+        .chars()
+        .find(|c| !matches!(c, '0'..='9' | 'A'..='Z' | 'a'..='z' | '.' | '_' | '-' | '/'))
+    {
+        bail!("invalid character in label {}: {}", s, c);
+    }
+    Ok(())
+}
+
+fn parse_requirement(requirement: &str) -> Result<Requirement> {
+    let requirement = requirement.trim();
+    let (key, value, equal) = if let Some((key, value)) = requirement.split_once("!=") {
+        (key, Some(value), false)
+    } else if let Some((key, value)) = requirement.split_once("==") {
+        (key, Some(value), true)
+    } else if let Some((key, value)) = requirement.split_once('=') {
+        (key, Some(value), true)
+    } else {
+        (requirement, None, true)
+    };
+
+    let requirement = match (key.trim(), value.map(str::trim)) {
+        (key, Some(value)) => {
+            validate_key(key)?;
+            validate_value(value)?;
+            if equal {
+                Requirement::Equal(key.into(), value.into())
+            } else {
+                Requirement::NotEqual(key.into(), value.into())
+            }
+        }
+        (key, None) => match key.strip_prefix('!') {
+            Some(key) => {
+                validate_key(key)?;
+                Requirement::NotExists(key.into())
+            }
+            None => {
+                validate_key(key)?;
+                Requirement::Exists(key.into())
+            }
+        },
+    };
+    Ok(requirement)
+}
+
+impl FromStr for Selector {
+    type Err = InvalidSelectorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.trim().is_empty() {
+            return Err(anyhow!("selector is empty").into());
+        }
+        s.split(',')
+            .map(|r| {
+                parse_requirement(r).map_err(|e| anyhow!("invalid requirement \"{}\": {}", r, e))
+            })
+            .collect::<Result<Vec<_>>>()
+            .map(Selector)
+            .map_err(Into::into)
+    }
+}
+
+impl TryFrom<&str> for Selector {
+    type Error = InvalidSelectorError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl TryFrom<String> for Selector {
+    type Error = InvalidSelectorError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl TryFrom<&Selector> for Selector {
+    type Error = InvalidSelectorError;
+
+    fn try_from(selector: &Selector) -> Result<Self, Self::Error> {
+        Ok(selector.clone())
+    }
+}
+
+impl fmt::Display for Requirement {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Requirement::Equal(key, value) => write!(f, "{}={}", key, value),
+            Requirement::NotEqual(key, value) => write!(f, "{}!={}", key, value),
+            Requirement::Exists(key) => write!(f, "{}", key),
+            Requirement::NotExists(key) => write!(f, "!{}", key),
+        }
+    }
+}
+
+impl fmt::Display for Selector {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for (n, requirement) in self.0.iter().enumerate() {
+            if n > 0 {
+                write!(f, ",")?;
+            }
+            write!(f, "{}", requirement)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Selector {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "\"{}\"", self)
+    }
+}
+
+impl Serialize for Selector {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Selector {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct SelectorVisitor;
+
+        impl<'de> Visitor<'de> for SelectorVisitor {
+            type Value = Selector;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("comma separated list of label requirements")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                v.try_into()
+                    .map_err(|e: InvalidSelectorError| E::custom(e.to_string()))
+            }
+        }
+
+        deserializer.deserialize_str(SelectorVisitor)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+fn labels(labels: &[(&str, &str)]) -> Labels {
+    labels
+        .iter()
+        .map(|(k, v)| ((*k).try_into().unwrap(), (*v).try_into().unwrap()))
+        .collect()
+}
+
+#[test]
+#[allow(clippy::unwrap_used)]
+fn parse() {
+    let selector = Selector::from_str("tier=background, app==hello,zone!=a,gpu,!debug").unwrap();
+    assert_eq!(
+        selector.requirements(),
+        &[
+            Requirement::Equal("tier".into(), "background".into()),
+            Requirement::Equal("app".into(), "hello".into()),
+            Requirement::NotEqual("zone".into(), "a".into()),
+            Requirement::Exists("gpu".into()),
+            Requirement::NotExists("debug".into()),
+        ]
+    );
+    assert_eq!(
+        selector.to_string(),
+        "tier=background,app=hello,zone!=a,gpu,!debug"
+    );
+}
+
+#[test]
+fn parse_invalid() {
+    assert!(Selector::from_str("").is_err());
+    assert!(Selector::from_str(" ").is_err());
+    assert!(Selector::from_str("=background").is_err());
+    assert!(Selector::from_str("tier=back ground").is_err());
+    assert!(Selector::from_str("tier=background,").is_err());
+    assert!(Selector::from_str("!").is_err());
+}
+
+#[test]
+#[allow(clippy::unwrap_used)]
+fn matches() {
+    let l = labels(&[("tier", "background"), ("app", "hello")]);
+    assert!(Selector::from_str("tier=background").unwrap().matches(&l));
+    assert!(Selector::from_str("tier=background,app")
+        .unwrap()
+        .matches(&l));
+    assert!(Selector::from_str("zone!=a,!debug").unwrap().matches(&l));
+    assert!(!Selector::from_str("tier=foreground").unwrap().matches(&l));
+    assert!(!Selector::from_str("tier!=background").unwrap().matches(&l));
+    assert!(!Selector::from_str("tier=background,debug")
+        .unwrap()
+        .matches(&l));
+}
+
+#[test]
+#[allow(clippy::unwrap_used)]
+fn serde() {
+    let selector = Selector::from_str("tier=background,!debug").unwrap();
+    let json = serde_json::to_string(&selector).unwrap();
+    assert_eq!(json, "\"tier=background,!debug\"");
+    assert_eq!(serde_json::from_str::<Selector>(&json).unwrap(), selector);
+    assert!(serde_json::from_str::<Selector>("\"tier=,\"").is_err());
+    assert!(serde_json::from_str::<Selector>("\"\"").is_err());
+}
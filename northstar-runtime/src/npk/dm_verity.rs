@@ -122,12 +122,31 @@ impl VerityHeader {
 /// and a dm-verity hash_tree
 /// <https://gitlab.com/cryptsetup/cryptsetup/-/wikis/DMVerity#hash-tree>
 /// to the given file.
-pub fn append_dm_verity_block(fsimg: &Path, fsimg_size: u64) -> Result<Sha256Digest> {
+/// If `reproducible` is set, the salt and uuid are derived from the image content
+/// instead of being random.
+pub fn append_dm_verity_block(
+    fsimg: &Path,
+    fsimg_size: u64,
+    reproducible: bool,
+) -> Result<Sha256Digest> {
     let (level_offsets, tree_size) =
         calculate_hash_tree_level_offsets(fsimg_size as usize, BLOCK_SIZE, SHA256_SIZE as usize);
-    let (salt, root_hash, hash_tree) =
-        generate_hash_tree(fsimg, fsimg_size, &level_offsets, tree_size)?;
-    append_superblock_and_hashtree(fsimg, fsimg_size, &salt, &hash_tree)?;
+    let salt = if reproducible {
+        content_salt(fsimg)?
+    } else {
+        generate_salt()
+    };
+    let (root_hash, hash_tree) =
+        generate_hash_tree(fsimg, fsimg_size, &salt, &level_offsets, tree_size)?;
+    let uuid = if reproducible {
+        // Use the first bytes of the salt as uuid
+        let mut bytes = [0u8; 16];
+        bytes.copy_from_slice(&salt[..16]);
+        *uuid::Builder::from_random_bytes(bytes).as_uuid().as_bytes()
+    } else {
+        generate_uuid()?
+    };
+    append_superblock_and_hashtree(fsimg, fsimg_size, &uuid, &salt, &hash_tree)?;
     Ok(root_hash)
 }
 
@@ -137,6 +156,25 @@ fn generate_salt() -> Salt {
     salt
 }
 
+/// Salt derived from the SHA256 digest of the image
+fn content_salt(fsimg: &Path) -> Result<Salt> {
+    let mut fsimg = std::fs::File::open(&fsimg)
+        .with_context(|| format!("failed to open {}", &fsimg.display()))?;
+    let mut sha256 = Sha256::new();
+    io::copy(&mut fsimg, &mut sha256).context("failed to read fs-image")?;
+    Ok(sha256.finalize().into())
+}
+
+fn generate_uuid() -> Result<[u8; 16]> {
+    let mut uuid = [0u8; 16];
+    uuid.copy_from_slice(
+        hex::decode(Uuid::new_v4().to_string().replace('-', ""))
+            .context("failed to create valid uuid")?
+            .as_slice(),
+    );
+    Ok(uuid)
+}
+
 fn calculate_hash_tree_level_offsets(
     image_size: usize,
     block_size: usize,
@@ -172,9 +210,10 @@ fn calculate_hash_tree_level_offsets(
 fn generate_hash_tree(
     fsimg: &Path,
     image_size: u64,
+    salt: &Salt,
     level_offsets: &[usize],
     tree_size: usize,
-) -> Result<(Salt, Sha256Digest, Vec<u8>)> {
+) -> Result<(Sha256Digest, Vec<u8>)> {
     // For a description of the overall hash tree generation logic see
     // https://source.android.com/security/verifiedboot/dm-verity#hash-tree
 
@@ -192,7 +231,7 @@ fn generate_hash_tree(
     }
 
     // "1. Choose a random salt (hexadecimal encoding)."
-    let salt = generate_salt();
+    // The salt is passed in by the caller.
 
     // "To form the hash, the system image is split at layer 0 into 4k blocks, each assigned a SHA256 hash.
     // Layer 1 is formed by joining only those SHA256 hashes into 4k blocks, resulting in a much smaller image.
@@ -254,12 +293,13 @@ fn generate_hash_tree(
     // "The result of this is a single hash, which is your root hash.
     // This and your salt are used during the construction of your dm-verity mapping table."
     let root_hash = hashes[0];
-    Ok((salt, root_hash, hash_tree))
+    Ok((root_hash, hash_tree))
 }
 
 fn append_superblock_and_hashtree(
     fsimg: &Path,
     fsimg_size: u64,
+    uuid: &[u8; 16],
     salt: &Salt,
     hash_tree: &[u8],
 ) -> Result<()> {
@@ -268,15 +308,9 @@ fn append_superblock_and_hashtree(
         .append(true)
         .open(&fsimg)
         .with_context(|| format!("failed to open {}", &fsimg.display()))?;
-    let mut uuid = [0u8; 16];
-    uuid.copy_from_slice(
-        hex::decode(Uuid::new_v4().to_string().replace('-', ""))
-            .context("failed to create valid uuid")?
-            .as_slice(),
-    );
     assert_eq!(fsimg_size % BLOCK_SIZE as u64, 0);
     let data_blocks = fsimg_size / BLOCK_SIZE as u64;
-    let header = VerityHeader::new(&uuid, data_blocks, SHA256_SIZE as u16, salt).to_bytes();
+    let header = VerityHeader::new(uuid, data_blocks, SHA256_SIZE as u16, salt).to_bytes();
     fsimg
         .write_all(&header)
         .context("failed to write verity header")?;
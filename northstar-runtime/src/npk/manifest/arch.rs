@@ -0,0 +1,94 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// CPU architecture the container binaries are built for
+#[derive(Clone, Copy, Eq, PartialEq, PartialOrd, Ord, Debug, Hash, Serialize, Deserialize)]
+#[allow(missing_docs)]
+pub enum Arch {
+    #[serde(rename = "x86")]
+    X86,
+    #[serde(rename = "x86_64")]
+    X86_64,
+    #[serde(rename = "arm")]
+    Arm,
+    #[serde(rename = "aarch64")]
+    Aarch64,
+    #[serde(rename = "mips")]
+    Mips,
+    #[serde(rename = "mips64")]
+    Mips64,
+    #[serde(rename = "powerpc")]
+    Powerpc,
+    #[serde(rename = "powerpc64")]
+    Powerpc64,
+    #[serde(rename = "riscv64")]
+    Riscv64,
+    #[serde(rename = "s390x")]
+    S390x,
+}
+
+impl Arch {
+    /// Architecture this binary is compiled for. None if the architecture is not
+    /// covered by `Arch`.
+    pub fn host() -> Option<Arch> {
+        match std::env::consts::ARCH {
+            "x86" => Some(Arch::X86),
+            "x86_64" => Some(Arch::X86_64),
+            "arm" => Some(Arch::Arm),
+            "aarch64" => Some(Arch::Aarch64),
+            "mips" => Some(Arch::Mips),
+            "mips64" => Some(Arch::Mips64),
+            "powerpc" => Some(Arch::Powerpc),
+            "powerpc64" => Some(Arch::Powerpc64),
+            "riscv64" => Some(Arch::Riscv64),
+            "s390x" => Some(Arch::S390x),
+            _ => None,
+        }
+    }
+
+    /// Returns true if binaries built for `self` run on `host`. Besides an exact match
+    /// 32 bit x86 and arm binaries are accepted on x86_64 and aarch64 hosts. Those require
+    /// a kernel with 32 bit compat support and on aarch64 a CPU that implements AArch32.
+    pub fn runs_on(self, host: Arch) -> bool {
+        matches!(
+            (self, host),
+            (Arch::X86, Arch::X86_64) | (Arch::Arm, Arch::Aarch64)
+        ) || self == host
+    }
+
+    /// Architecture of an ELF file identified by the `e_machine` field and the
+    /// file class (`ELFCLASS64`) of the ELF header.
+    pub fn from_elf(machine: u16, class64: bool) -> Option<Arch> {
+        match (machine, class64) {
+            (3, false) => Some(Arch::X86),
+            (62, true) => Some(Arch::X86_64),
+            (40, false) => Some(Arch::Arm),
+            (183, true) => Some(Arch::Aarch64),
+            (8, false) => Some(Arch::Mips),
+            (8, true) => Some(Arch::Mips64),
+            (20, false) => Some(Arch::Powerpc),
+            (21, true) => Some(Arch::Powerpc64),
+            (243, true) => Some(Arch::Riscv64),
+            (22, true) => Some(Arch::S390x),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Arch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let arch = match self {
+            Arch::X86 => "x86",
+            Arch::X86_64 => "x86_64",
+            Arch::Arm => "arm",
+            Arch::Aarch64 => "aarch64",
+            Arch::Mips => "mips",
+            Arch::Mips64 => "mips64",
+            Arch::Powerpc => "powerpc",
+            Arch::Powerpc64 => "powerpc64",
+            Arch::Riscv64 => "riscv64",
+            Arch::S390x => "s390x",
+        };
+        write!(f, "{}", arch)
+    }
+}
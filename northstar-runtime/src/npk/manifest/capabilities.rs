@@ -1,7 +1,7 @@
 use serde::{Deserialize, Serialize};
 
 /// Linux capability
-#[derive(Clone, Eq, Hash, PartialEq, Debug, Serialize, Deserialize)]
+#[derive(Clone, Eq, Hash, PartialEq, PartialOrd, Ord, Debug, Serialize, Deserialize)]
 #[allow(non_camel_case_types)]
 pub enum Capability {
     /// `CAP_CHOWN` (from POSIX)
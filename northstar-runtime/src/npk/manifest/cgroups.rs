@@ -1,3 +1,4 @@
+use crate::common::ordered;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -10,6 +11,10 @@ pub struct CGroups {
     pub cpu: Option<CpuResources>,
     /// Memory controller
     pub memory: Option<MemoryResources>,
+    /// Delegate the cgroup of the container to the container. The container can
+    /// create child cgroups and distribute its resources. Requires cgroup v2.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub delegate: bool,
 }
 
 /// Bkio device resource
@@ -78,7 +83,11 @@ pub struct CpuResources {
     /// This is currently a no-operation.
     pub realtime_period: Option<u64>,
     /// Customized key-value attributes
-    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    #[serde(
+        default,
+        skip_serializing_if = "HashMap::is_empty",
+        serialize_with = "ordered::map"
+    )]
     pub attrs: HashMap<String, String>,
 }
 
@@ -103,6 +112,10 @@ pub struct MemoryResources {
     /// traditional `mlock(2)` system call for that purpose.
     pub swappiness: Option<u64>,
     /// Customized key-value attributes
-    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    #[serde(
+        default,
+        skip_serializing_if = "HashMap::is_empty",
+        serialize_with = "ordered::map"
+    )]
     pub attrs: HashMap<String, String>,
 }
@@ -27,9 +27,13 @@ pub struct Configuration {
 }
 
 /// Console features. Matches the api request struct and notifications
-#[derive(Clone, Eq, EnumIter, EnumCount, PartialEq, Debug, Hash, Serialize, Deserialize)]
+#[derive(
+    Clone, Eq, EnumIter, EnumCount, PartialEq, PartialOrd, Ord, Debug, Hash, Serialize, Deserialize,
+)]
 #[serde(rename_all = "snake_case")]
 pub enum Permission {
+    /// Attach to the terminal of a container and resize it
+    Attach,
     /// Identification
     Ident,
     /// Inspect a container
@@ -154,7 +158,7 @@ impl Serialize for Permissions {
             serializer.serialize_str("full")
         } else {
             let mut seq = serializer.serialize_seq(Some(self.0.len()))?;
-            for e in self.0.iter() {
+            for e in self.0.iter().sorted() {
                 seq.serialize_element(e)?;
             }
             seq.end()
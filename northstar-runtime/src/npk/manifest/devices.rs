@@ -0,0 +1,110 @@
+use crate::common::non_nul_string::NonNulString;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::{fmt, str::FromStr};
+
+/// Device node created in the container. Access to the device is granted via the
+/// device cgroup of the container.
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Device {
+    /// Path of the device node, e.g. /dev/ttyS1. The path must be beneath a `dev` mount.
+    pub path: NonNulString,
+    /// Device type
+    pub r#type: DeviceType,
+    /// Major number
+    pub major: u32,
+    /// Minor number
+    pub minor: u32,
+    /// Access permissions, e.g. `rw`. Default: `rw`
+    #[serde(default)]
+    pub permissions: Permissions,
+}
+
+/// Device type
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeviceType {
+    /// Character device
+    Char,
+    /// Block device
+    Block,
+}
+
+/// Device access permissions
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Hash)]
+pub struct Permissions {
+    /// Read from the device (`r`)
+    pub read: bool,
+    /// Write to the device (`w`)
+    pub write: bool,
+    /// Create a device node with `mknod` (`m`)
+    pub mknod: bool,
+}
+
+impl Default for Permissions {
+    fn default() -> Self {
+        Permissions {
+            read: true,
+            write: true,
+            mknod: false,
+        }
+    }
+}
+
+impl FromStr for Permissions {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut permissions = Permissions {
+            read: false,
+            write: false,
+            mknod: false,
+        };
+        for c in s.chars() {
+            let permission = match c {
+                'r' => &mut permissions.read,
+                'w' => &mut permissions.write,
+                'm' => &mut permissions.mknod,
+                _ => return Err(format!("invalid device permissions {}", s)),
+            };
+            if *permission {
+                return Err(format!("invalid device permissions {}", s));
+            }
+            *permission = true;
+        }
+        if s.is_empty() {
+            return Err("empty device permissions".into());
+        }
+        Ok(permissions)
+    }
+}
+
+impl fmt::Display for Permissions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (set, c) in [(self.read, 'r'), (self.write, 'w'), (self.mknod, 'm')] {
+            if set {
+                write!(f, "{}", c)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Serialize for Permissions {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Permissions {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        Permissions::from_str(&s).map_err(serde::de::Error::custom)
+    }
+}
@@ -0,0 +1,45 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// File in `/etc` that is generated by the runtime and mounted read only into the container
+#[derive(Clone, Copy, Eq, PartialEq, PartialOrd, Ord, Debug, Hash, Serialize, Deserialize)]
+pub enum File {
+    /// Hosts file with `localhost` and the hostname of the container
+    #[serde(rename = "hosts")]
+    Hosts,
+    /// Copy of the resolver configuration of the host
+    #[serde(rename = "resolv.conf")]
+    ResolvConf,
+    /// User database with root and the container user
+    #[serde(rename = "passwd")]
+    Passwd,
+    /// Group database with root, the container group and the supplementary groups
+    #[serde(rename = "group")]
+    Group,
+}
+
+impl File {
+    /// All generated files
+    pub const ALL: [File; 4] = [File::Hosts, File::ResolvConf, File::Passwd, File::Group];
+
+    /// Name of the file in `/etc`
+    pub fn name(&self) -> &'static str {
+        match self {
+            File::Hosts => "hosts",
+            File::ResolvConf => "resolv.conf",
+            File::Passwd => "passwd",
+            File::Group => "group",
+        }
+    }
+
+    /// Absolute path of the file in the container
+    pub fn path(&self) -> String {
+        format!("/etc/{}", self.name())
+    }
+}
+
+impl fmt::Display for File {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
@@ -1,4 +1,9 @@
-use serde::{Deserialize, Serialize};
+use crate::common::non_nul_string::NonNulString;
+use serde::{
+    de::{self, EnumAccess, MapAccess, VariantAccess, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use std::fmt;
 
 /// IO configuration for stdin, stdout, stderr
 #[derive(Default, Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
@@ -8,24 +13,142 @@ pub struct Io {
     pub stdout: Output,
     /// stderr configuration
     pub stderr: Output,
+    /// Allocate a pseudo terminal for stdin, stdout and stderr. The output of the
+    /// terminal is forwarded according to the stdout configuration
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub tty: bool,
 }
 
-/// Io redirection for stdout/stderr
-#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+/// Io redirection for stdout/stderr. `pipe` and `syslog` take optional settings:
+/// ```yaml
+/// stdout: pipe
+/// stderr: !syslog
+///   level: warn
+///   tag: hello
+/// ```
+#[derive(Clone, Default, Eq, PartialEq, Debug)]
 pub enum Output {
     /// Discard output
-    #[serde(rename = "discard")]
+    #[default]
     Discard,
     /// Forward output to the logging system with level and optional tag
-    #[serde(rename = "pipe")]
-    Pipe,
+    Pipe(Log),
     /// Inherit stdout/stderr from the runtime
-    #[serde(rename = "inherit")]
     Inherit,
+    /// Forward output to the syslog socket configured in the runtime
+    Syslog(Log),
 }
 
-impl Default for Output {
-    fn default() -> Output {
-        Output::Discard
+/// Settings of forwarded lines
+#[derive(Default, Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Log {
+    /// Level or severity of the lines. Default: debug
+    #[serde(default)]
+    pub level: Level,
+    /// Log target or syslog identifier. Default: the container name
+    pub tag: Option<NonNulString>,
+}
+
+/// Log level
+#[derive(
+    Clone, Copy, Default, Eq, PartialEq, PartialOrd, Ord, Debug, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Level {
+    /// Error
+    Error,
+    /// Warning
+    Warn,
+    /// Info
+    Info,
+    /// Debug
+    #[default]
+    Debug,
+    /// Trace
+    Trace,
+}
+
+impl fmt::Display for Output {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Output::Discard => f.write_str("discard"),
+            Output::Pipe(_) => f.write_str("pipe"),
+            Output::Inherit => f.write_str("inherit"),
+            Output::Syslog(_) => f.write_str("syslog"),
+        }
+    }
+}
+
+const VARIANTS: &[&str] = &["discard", "pipe", "inherit", "syslog"];
+
+/// Outputs with default settings are serialized as plain names
+impl Serialize for Output {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Output::Discard => serializer.serialize_unit_variant("Output", 0, "discard"),
+            Output::Pipe(log) if *log == Log::default() => {
+                serializer.serialize_unit_variant("Output", 1, "pipe")
+            }
+            Output::Pipe(log) => serializer.serialize_newtype_variant("Output", 1, "pipe", log),
+            Output::Inherit => serializer.serialize_unit_variant("Output", 2, "inherit"),
+            Output::Syslog(log) if *log == Log::default() => {
+                serializer.serialize_unit_variant("Output", 3, "syslog")
+            }
+            Output::Syslog(log) => serializer.serialize_newtype_variant("Output", 3, "syslog", log),
+        }
+    }
+}
+
+/// Accept plain names, tagged values and single key maps
+impl<'de> Deserialize<'de> for Output {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Output, D::Error> {
+        struct OutputVisitor;
+
+        impl<'de> Visitor<'de> for OutputVisitor {
+            type Value = Output;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("discard, pipe, inherit or syslog with optional settings")
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Output, E> {
+                match v {
+                    "discard" => Ok(Output::Discard),
+                    "pipe" => Ok(Output::Pipe(Log::default())),
+                    "inherit" => Ok(Output::Inherit),
+                    "syslog" => Ok(Output::Syslog(Log::default())),
+                    _ => Err(E::unknown_variant(v, VARIANTS)),
+                }
+            }
+
+            fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<Output, A::Error> {
+                let (variant, access) = data.variant::<String>()?;
+                match variant.as_str() {
+                    "discard" => access.unit_variant().map(|_| Output::Discard),
+                    "pipe" => access.newtype_variant().map(Output::Pipe),
+                    "inherit" => access.unit_variant().map(|_| Output::Inherit),
+                    "syslog" => access.newtype_variant().map(Output::Syslog),
+                    v => Err(de::Error::unknown_variant(v, VARIANTS)),
+                }
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Output, A::Error> {
+                let variant = map
+                    .next_key::<String>()?
+                    .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+                let output = match variant.as_str() {
+                    "pipe" => Output::Pipe(map.next_value()?),
+                    "syslog" => Output::Syslog(map.next_value()?),
+                    v => return Err(de::Error::unknown_variant(v, &["pipe", "syslog"])),
+                };
+                if map.next_key::<String>()?.is_some() {
+                    return Err(de::Error::invalid_length(2, &self));
+                }
+                Ok(output)
+            }
+        }
+
+        deserializer.deserialize_any(OutputVisitor)
     }
 }
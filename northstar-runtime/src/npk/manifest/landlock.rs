@@ -0,0 +1,72 @@
+use crate::common::{non_nul_string::NonNulString, ordered};
+use serde::{Deserialize, Serialize};
+use serde_with::rust::maps_duplicate_key_is_error;
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+/// Landlock configuration. Filesystem access of the container is restricted to the
+/// listed paths and access rights. Paths are resolved within the container root.
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Landlock {
+    /// Refuse to start the container if Landlock or one of the requested access rights is not
+    /// supported by the kernel. Otherwise the restrictions are applied as far as supported.
+    /// Default: false
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub strict: bool,
+    /// Access rights per path. The rights apply to the path and everything beneath it.
+    #[serde(
+        serialize_with = "serialize_paths",
+        deserialize_with = "maps_duplicate_key_is_error::deserialize"
+    )]
+    pub paths: HashMap<NonNulString, HashSet<Access>>,
+}
+
+/// Filesystem access right
+#[derive(Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Debug, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Access {
+    /// Read files and list directories
+    Read,
+    /// Write files
+    Write,
+    /// Execute files
+    Execute,
+    /// Read files
+    ReadFile,
+    /// List directories
+    ReadDir,
+    /// Remove directories
+    RemoveDir,
+    /// Remove files
+    RemoveFile,
+    /// Create character devices
+    MakeChar,
+    /// Create directories
+    MakeDir,
+    /// Create regular files
+    MakeReg,
+    /// Create unix sockets
+    MakeSock,
+    /// Create named pipes
+    MakeFifo,
+    /// Create block devices
+    MakeBlock,
+    /// Create symbolic links
+    MakeSym,
+    /// Link or rename files to a different directory. Requires Landlock ABI 2.
+    Refer,
+    /// Truncate files. Requires Landlock ABI 3.
+    Truncate,
+}
+
+/// Serialize the paths sorted by name and their access rights sorted
+fn serialize_paths<S: serde::Serializer>(
+    paths: &HashMap<NonNulString, HashSet<Access>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let paths = paths
+        .iter()
+        .map(|(path, access)| (path, access.iter().collect::<BTreeSet<_>>()))
+        .collect::<HashMap<_, _>>();
+    ordered::map(&paths, serializer)
+}
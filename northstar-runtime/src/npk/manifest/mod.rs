@@ -1,5 +1,8 @@
 use crate::{
-    common::{container::Container, name::Name, non_nul_string::NonNulString, version::Version},
+    common::{
+        container::Container, name::Name, non_nul_string::NonNulString, ordered, selector::Labels,
+        version::Version,
+    },
     seccomp::Seccomp,
 };
 use serde::{Deserialize, Serialize};
@@ -11,12 +14,15 @@ use serde_with::{
 use std::{
     collections::{HashMap, HashSet},
     str::FromStr,
+    time::Duration,
 };
 use thiserror::Error;
 use validator::{Validate, ValidationErrors};
 
 use self::network::Network;
 
+/// CPU architecture
+pub mod arch;
 /// Autostart
 pub mod autostart;
 /// Linux capabilities
@@ -25,8 +31,14 @@ pub mod capabilities;
 pub mod cgroups;
 /// Northstar console configuration
 pub mod console;
+/// Device nodes
+pub mod devices;
+/// Generated /etc files
+pub mod etc;
 /// Container io
 pub mod io;
+/// Landlock filesystem access restrictions
+pub mod landlock;
 /// Container mounts
 pub mod mount;
 /// Networking
@@ -35,8 +47,10 @@ pub mod network;
 pub mod rlimit;
 /// SE Linux
 pub mod selinux;
+/// File mode creation mask
+pub mod umask;
 
-mod validation;
+pub(crate) mod validation;
 
 /// Manifest parsing error
 #[derive(Error, Debug)]
@@ -60,6 +74,11 @@ pub struct Manifest {
     pub name: Name,
     /// Container version
     pub version: Version,
+    /// CPU architecture of the container binaries. The runtime refuses to install and
+    /// start containers built for a different architecture.
+    pub arch: Option<arch::Arch>,
+    /// Minimum runtime version required by this container
+    pub min_runtime_version: Option<Version>,
     /// Pass a console fd number in NORTHSTAR_CONSOLE
     pub console: Option<console::Configuration>,
     /// Path to init
@@ -69,7 +88,11 @@ pub struct Manifest {
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub args: Vec<NonNulString>,
     /// Environment passed to container
-    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    #[serde(
+        default,
+        skip_serializing_if = "HashMap::is_empty",
+        serialize_with = "ordered::map"
+    )]
     #[validate(custom = "validation::env")]
     pub env: HashMap<NonNulString, NonNulString>,
     /// UID
@@ -78,31 +101,75 @@ pub struct Manifest {
     /// GID
     #[validate(range(min = 1, message = "gid must be greater than 0"))]
     pub gid: u16,
+    /// Working directory of the container process. Default: /
+    #[validate(custom = "validation::cwd")]
+    pub cwd: Option<NonNulString>,
+    /// File mode creation mask of the container process in octal notation, e.g. "0027"
+    pub umask: Option<umask::Umask>,
     /// List of bind mounts and resources
     #[serde(
         default,
         skip_serializing_if = "HashMap::is_empty",
+        serialize_with = "ordered::map",
         deserialize_with = "maps_duplicate_key_is_error::deserialize"
     )]
     #[validate(custom = "validation::mounts")]
     pub mounts: HashMap<mount::MountPoint, mount::Mount>,
+    /// Writable overlay over the root filesystem
+    pub overlay: Option<mount::Overlay>,
     /// Autostart this container upon northstar startup
     pub autostart: Option<autostart::Autostart>,
+    /// Maximum runtime after which the runtime stops the container, e.g. "10m"
+    #[serde(default, with = "humantime_serde")]
+    #[validate(custom = "validation::max_runtime")]
+    pub max_runtime: Option<Duration>,
     /// CGroup configuration
     pub cgroups: Option<self::cgroups::CGroups>,
+    /// Run the container in a user namespace with the ids mapped to a range of host
+    /// ids configured in the runtime. Default: false
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub user_namespace: bool,
+    /// Run the container in a PID namespace with init as pid 1. Default: true
+    #[serde(default = "pid_namespace_default", skip_serializing_if = "is_true")]
+    pub pid_namespace: bool,
+    /// Set the no new privileges flag of the container process. Default: true
+    #[serde(default = "no_new_privs_default", skip_serializing_if = "is_true")]
+    pub no_new_privs: bool,
     /// Network configuration. Unshare the network if omitted.
     #[validate(custom = "validation::network")]
     pub network: Option<Network>,
+    /// Hostname set in the UTS namespace of the container
+    #[validate(custom = "validation::hostname")]
+    pub hostname: Option<NonNulString>,
+    /// NIS domain name set in the UTS namespace of the container
+    #[validate(custom = "validation::hostname")]
+    pub domainname: Option<NonNulString>,
+    /// Files generated by the runtime and mounted read only into `/etc`
+    #[serde(
+        default,
+        skip_serializing_if = "HashSet::is_empty",
+        serialize_with = "ordered::set",
+        deserialize_with = "sets_duplicate_value_is_error::deserialize"
+    )]
+    pub etc: HashSet<etc::File>,
     /// Seccomp configuration
     #[validate(custom = "validation::seccomp")]
     pub seccomp: Option<Seccomp>,
     /// SELinux configuration
     #[validate(custom = "validation::selinux")]
     pub selinux: Option<selinux::Selinux>,
+    /// Landlock configuration
+    #[validate(custom = "validation::landlock")]
+    pub landlock: Option<landlock::Landlock>,
+    /// Device nodes created in the container. Access to other devices than the ones of
+    /// the `dev` mount and this list is denied if the list is not empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub devices: Vec<devices::Device>,
     /// Capabilities
     #[serde(
         default,
         skip_serializing_if = "HashSet::is_empty",
+        serialize_with = "ordered::set",
         deserialize_with = "sets_duplicate_value_is_error::deserialize"
     )]
     pub capabilities: HashSet<capabilities::Capability>,
@@ -110,6 +177,7 @@ pub struct Manifest {
     #[serde(
         default,
         skip_serializing_if = "HashSet::is_empty",
+        serialize_with = "ordered::set",
         deserialize_with = "sets_duplicate_value_is_error::deserialize"
     )]
     #[validate(custom = "validation::suppl_groups")]
@@ -118,16 +186,47 @@ pub struct Manifest {
     #[serde(
         default,
         skip_serializing_if = "HashMap::is_empty",
+        serialize_with = "ordered::map",
         deserialize_with = "maps_duplicate_key_is_error::deserialize"
     )]
     pub rlimits: HashMap<rlimit::RLimitResource, rlimit::RLimitValue>,
+    /// Kernel parameters of the namespaces of the container, e.g. `net.ipv4.ip_forward`
+    #[serde(
+        default,
+        skip_serializing_if = "HashMap::is_empty",
+        serialize_with = "ordered::map",
+        deserialize_with = "maps_duplicate_key_is_error::deserialize"
+    )]
+    #[validate(custom = "validation::sysctl")]
+    pub sysctl: HashMap<NonNulString, NonNulString>,
     /// IO configuration
     #[serde(default)]
     pub io: Option<io::Io>,
+    /// Labels used to select groups of containers in console requests
+    #[serde(
+        default,
+        skip_serializing_if = "HashMap::is_empty",
+        serialize_with = "ordered::map",
+        deserialize_with = "maps_duplicate_key_is_error::deserialize"
+    )]
+    #[validate(custom = "validation::labels")]
+    pub labels: Labels,
     /// Optional custom data. The runtime doesn't use this.
     pub custom: Option<Value>,
 }
 
+const fn pid_namespace_default() -> bool {
+    true
+}
+
+const fn no_new_privs_default() -> bool {
+    true
+}
+
+fn is_true(value: &bool) -> bool {
+    *value
+}
+
 impl Manifest {
     /// Container that is specified in the manifest
     pub fn container(&self) -> Container {
@@ -171,8 +270,12 @@ impl ToString for Manifest {
 #[cfg(test)]
 #[allow(clippy::unwrap_used)]
 mod tests {
-    use super::mount::*;
-    use crate::{common::version::VersionReq, npk::manifest::*, seccomp::SyscallRule};
+    use super::{etc, io, mount::*, network::Network};
+    use crate::{
+        common::version::VersionReq,
+        npk::manifest::*,
+        seccomp::{Action, Profile, SyscallArgCondition, SyscallArgOp, SyscallRule},
+    };
     use anyhow::Result;
     use std::{
         convert::{TryFrom, TryInto},
@@ -265,7 +368,7 @@ cgroups:
                 options: [MountOption::Rw].iter().cloned().collect(),
             }),
         );
-        mounts.insert(nn("/data"), Mount::Persist);
+        mounts.insert(nn("/data"), Mount::Persist(Persist::default()));
         mounts.insert(
             nn("/resource"),
             Mount::Resource(Resource {
@@ -291,8 +394,8 @@ cgroups:
         assert_eq!(
             manifest.seccomp,
             Some(Seccomp {
-                profile: None,
-                allow: Some(syscalls)
+                allow: Some(syscalls),
+                ..Default::default()
             })
         );
 
@@ -334,12 +437,256 @@ cgroups:
         Ok(())
     }
 
+    /// Network configuration
+    #[test]
+    fn network() -> Result<()> {
+        let manifest = "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1\ngid: 1";
+        assert_eq!(Manifest::from_str(manifest)?.network, None);
+        let manifest = "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1\ngid: 1\nnetwork: host";
+        assert_eq!(Manifest::from_str(manifest)?.network, Some(Network::Host));
+        let manifest =
+            "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1\ngid: 1\nnetwork: bridge";
+        assert_eq!(Manifest::from_str(manifest)?.network, Some(Network::Bridge));
+        let manifest =
+            "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1\ngid: 1\nnetwork: !namespace foo";
+        assert_eq!(
+            Manifest::from_str(manifest)?.network,
+            Some(Network::Namespace("foo".try_into()?))
+        );
+        Ok(())
+    }
+
+    /// Seccomp argument conditions
+    #[test]
+    fn seccomp_conditions() -> Result<()> {
+        let manifest = "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1\ngid: 1
+seccomp:
+  allow:
+    mmap: !all_of
+      - index: 2
+        op: masked_eq
+        mask: 4
+        value: 0
+      - index: 1
+        op: le
+        value: 4096
+    socket: !any_of
+      - index: 0
+        op: eq
+        value: 1
+";
+        let manifest = Manifest::from_str(manifest)?;
+        let allow = manifest.seccomp.as_ref().unwrap().allow.as_ref().unwrap();
+        assert_eq!(
+            allow.get(&"mmap".try_into()?),
+            Some(&SyscallRule::AllOf(vec![
+                SyscallArgCondition {
+                    index: 2,
+                    op: SyscallArgOp::MaskedEq,
+                    value: 0,
+                    mask: Some(4),
+                },
+                SyscallArgCondition {
+                    index: 1,
+                    op: SyscallArgOp::Le,
+                    value: 4096,
+                    mask: None,
+                },
+            ]))
+        );
+        assert_eq!(Manifest::from_str(&manifest.to_string())?, manifest);
+
+        // Invalid conditions
+        for condition in [
+            "[]",
+            "[{index: 6, op: eq, value: 1}]",
+            "[{index: 0, op: masked_eq, value: 1}]",
+            "[{index: 0, op: eq, value: 1, mask: 1}]",
+        ] {
+            let manifest = format!(
+                "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1\ngid: 1\nseccomp:\n  allow:\n    mmap: !all_of {}",
+                condition
+            );
+            assert!(Manifest::from_str(&manifest).is_err(), "{}", condition);
+        }
+        Ok(())
+    }
+
+    /// Seccomp named profile, default action and deny rules
+    #[test]
+    fn seccomp_actions() -> Result<()> {
+        let manifest = "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1\ngid: 1
+seccomp:
+  profile: !named platform
+  default_action: !errno 1
+  deny:
+    ptrace: kill_thread
+";
+        let manifest = Manifest::from_str(manifest)?;
+        let seccomp = manifest.seccomp.as_ref().unwrap();
+        assert_eq!(
+            seccomp.profile,
+            Some(Profile::Named("platform".try_into()?))
+        );
+        assert_eq!(seccomp.default_action, Some(Action::Errno(1)));
+        assert_eq!(
+            seccomp.deny.as_ref().unwrap().get(&"ptrace".try_into()?),
+            Some(&Action::KillThread)
+        );
+        assert_eq!(Manifest::from_str(&manifest.to_string())?, manifest);
+
+        // Invalid errno values
+        for errno in [0, 4096] {
+            let manifest = format!(
+                "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1\ngid: 1\nseccomp:\n  default_action: !errno {}",
+                errno
+            );
+            assert!(Manifest::from_str(&manifest).is_err(), "{}", errno);
+        }
+        Ok(())
+    }
+
+    /// Landlock paths and access rights
+    #[test]
+    fn landlock() -> Result<()> {
+        let manifest = "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1\ngid: 1
+landlock:
+  strict: true
+  paths:
+    /:
+      - read
+      - execute
+    /data:
+      - write
+      - make_dir
+";
+        let manifest = Manifest::from_str(manifest)?;
+        let landlock = manifest.landlock.as_ref().unwrap();
+        assert!(landlock.strict);
+        assert_eq!(
+            landlock.paths.get(&"/data".try_into()?),
+            Some(&HashSet::from([
+                landlock::Access::Write,
+                landlock::Access::MakeDir
+            ]))
+        );
+        assert_eq!(Manifest::from_str(&manifest.to_string())?, manifest);
+
+        // Invalid paths
+        for paths in ["{data: [read]}", "{/data: []}"] {
+            let manifest = format!(
+                "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1\ngid: 1\nlandlock:\n  paths: {}",
+                paths
+            );
+            assert!(Manifest::from_str(&manifest).is_err(), "{}", paths);
+        }
+        Ok(())
+    }
+
+    /// Device nodes
+    #[test]
+    fn devices() -> Result<()> {
+        let manifest = "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1\ngid: 1
+mounts:
+  /dev:
+    type: dev
+devices:
+  - path: /dev/ttyS1
+    type: char
+    major: 4
+    minor: 65
+  - path: /dev/gpio/chip0
+    type: char
+    major: 254
+    minor: 0
+    permissions: rwm
+";
+        let manifest = Manifest::from_str(manifest)?;
+        assert_eq!(manifest.devices.len(), 2);
+        assert_eq!(manifest.devices[0].r#type, devices::DeviceType::Char);
+        assert_eq!(manifest.devices[0].permissions.to_string(), "rw");
+        assert!(manifest.devices[1].permissions.mknod);
+        assert_eq!(Manifest::from_str(&manifest.to_string())?, manifest);
+
+        // Invalid devices
+        for device in [
+            "{path: /ttyS1, type: char, major: 4, minor: 65}",
+            "{path: /dev, type: char, major: 4, minor: 65}",
+            "{path: /dev/../ttyS1, type: char, major: 4, minor: 65}",
+            "{path: /dev/ttyS1, type: char, major: 4, minor: 65, permissions: rx}",
+            "{path: /dev/ttyS1, type: char, major: 4, minor: 65, permissions: rr}",
+        ] {
+            let manifest = format!(
+                "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1\ngid: 1\nmounts:\n  /dev:\n    type: dev\ndevices: [{}]",
+                device
+            );
+            assert!(Manifest::from_str(&manifest).is_err(), "{}", device);
+        }
+        Ok(())
+    }
+
+    /// PID namespace is enabled by default and required by a user namespace
+    #[test]
+    fn pid_namespace() -> Result<()> {
+        let manifest = "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1\ngid: 1";
+        assert!(Manifest::from_str(manifest)?.pid_namespace);
+        let manifest =
+            "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1\ngid: 1\npid_namespace: false";
+        let manifest = Manifest::from_str(manifest)?;
+        assert!(!manifest.pid_namespace);
+        assert!(manifest.to_string().contains("pid_namespace: false"));
+        let manifest = "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1\ngid: 1\npid_namespace: false\nuser_namespace: true";
+        assert!(Manifest::from_str(manifest).is_err());
+        let manifest = "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1\ngid: 1\nuser_namespace: true\nsuppl_groups: [inet]";
+        assert!(Manifest::from_str(manifest).is_err());
+        Ok(())
+    }
+
     /// Invalid selinux context
     #[test]
     fn invalid_selinux_context() -> Result<()> {
         let manifest =
             "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1\ngid: 1\nselinux_context: fo@o";
         assert!(Manifest::from_str(manifest).is_err());
+
+        for context in [
+            "fo@o",
+            "user_u:object_r",
+            "user_u::type_t",
+            "user_u:object_r:type_t:",
+            "user_u:object_r:type_t:s0 c1",
+        ] {
+            let manifest = format!(
+                "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1\ngid: 1\nselinux:\n  context: \"{}\"",
+                context
+            );
+            assert!(Manifest::from_str(&manifest).is_err(), "{}", context);
+        }
+        Ok(())
+    }
+
+    /// Selinux mount and exec context
+    #[test]
+    fn selinux_exec_context() -> Result<()> {
+        let manifest = "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1\ngid: 1
+selinux:
+  context: system_u:object_r:container_file_t:s0:c1,c2
+  exec_context: system_u:system_r:container_t:s0-s0:c0.c1023
+";
+        let manifest = Manifest::from_str(manifest)?;
+        let selinux = manifest.selinux.as_ref().unwrap();
+        assert_eq!(
+            selinux.exec_context.as_deref(),
+            Some("system_u:system_r:container_t:s0-s0:c0.c1023")
+        );
+        assert_eq!(Manifest::from_str(&manifest.to_string())?, manifest);
+
+        let manifest = "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1\ngid: 1
+selinux:
+  context: system_u:object_r:container_file_t
+  exec_context: container_t
+";
+        assert!(Manifest::from_str(manifest).is_err());
         Ok(())
     }
 
@@ -509,6 +856,337 @@ mounts:
         assert!(Manifest::from_str(manifest).is_ok());
     }
 
+    /// Size limited persist mounts
+    #[test]
+    fn persist_size() -> Result<()> {
+        let manifest = "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1\ngid: 1
+mounts:
+  /data:
+    type: persist
+    size: 10MB
+  /log:
+    type: persist
+    size: 10000000";
+        let manifest = Manifest::from_str(manifest)?;
+        assert_eq!(
+            manifest.mounts.get(&nn("/data")),
+            Some(&Mount::Persist(Persist {
+                size: Some(10000000)
+            }))
+        );
+        assert_eq!(Manifest::from_str(&manifest.to_string())?, manifest);
+
+        let manifest = "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1\ngid: 1
+mounts:
+  /data:
+    type: persist
+    size: 10MB
+  /log:
+    type: persist";
+        assert!(Manifest::from_str(manifest).is_err());
+
+        let manifest = "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1\ngid: 1
+mounts:
+  /data:
+    type: persist
+    size: 0";
+        assert!(Manifest::from_str(manifest).is_err());
+        Ok(())
+    }
+
+    /// Named volume mounts
+    #[test]
+    fn volume() -> Result<()> {
+        let manifest = "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1\ngid: 1
+mounts:
+  /exchange:
+    type: volume
+    name: exchange
+    options: rw,noexec";
+        let manifest = Manifest::from_str(manifest)?;
+        assert_eq!(
+            manifest.mounts.get(&nn("/exchange")),
+            Some(&Mount::Volume(Volume {
+                name: "exchange".try_into()?,
+                options: [MountOption::Rw, MountOption::NoExec].into_iter().collect(),
+            }))
+        );
+        assert_eq!(Manifest::from_str(&manifest.to_string())?, manifest);
+
+        let manifest = "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1\ngid: 1
+mounts:
+  /exchange:
+    type: volume
+    name: exchange
+    options: rec";
+        assert!(Manifest::from_str(manifest).is_err());
+        Ok(())
+    }
+
+    /// Hostname, domain name and generated /etc files
+    #[test]
+    fn hostname_etc() -> Result<()> {
+        let manifest = "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1\ngid: 1
+hostname: hello
+domainname: example.com
+etc:
+  - hosts
+  - resolv.conf
+  - passwd
+  - group";
+        let manifest = Manifest::from_str(manifest)?;
+        assert_eq!(manifest.hostname, Some(nn("hello")));
+        assert_eq!(manifest.domainname, Some(nn("example.com")));
+        assert_eq!(manifest.etc, etc::File::ALL.into_iter().collect());
+        assert_eq!(Manifest::from_str(&manifest.to_string())?, manifest);
+
+        for hostname in [
+            "",
+            "-hello",
+            "hello-",
+            "hel_lo",
+            "hello..world",
+            &"a".repeat(65),
+        ] {
+            let manifest = format!(
+                "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1\ngid: 1\nhostname: \"{}\"",
+                hostname
+            );
+            assert!(Manifest::from_str(&manifest).is_err(), "{}", hostname);
+        }
+
+        let manifest = "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1\ngid: 1
+etc:
+  - shadow";
+        assert!(Manifest::from_str(manifest).is_err());
+        Ok(())
+    }
+
+    /// Sysctl settings
+    #[test]
+    fn sysctl() -> Result<()> {
+        let manifest = "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1\ngid: 1
+sysctl:
+  net.ipv4.ip_forward: 1
+  net.core.somaxconn: 1024
+  kernel.msgmax: 65536
+  fs.mqueue.msg_max: 100";
+        let manifest = Manifest::from_str(manifest)?;
+        assert_eq!(manifest.sysctl.len(), 4);
+        assert_eq!(
+            manifest.sysctl.get(&nn("net.core.somaxconn")),
+            Some(&nn("1024"))
+        );
+        assert_eq!(Manifest::from_str(&manifest.to_string())?, manifest);
+
+        for key in [
+            "kernel.hostname",
+            "vm.swappiness",
+            "net",
+            "net..ipv4",
+            "net.ipv4/../../x",
+        ] {
+            let manifest = format!(
+                "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1\ngid: 1\nsysctl:\n  {}: 1",
+                key
+            );
+            assert!(Manifest::from_str(&manifest).is_err(), "{}", key);
+        }
+
+        // The network namespace of the host and named network namespaces cannot be tuned
+        for network in ["host", "!namespace foo"] {
+            let manifest = format!(
+                "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1\ngid: 1\nnetwork: {}\nsysctl:\n  net.ipv4.ip_forward: 1",
+                network
+            );
+            assert!(Manifest::from_str(&manifest).is_err(), "{}", network);
+        }
+
+        // The network namespace of a bridged container is owned by the container
+        let manifest = "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1\ngid: 1
+network: bridge
+sysctl:
+  net.ipv4.ip_forward: 1";
+        assert!(Manifest::from_str(manifest).is_ok());
+        Ok(())
+    }
+
+    /// Working directory, umask and no new privileges
+    #[test]
+    fn cwd_umask_no_new_privs() -> Result<()> {
+        let manifest = "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1\ngid: 1
+cwd: /opt/hello
+umask: '0027'
+no_new_privs: false";
+        let manifest = Manifest::from_str(manifest)?;
+        assert_eq!(manifest.cwd, Some(nn("/opt/hello")));
+        assert_eq!(manifest.umask.map(|umask| umask.bits()), Some(0o027));
+        assert!(!manifest.no_new_privs);
+        assert_eq!(Manifest::from_str(&manifest.to_string())?, manifest);
+
+        let manifest =
+            Manifest::from_str("name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1\ngid: 1")?;
+        assert!(manifest.no_new_privs);
+
+        for cwd in ["opt", "/opt/../etc", ""] {
+            let manifest = format!(
+                "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1\ngid: 1\ncwd: \"{}\"",
+                cwd
+            );
+            assert!(Manifest::from_str(&manifest).is_err(), "{}", cwd);
+        }
+
+        for umask in ["0778", "1777", "", "rwx", "-1"] {
+            let manifest = format!(
+                "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1\ngid: 1\numask: \"{}\"",
+                umask
+            );
+            assert!(Manifest::from_str(&manifest).is_err(), "{}", umask);
+        }
+
+        // Seccomp filters require no new privileges without CAP_SYS_ADMIN
+        let manifest = "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1\ngid: 1
+no_new_privs: false
+seccomp:
+  profile:
+    default";
+        assert!(Manifest::from_str(manifest).is_err());
+        let manifest = format!("{}\ncapabilities:\n  - CAP_SYS_ADMIN", manifest);
+        assert!(Manifest::from_str(&manifest).is_ok());
+        Ok(())
+    }
+
+    /// Maximum runtime
+    #[test]
+    fn max_runtime() -> Result<()> {
+        let manifest = "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1\ngid: 1
+max_runtime: 1h 30m";
+        let manifest = Manifest::from_str(manifest)?;
+        assert_eq!(manifest.max_runtime, Some(Duration::from_secs(5400)));
+        assert_eq!(Manifest::from_str(&manifest.to_string())?, manifest);
+
+        let manifest =
+            Manifest::from_str("name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1\ngid: 1")?;
+        assert!(manifest.max_runtime.is_none());
+
+        for max_runtime in ["0s", "10", "forever"] {
+            let manifest = format!(
+                "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1\ngid: 1\nmax_runtime: {}",
+                max_runtime
+            );
+            assert!(Manifest::from_str(&manifest).is_err(), "{}", max_runtime);
+        }
+
+        // Resource containers are not started
+        let manifest = "name: hello\nversion: 0.0.0\nuid: 1\ngid: 1\nmax_runtime: 10s";
+        assert!(Manifest::from_str(manifest).is_err());
+        Ok(())
+    }
+
+    /// Pseudo terminal
+    #[test]
+    fn tty() -> Result<()> {
+        let manifest = "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1\ngid: 1
+io:
+  stdout: inherit
+  stderr: discard
+  tty: true";
+        let manifest = Manifest::from_str(manifest)?;
+        let io = manifest.io.clone().expect("missing io");
+        assert!(io.tty);
+        assert_eq!(io.stdout, io::Output::Inherit);
+        assert_eq!(Manifest::from_str(&manifest.to_string())?, manifest);
+
+        let manifest = "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1\ngid: 1
+io:
+  stdout: pipe
+  stderr: pipe";
+        assert!(!Manifest::from_str(manifest)?.io.expect("missing io").tty);
+        Ok(())
+    }
+
+    /// Output with level and tag
+    #[test]
+    fn output() -> Result<()> {
+        let manifest = "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1\ngid: 1
+io:
+  stdout: !pipe
+    level: info
+    tag: hello-out
+  stderr: !syslog
+    level: warn";
+        let manifest = Manifest::from_str(manifest)?;
+        let io = manifest.io.clone().expect("missing io");
+        assert_eq!(
+            io.stdout,
+            io::Output::Pipe(io::Log {
+                level: io::Level::Info,
+                tag: Some(nn("hello-out")),
+            })
+        );
+        assert_eq!(
+            io.stderr,
+            io::Output::Syslog(io::Log {
+                level: io::Level::Warn,
+                tag: None,
+            })
+        );
+        assert_eq!(Manifest::from_str(&manifest.to_string())?, manifest);
+        let json = serde_json::to_string(&manifest)?;
+        assert_eq!(serde_json::from_str::<Manifest>(&json)?, manifest);
+
+        // Plain names and single key maps
+        let manifest = "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1\ngid: 1
+io:
+  stdout: syslog
+  stderr:
+    pipe:
+      tag: hello-err";
+        let io = Manifest::from_str(manifest)?.io.expect("missing io");
+        assert_eq!(io.stdout, io::Output::Syslog(io::Log::default()));
+        assert_eq!(
+            io.stderr,
+            io::Output::Pipe(io::Log {
+                level: io::Level::Debug,
+                tag: Some(nn("hello-err")),
+            })
+        );
+
+        for output in ["unknown", "!pipe\n    level: fatal", "!pipe\n    foo: bar"] {
+            let manifest = format!(
+                "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1\ngid: 1\nio:\n  stdout: {}\n  stderr: discard",
+                output
+            );
+            assert!(Manifest::from_str(&manifest).is_err(), "{}", output);
+        }
+        Ok(())
+    }
+
+    /// Writable overlay over the root filesystem
+    #[test]
+    fn overlay() -> Result<()> {
+        let manifest = "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1\ngid: 1
+overlay:
+  type: tmpfs
+  size: 10MB";
+        let manifest = Manifest::from_str(manifest)?;
+        assert_eq!(
+            manifest.overlay,
+            Some(Overlay::Tmpfs(Tmpfs { size: 10000000 }))
+        );
+        assert_eq!(Manifest::from_str(&manifest.to_string())?, manifest);
+
+        let manifest =
+            "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1\ngid: 1\noverlay:\n  type: persist";
+        let manifest = Manifest::from_str(manifest)?;
+        assert_eq!(manifest.overlay, Some(Overlay::Persist));
+
+        // Resource containers are not started
+        let manifest = "name: hello\nversion: 0.0.0\nuid: 1\ngid: 1\noverlay:\n  type: persist";
+        assert!(Manifest::from_str(manifest).is_err());
+        Ok(())
+    }
+
     #[test]
     fn mount_resource() {
         let manifest = "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1000\ngid: 1001
@@ -528,6 +1206,8 @@ mounts:
         let m = "
 name: hello
 version: 0.0.0
+arch: aarch64
+min_runtime_version: 0.6.0
 init: /binary
 uid: 1000
 gid: 1001
@@ -559,7 +1239,10 @@ mounts:
   /tmp:
     type: tmpfs
     size: 42
+  /sys/fs/cgroup:
+    type: cgroup
 autostart: relaxed
+user_namespace: true
 rlimits:
   nproc:
     soft: 100
@@ -574,6 +1257,7 @@ io:
   stdout: pipe
   stderr: pipe
 cgroups:
+    delegate: true
     memory:
       memory_hard_limit: 1000000
       memory_soft_limit: 1000000
@@ -583,6 +1267,9 @@ cgroups:
       cpus: 0,1
       shares: 1024
       attrs: {}
+labels:
+  tier: background
+  app.example.com/owner: team-a
 custom:
     blah: foo
     foo: 234
@@ -630,4 +1317,50 @@ env:
         assert!(Manifest::from_str(manifest).is_err());
         Ok(())
     }
+
+    /// Label keys and values
+    #[test]
+    fn labels() -> Result<()> {
+        let manifest = "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1000\ngid: 1001
+labels:
+  tier: background
+  example.com/owner: team-a
+  empty: ''";
+        let manifest = Manifest::from_str(manifest)?;
+        assert_eq!(manifest.labels.len(), 3);
+        assert_eq!(manifest.labels.get(&nn("tier")), Some(&nn("background")));
+
+        let manifest = "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1000\ngid: 1001
+labels:
+  tier=1: background";
+        assert!(Manifest::from_str(manifest).is_err());
+
+        let manifest = "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1000\ngid: 1001
+labels:
+  tier: back ground";
+        assert!(Manifest::from_str(manifest).is_err());
+
+        let manifest = "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1000\ngid: 1001
+labels:
+  tier: background
+  tier: foreground";
+        assert!(Manifest::from_str(manifest).is_err());
+        Ok(())
+    }
+
+    /// Architecture and minimum runtime version
+    #[test]
+    fn arch() -> Result<()> {
+        let manifest = "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1000\ngid: 1001
+arch: x86_64
+min_runtime_version: 0.6.1";
+        let manifest = Manifest::from_str(manifest)?;
+        assert_eq!(manifest.arch, Some(arch::Arch::X86_64));
+        assert_eq!(manifest.min_runtime_version, Some(Version::new(0, 6, 1)));
+
+        let manifest = "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1000\ngid: 1001
+arch: vax";
+        assert!(Manifest::from_str(manifest).is_err());
+        Ok(())
+    }
 }
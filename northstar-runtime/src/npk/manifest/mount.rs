@@ -45,6 +45,30 @@ pub struct Tmpfs {
     pub size: u64,
 }
 
+/// Persist mount configuration
+#[derive(Clone, Default, Eq, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Persist {
+    /// Size limit in bytes. The persist directory is a filesystem image of this size if set.
+    #[serde(
+        default,
+        deserialize_with = "deserialize_optional_size",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub size: Option<u64>,
+}
+
+/// Volume mount configuration
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Volume {
+    /// Name of the volume in the runtime configuration
+    pub name: Name,
+    /// Mount options
+    #[serde(default, skip_serializing_if = "HashSet::is_empty")]
+    pub options: MountOptions,
+}
+
 /// Mounts
 #[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
 #[serde(tag = "type")]
@@ -52,12 +76,16 @@ pub enum Mount {
     /// Bind mount of a host dir with options
     #[serde(rename = "bind")]
     Bind(Bind),
+    /// Mount the cgroup2 filesystem rooted at the cgroup of the container. The
+    /// mount is read only unless the cgroup is delegated to the container.
+    #[serde(rename = "cgroup")]
+    Cgroup,
     /// Use a minimal dev tree
     #[serde(rename = "dev")]
     Dev,
     /// Mount a rw host directory dedicated to this container rw
     #[serde(rename = "persist")]
-    Persist,
+    Persist(Persist),
     /// Mount proc
     #[serde(rename = "proc")]
     Proc,
@@ -70,9 +98,25 @@ pub enum Mount {
     /// Mount a tmpfs with size
     #[serde(rename = "tmpfs")]
     Tmpfs(Tmpfs),
+    /// Mount a named volume shared between containers
+    #[serde(rename = "volume")]
+    Volume(Volume),
+}
+
+/// Writable overlay over the root filesystem of a container
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum Overlay {
+    /// Keep the changes on a tmpfs with size. Changes are lost when the container exits.
+    #[serde(rename = "tmpfs")]
+    Tmpfs(Tmpfs),
+    /// Keep the changes in the data directory of the runtime. Changes are kept until
+    /// the container is uninstalled with wipe.
+    #[serde(rename = "persist")]
+    Persist,
 }
 
-#[derive(Clone, Eq, PartialEq, Debug, Hash, Serialize, Deserialize)]
+#[derive(Clone, Eq, PartialEq, PartialOrd, Ord, Debug, Hash, Serialize, Deserialize)]
 #[allow(missing_docs)]
 /// Mount option
 pub enum MountOption {
@@ -142,7 +186,7 @@ impl Serialize for MountOptions {
     where
         S: Serializer,
     {
-        serializer.serialize_str(&self.0.iter().map(ToString::to_string).join(","))
+        serializer.serialize_str(&self.0.iter().sorted().map(ToString::to_string).join(","))
     }
 }
 
@@ -199,6 +243,10 @@ fn deserialize_tmpfs_size<'de, D: Deserializer<'de>>(deserializer: D) -> Result<
             Ok(v)
         }
 
+        fn visit_i64<E: serde::de::Error>(self, v: i64) -> Result<u64, E> {
+            u64::try_from(v).map_err(serde::de::Error::custom)
+        }
+
         fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<u64, E> {
             use humanize_rs::bytes::Bytes;
             v.parse::<Bytes>()
@@ -209,3 +257,15 @@ fn deserialize_tmpfs_size<'de, D: Deserializer<'de>>(deserializer: D) -> Result<
 
     deserializer.deserialize_any(SizeVisitor)
 }
+
+pub(crate) fn deserialize_size<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<u64, D::Error> {
+    deserialize_tmpfs_size(deserializer)
+}
+
+pub(crate) fn deserialize_optional_size<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<u64>, D::Error> {
+    deserialize_tmpfs_size(deserializer).map(Some)
+}
@@ -2,10 +2,10 @@ use serde::{Deserialize, Serialize};
 
 use crate::common::non_nul_string::NonNulString;
 
-/// Container network configuration. Either join the host network or
-/// an existing network namespace. In order to create a new network
-/// namespace for the container, omit the network confuration in the
-/// manifest.
+/// Container network configuration. Either join the host network,
+/// an existing network namespace or the bridge of the runtime. In order
+/// to create a new network namespace for the container, omit the network
+/// confuration in the manifest.
 #[derive(Clone, Eq, PartialEq, Debug, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum Network {
@@ -15,4 +15,8 @@ pub enum Network {
     /// Join an existing namespace.
     #[serde(rename = "namespace")]
     Namespace(NonNulString),
+    /// Create a network namespace that is connected to the bridge configured
+    /// in the runtime.
+    #[serde(rename = "bridge")]
+    Bridge,
 }
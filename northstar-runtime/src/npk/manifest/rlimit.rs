@@ -1,7 +1,7 @@
 use serde::{Deserialize, Serialize};
 
 /// Resource limits. See setrlimit(2)
-#[derive(Clone, Eq, PartialEq, Hash, Debug, Serialize, Deserialize)]
+#[derive(Clone, Eq, PartialEq, PartialOrd, Ord, Hash, Debug, Serialize, Deserialize)]
 #[serde(rename_all(serialize = "lowercase", deserialize = "lowercase"))]
 pub enum RLimitResource {
     /// Address space
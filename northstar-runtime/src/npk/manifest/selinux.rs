@@ -4,7 +4,12 @@ use crate::common::non_nul_string::NonNulString;
 
 /// SELinux configuration
 #[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct Selinux {
-    /// Explicit list of allowed syscalls
+    /// Context of the root filesystem mount
     pub context: NonNulString,
+    /// Context the container process is executed in. The process keeps the domain of the
+    /// runtime if omitted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exec_context: Option<NonNulString>,
 }
@@ -0,0 +1,52 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::{fmt, str::FromStr};
+
+/// File mode creation mask in octal notation, e.g. `0027`
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Hash)]
+pub struct Umask(u32);
+
+impl Umask {
+    /// Permission bits of the mask
+    pub fn bits(&self) -> u32 {
+        self.0
+    }
+}
+
+impl FromStr for Umask {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() || !s.chars().all(|c| c.is_digit(8)) {
+            return Err(format!("invalid umask {}", s));
+        }
+        match u32::from_str_radix(s, 8) {
+            Ok(umask) if umask <= 0o777 => Ok(Umask(umask)),
+            _ => Err(format!("invalid umask {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for Umask {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04o}", self.0)
+    }
+}
+
+impl Serialize for Umask {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Umask {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        Umask::from_str(&s).map_err(serde::de::Error::custom)
+    }
+}
@@ -1,15 +1,18 @@
 use crate::{
-    common::non_nul_string::NonNulString,
-    seccomp::{Seccomp, SyscallRule},
+    common::{non_nul_string::NonNulString, selector},
+    seccomp::{Action, Seccomp, SyscallArgOp, SyscallRule},
 };
 use itertools::Itertools;
 use std::{
     collections::{HashMap, HashSet},
     path::{Component, Component::RootDir, Path},
+    time::Duration,
 };
 use validator::ValidationError;
 
 use super::{
+    capabilities::Capability,
+    landlock::Landlock,
     mount::{Mount, MountOption, MountPoint},
     network::Network,
     selinux::Selinux,
@@ -28,6 +31,8 @@ const MAX_ENV_VAR_VALUE_LENTH: usize = 1024;
 const MAX_SUPPL_GROUPS: usize = 64;
 /// Max length of a supplementary group name
 const MAX_SUPPL_GROUP_LENGTH: usize = 64;
+/// Maximum number of labels
+const MAX_LABELS: usize = 64;
 /// Max length of a network namespace
 const MAX_NET_NAMESPACE_LENGTH: usize = 256;
 
@@ -45,18 +50,89 @@ pub fn manifest(manifest: &Manifest) -> Result<(), ValidationError> {
         && (!manifest.args.is_empty()
             || !manifest.capabilities.is_empty()
             || !manifest.env.is_empty()
+            || manifest.cwd.is_some()
+            || manifest.umask.is_some()
             || !manifest.suppl_groups.is_empty()
             || manifest.autostart.is_some()
+            || manifest.max_runtime.is_some()
             || manifest.cgroups.is_some()
             || manifest.io.is_some()
-            || manifest.seccomp.is_some())
+            || manifest.seccomp.is_some()
+            || manifest.landlock.is_some()
+            || manifest.overlay.is_some()
+            || manifest.hostname.is_some()
+            || manifest.domainname.is_some()
+            || !manifest.etc.is_empty()
+            || !manifest.sysctl.is_empty()
+            || !manifest.devices.is_empty())
     {
         return Err(ValidationError::new(
             "resource containers must not define any of the following manifest entries:\
-                args, env, autostart, cgroups, seccomp, capabilities, suppl_groups, io",
+                args, env, cwd, umask, autostart, max_runtime, cgroups, seccomp, landlock, overlay, hostname, domainname, etc, sysctl, devices, capabilities, suppl_groups, io",
         ));
     }
 
+    // Mounting proc in a user namespace requires a pid namespace owned by the user namespace
+    if manifest.user_namespace && !manifest.pid_namespace {
+        return Err(ValidationError::new(
+            "user_namespace requires pid_namespace",
+        ));
+    }
+
+    // Host gids of supplementary groups are not mapped into the user namespace
+    if manifest.user_namespace && !manifest.suppl_groups.is_empty() {
+        return Err(ValidationError::new(
+            "user_namespace cannot be combined with suppl_groups",
+        ));
+    }
+
+    // Seccomp filters can only be installed with no new privileges or CAP_SYS_ADMIN
+    if !manifest.no_new_privs
+        && manifest.seccomp.is_some()
+        && !manifest.capabilities.contains(&Capability::CAP_SYS_ADMIN)
+    {
+        return Err(ValidationError::new(
+            "seccomp requires no_new_privs or CAP_SYS_ADMIN",
+        ));
+    }
+
+    // Network sysctls apply to the network namespace of the container. The host network
+    // and named network namespaces are shared with other processes.
+    if matches!(
+        manifest.network,
+        Some(Network::Host) | Some(Network::Namespace(_))
+    ) && manifest.sysctl.keys().any(|k| k.starts_with("net."))
+    {
+        return Err(ValidationError::new(
+            "net sysctls require a network namespace owned by the container",
+        ));
+    }
+
+    // Device nodes are created in a dev mount
+    let mut paths = HashSet::with_capacity(manifest.devices.len());
+    for device in &manifest.devices {
+        let path = Path::new(device.path.as_str());
+        let in_dev = manifest
+            .mounts
+            .iter()
+            .filter(|(_, mount)| matches!(mount, Mount::Dev))
+            .filter_map(|(target, _)| path.strip_prefix(target.as_str()).ok())
+            .any(|relative| {
+                relative.components().next().is_some()
+                    && relative
+                        .components()
+                        .all(|c| matches!(c, Component::Normal(_)))
+            });
+        if !in_dev {
+            return Err(ValidationError::new(
+                "device paths must be beneath a dev mount",
+            ));
+        }
+        if !paths.insert(path) {
+            return Err(ValidationError::new("duplicate device path"));
+        }
+    }
+
     Ok(())
 }
 
@@ -70,6 +146,14 @@ pub fn init(init: &NonNulString) -> Result<(), ValidationError> {
     }
 }
 
+/// Validate the maximum runtime. A zero duration would stop the container right away.
+pub fn max_runtime(max_runtime: &Duration) -> Result<(), ValidationError> {
+    if max_runtime.is_zero() {
+        return Err(ValidationError::new("max_runtime must not be zero"));
+    }
+    Ok(())
+}
+
 /// Validate the map of environment variables. They shall not contain reserved variable names
 /// that are used by the runtime.
 pub fn env(env: &HashMap<NonNulString, NonNulString>) -> Result<(), ValidationError> {
@@ -98,6 +182,26 @@ pub fn env(env: &HashMap<NonNulString, NonNulString>) -> Result<(), ValidationEr
     }
 }
 
+/// Validate the labels. Keys and values are restricted to the characters allowed in selectors.
+pub fn labels(labels: &selector::Labels) -> Result<(), ValidationError> {
+    if labels.len() > MAX_LABELS {
+        return Err(ValidationError::new("labels exceeds max length"));
+    }
+
+    if labels.keys().any(|k| selector::validate_key(k).is_err()) {
+        return Err(ValidationError::new("invalid label key"));
+    }
+
+    if labels
+        .values()
+        .any(|v| selector::validate_value(v).is_err())
+    {
+        Err(ValidationError::new("invalid label value"))
+    } else {
+        Ok(())
+    }
+}
+
 pub fn mounts(mounts: &HashMap<MountPoint, Mount>) -> Result<(), ValidationError> {
     // Check for relative and overlapping bind mounts
     let mut prev_comps = vec![RootDir];
@@ -124,12 +228,33 @@ pub fn mounts(mounts: &HashMap<MountPoint, Mount>) -> Result<(), ValidationError
             }
         })?;
 
+    // Persist mounts share the persist directory of the container
+    let persist_sizes = mounts
+        .values()
+        .filter_map(|m| match m {
+            Mount::Persist(persist) => Some(persist.size),
+            _ => None,
+        })
+        .unique()
+        .collect::<Vec<_>>();
+    if persist_sizes.len() > 1 {
+        return Err(ValidationError::new(
+            "persist mounts must have the same size",
+        ));
+    }
+    if persist_sizes.contains(&Some(0)) {
+        return Err(ValidationError::new("persist size must not be zero"));
+    }
+
     // Check for recursive non bind mounts
     mounts.iter().map(|(_, m)| m).try_for_each(|m| match m {
         // Recursive bind mounts are allowed but not resources
         Mount::Resource(m) if m.options.contains(&MountOption::Rec) => Err(ValidationError::new(
             "non bind mounts must not be recursive",
         )),
+        Mount::Volume(m) if m.options.contains(&MountOption::Rec) => Err(ValidationError::new(
+            "non bind mounts must not be recursive",
+        )),
         Mount::Resource(m) if !m.dir.starts_with('/') => Err(ValidationError::new(
             "resource directory options must not be absolute",
         )),
@@ -137,34 +262,152 @@ pub fn mounts(mounts: &HashMap<MountPoint, Mount>) -> Result<(), ValidationError
     })
 }
 
+/// Validate a hostname or domain name. Labels consist of alphanumeric characters and
+/// hyphens and are separated by dots.
+pub fn hostname(name: &NonNulString) -> Result<(), ValidationError> {
+    // HOST_NAME_MAX
+    const MAX_LEN: usize = 64;
+
+    if name.is_empty() || name.len() > MAX_LEN {
+        return Err(ValidationError::new("invalid hostname length"));
+    }
+    let valid = name.split('.').all(|label| {
+        !label.is_empty()
+            && !label.starts_with('-')
+            && !label.ends_with('-')
+            && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+    });
+    if valid {
+        Ok(())
+    } else {
+        Err(ValidationError::new("invalid hostname"))
+    }
+}
+
+/// Validate that the working directory is absolute and normalized
+pub fn cwd(cwd: &NonNulString) -> Result<(), ValidationError> {
+    let path = Path::new(cwd.as_str());
+    if !path.is_absolute() {
+        Err(ValidationError::new("cwd must be absolute"))
+    } else if path.components().any(|c| c == Component::ParentDir) {
+        Err(ValidationError::new("cwd must not contain .."))
+    } else {
+        Ok(())
+    }
+}
+
+/// Sysctls of the IPC namespace
+const SYSCTL_IPC: &[&str] = &[
+    "kernel.msgmax",
+    "kernel.msgmnb",
+    "kernel.msgmni",
+    "kernel.sem",
+    "kernel.shm_rmid_forced",
+    "kernel.shmall",
+    "kernel.shmmax",
+    "kernel.shmmni",
+];
+
+/// Prefixes of the sysctls of the IPC and network namespace
+const SYSCTL_PREFIXES: &[&str] = &["fs.mqueue.", "net."];
+
+/// Validate that the sysctl keys are namespaced and well formed
+pub fn sysctl(sysctl: &HashMap<NonNulString, NonNulString>) -> Result<(), ValidationError> {
+    for key in sysctl.keys() {
+        let well_formed = key.split('.').all(|s| !s.is_empty() && !s.contains('/'));
+        if !well_formed {
+            return Err(ValidationError::new("invalid sysctl key"));
+        }
+        let namespaced = SYSCTL_IPC.contains(&key.as_str())
+            || SYSCTL_PREFIXES
+                .iter()
+                .any(|prefix| key.len() > prefix.len() && key.starts_with(prefix));
+        if !namespaced {
+            return Err(ValidationError::new("sysctl is not namespaced"));
+        }
+    }
+    Ok(())
+}
+
 /// Validate selinux settings
 pub fn selinux(selinux: &Selinux) -> Result<(), ValidationError> {
+    selinux_context(&selinux.context)?;
+    if let Some(exec_context) = &selinux.exec_context {
+        selinux_context(exec_context)?;
+    }
+    Ok(())
+}
+
+/// Validate a selinux context of the form `user:role:type[:level]`
+fn selinux_context(context: &str) -> Result<(), ValidationError> {
     // Maximum length since at least Linux v3.7
     // (https://elixir.bootlin.com/linux/v3.7/source/include/uapi/linux/limits.h)
     const XATTR_SIZE_MAX: usize = 65536;
 
-    if selinux.context.len() >= XATTR_SIZE_MAX {
+    if context.len() >= XATTR_SIZE_MAX {
         return Err(ValidationError::new("Selinux context too long"));
     }
 
-    if !selinux
-        .context
-        .chars()
-        .all(|c| c.is_ascii_alphanumeric() || c == ':' || c == '_')
-    {
+    let identifier = |s: &str| {
+        !s.is_empty()
+            && s.chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+    };
+    // The level may contain categories, e.g. s0-s0:c0.c1023 or s0:c1,c2
+    let level = |s: &str| {
+        !s.is_empty()
+            && s.chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | ',' | ':' | '-'))
+    };
+
+    let mut parts = context.splitn(4, ':');
+    let valid = (0..3).all(|_| parts.next().map(identifier).unwrap_or(false))
+        && parts.next().map(level).unwrap_or(true);
+    if !valid {
         return Err(ValidationError::new(
-            "Selinux context must consist of alphanumeric ASCII characters, '?' or '_'",
+            "Selinux context must be of the form user:role:type[:level]",
         ));
     }
 
     Ok(())
 }
 
+/// Validate landlock paths. Paths must be absolute and grant at least one access right.
+pub fn landlock(landlock: &Landlock) -> Result<(), ValidationError> {
+    for (path, access) in &landlock.paths {
+        if !path.starts_with('/') {
+            return Err(ValidationError::new("Landlock paths must be absolute"));
+        }
+        if access.is_empty() {
+            return Err(ValidationError::new(
+                "Landlock paths must grant at least one access right",
+            ));
+        }
+    }
+    Ok(())
+}
+
 /// Validate seccomp rules
 pub fn seccomp(seccomp: &Seccomp) -> Result<(), ValidationError> {
     // Check seccomp filter
     const MAX_ARG_INDEX: usize = 5; // Restricted by seccomp_data struct
     const MAX_ARG_VALUES: usize = 50; // BPF jumps cannot exceed 255 and each check needs multiple instructions
+    const MAX_ARG_CONDITIONS: usize = 32; // Each condition needs up to 6 instructions
+    const MAX_ERRNO: u16 = 4095; // Larger values are not interpreted as errors by libc
+    let actions = seccomp
+        .deny
+        .iter()
+        .flat_map(|deny| deny.values())
+        .chain(seccomp.default_action.iter());
+    for action in actions {
+        if let Action::Errno(errno) = action {
+            if *errno == 0 || *errno > MAX_ERRNO {
+                return Err(ValidationError::new(
+                    "Seccomp errno action value must be between 1 and MAX_ERRNO",
+                ));
+            }
+        }
+    }
     if let Some(allowlist) = &seccomp.allow {
         for filter in allowlist {
             match filter.1 {
@@ -186,6 +429,30 @@ pub fn seccomp(seccomp: &Seccomp) -> Result<(), ValidationError> {
                         }
                     }
                 }
+                SyscallRule::AllOf(conditions) | SyscallRule::AnyOf(conditions) => {
+                    if conditions.is_empty() {
+                        return Err(ValidationError::new(
+                            "Seccomp syscall argument conditions must not be empty",
+                        ));
+                    }
+                    if conditions.len() > MAX_ARG_CONDITIONS {
+                        return Err(ValidationError::new(
+                            "Seccomp syscall cannot have more than MAX_ARG_CONDITIONS argument conditions",
+                        ));
+                    }
+                    for condition in conditions {
+                        if condition.index > MAX_ARG_INDEX {
+                            return Err(ValidationError::new(
+                                "Seccomp syscall argument index must be MAX_ARG_INDEX or less",
+                            ));
+                        }
+                        if (condition.op == SyscallArgOp::MaskedEq) != condition.mask.is_some() {
+                            return Err(ValidationError::new(
+                                "Seccomp syscall argument 'mask' must be defined for and only for 'masked_eq'",
+                            ));
+                        }
+                    }
+                }
                 SyscallRule::Any => {
                     // This syscall is allowed unconditionally
                 }
@@ -214,7 +481,7 @@ pub fn suppl_groups(groups: &HashSet<NonNulString>) -> Result<(), ValidationErro
 /// Validate network namespace setting
 pub fn network(network: &Network) -> Result<(), ValidationError> {
     match network {
-        Network::Host => Ok(()),
+        Network::Host | Network::Bridge => Ok(()),
         Network::Namespace(netns) if netns.len() <= MAX_NET_NAMESPACE_LENGTH => Ok(()),
         Network::Namespace(_) => Err(ValidationError::new("network namespace exceeds max length")),
     }
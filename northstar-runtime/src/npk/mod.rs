@@ -1,5 +1,4 @@
 use crate::common::version::Version;
-use pkg_version::{pkg_version_major, pkg_version_minor, pkg_version_patch};
 
 /// dm-verity for integrity checking of block devices
 pub(crate) mod dm_verity;
@@ -11,9 +10,9 @@ pub mod manifest;
 #[allow(clippy::module_inception)]
 pub mod npk;
 
-/// API version
-pub const VERSION: Version = Version::new(
-    pkg_version_major!(),
-    pkg_version_minor!(),
-    pkg_version_patch!(),
-);
+/// NPK format version. NPKs with a newer major or minor version are rejected.
+/// 0.7 added EROFS images. NPKs with a squashfs image are written with `VERSION_MIN`.
+pub const VERSION: Version = Version::new(0, 7, 0);
+
+/// Oldest NPK format version that is supported
+pub const VERSION_MIN: Version = Version::new(0, 6, 0);
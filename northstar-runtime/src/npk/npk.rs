@@ -3,7 +3,8 @@ use crate::{
     npk::{
         dm_verity::{append_dm_verity_block, VerityHeader, BLOCK_SIZE},
         manifest::{
-            mount::{Bind, Mount, MountOption},
+            devices::{Device, DeviceType},
+            mount::{Bind, Mount, MountOption, Volume},
             Manifest,
         },
     },
@@ -16,9 +17,14 @@ use semver::Comparator;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use std::{
+    ffi::CString,
     fmt, fs,
     io::{self, BufReader, Read, Seek, SeekFrom, Write},
-    os::unix::io::{AsRawFd, RawFd},
+    os::unix::{
+        ffi::OsStrExt,
+        fs::{FileTypeExt, MetadataExt, PermissionsExt},
+        io::{AsRawFd, RawFd},
+    },
     path::{Path, PathBuf},
     process::Command,
     str::FromStr,
@@ -28,12 +34,16 @@ use thiserror::Error;
 use zeroize::Zeroize;
 use zip::ZipArchive;
 
-use super::VERSION;
+use super::{VERSION, VERSION_MIN};
 
 /// Default path to mksquashfs
 pub const MKSQUASHFS: &str = "mksquashfs";
 /// Default path to unsquashfs
 pub const UNSQUASHFS: &str = "unsquashfs";
+/// Default path to mkfs.erofs
+pub const MKFS_EROFS: &str = "mkfs.erofs";
+/// Default path to fsck.erofs
+pub const FSCK_EROFS: &str = "fsck.erofs";
 
 /// File system file name
 pub const FS_IMG_NAME: &str = "fs.img";
@@ -48,6 +58,13 @@ pub const NPK_EXT: &str = "npk";
 const MKSQUASHFS_MAJOR_VERSION_MIN: u64 = 4;
 /// Minimum mksquashfs minor version supported
 const MKSQUASHFS_MINOR_VERSION_MIN: u64 = 1;
+/// Minimum mksquashfs minor version supported for reproducible images (-mkfs-time, -all-time)
+const MKSQUASHFS_MINOR_VERSION_MIN_REPRODUCIBLE: u64 = 4;
+/// Minimum mkfs.erofs version supported (--force-uid, --force-gid, --ignore-mtime)
+const MKFS_EROFS_VERSION_MIN: Version = Version::new(1, 4, 0);
+/// Environment variable with the timestamp used for reproducible packing
+/// See <https://reproducible-builds.org/docs/source-date-epoch/>
+const SOURCE_DATE_EPOCH: &str = "SOURCE_DATE_EPOCH";
 
 type Zip<R> = ZipArchive<R>;
 
@@ -61,6 +78,41 @@ pub struct Error(#[from] anyhow::Error);
 pub struct Meta {
     /// Version
     pub version: Version,
+    /// Filesystem type of the fs image. NPKs without this field contain a squashfs image.
+    #[serde(default)]
+    pub fs: FsType,
+}
+
+/// Filesystem type of the fs image
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FsType {
+    /// Squashfs
+    #[default]
+    Squashfs,
+    /// Enhanced read-only file system
+    Erofs,
+}
+
+impl fmt::Display for FsType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FsType::Squashfs => write!(f, "squashfs"),
+            FsType::Erofs => write!(f, "erofs"),
+        }
+    }
+}
+
+impl FromStr for FsType {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "squashfs" => Ok(FsType::Squashfs),
+            "erofs" => Ok(FsType::Erofs),
+            _ => Err(anyhow!("invalid filesystem type").into()),
+        }
+    }
 }
 
 /// NPK Hashes
@@ -136,15 +188,24 @@ impl<R: Read + Seek> Npk<R> {
     pub fn from_reader(reader: R, key: Option<&PublicKey>) -> Result<Self, Error> {
         let mut zip = Zip::new(reader).context("archive error")?;
 
-        // Check npk format version against `VERSION`.
+        // Check npk format version against `VERSION_MIN` and `VERSION`.
         let version_request = semver::VersionReq {
-            comparators: vec![Comparator {
-                op: semver::Op::GreaterEq,
-                major: VERSION.major,
-                minor: Some(VERSION.minor),
-                patch: None,
-                pre: semver::Prerelease::default(),
-            }],
+            comparators: vec![
+                Comparator {
+                    op: semver::Op::GreaterEq,
+                    major: VERSION_MIN.major,
+                    minor: Some(VERSION_MIN.minor),
+                    patch: None,
+                    pre: semver::Prerelease::default(),
+                },
+                Comparator {
+                    op: semver::Op::LessEq,
+                    major: VERSION.major,
+                    minor: Some(VERSION.minor),
+                    patch: None,
+                    pre: semver::Prerelease::default(),
+                },
+            ],
         };
 
         // Read hashes from the npk if a key is passed
@@ -334,7 +395,8 @@ struct Builder {
     root: PathBuf,
     manifest: Manifest,
     key: Option<PathBuf>,
-    squashfs_options: SquashfsOptions,
+    fs_options: FsOptions,
+    reproducible: bool,
 }
 
 impl Builder {
@@ -343,7 +405,8 @@ impl Builder {
             root: PathBuf::from(root),
             manifest,
             key: None,
-            squashfs_options: SquashfsOptions::default(),
+            fs_options: FsOptions::default(),
+            reproducible: false,
         }
     }
 
@@ -352,24 +415,64 @@ impl Builder {
         self
     }
 
-    fn squashfs_opts(mut self, opts: SquashfsOptions) -> Builder {
-        self.squashfs_options = opts;
+    fn fs_opts(mut self, opts: FsOptions) -> Builder {
+        self.fs_options = opts;
+        self
+    }
+
+    fn reproducible(mut self, reproducible: bool) -> Builder {
+        self.reproducible = reproducible;
         self
     }
 
     fn build<W: Write + Seek>(&self, writer: W) -> Result<()> {
-        // Create squashfs image
+        // Timestamp used for all files if the npk is packed reproducible
+        let epoch = if self.reproducible {
+            // Extended attributes like file capabilities or SELinux labels are host specific
+            // and would end up in the image.
+            if let Some(path) = find_xattrs(&self.root)? {
+                bail!(
+                    "{} has extended attributes. Reproducible packing requires a root without extended attributes",
+                    path.display()
+                );
+            }
+            Some(source_date_epoch()?)
+        } else {
+            None
+        };
+
+        // Create fs image
         let tmp = tempfile::TempDir::new().context("failed to create temporary directory")?;
-        let meta = &Meta { version: VERSION };
+        let fs = self.fs_options.fs_type();
+        // Squashfs NPKs remain loadable by runtimes that do not know EROFS images
+        let version = match fs {
+            FsType::Squashfs => VERSION_MIN,
+            FsType::Erofs => VERSION,
+        };
+        let meta = &Meta { version, fs };
         let fsimg = tmp.path().join(FS_IMG_NAME);
-        create_squashfs_img(&self.manifest, &self.root, &fsimg, &self.squashfs_options)?;
+        match &self.fs_options {
+            FsOptions::Squashfs(options) => {
+                create_squashfs_img(&self.manifest, &self.root, &fsimg, options, epoch)?
+            }
+            FsOptions::Erofs(options) => {
+                create_erofs_img(&self.manifest, &self.root, &fsimg, options, epoch)?
+            }
+        }
 
         // Sign and write NPK
         if let Some(key) = &self.key {
-            let signature = signature(key, meta, &fsimg, &self.manifest)?;
-            write_npk(writer, meta, &self.manifest, &fsimg, Some(&signature))
+            let signature = signature(key, meta, &fsimg, &self.manifest, self.reproducible)?;
+            write_npk(
+                writer,
+                meta,
+                &self.manifest,
+                &fsimg,
+                Some(&signature),
+                epoch,
+            )
         } else {
-            write_npk(writer, meta, &self.manifest, &fsimg, None)
+            write_npk(writer, meta, &self.manifest, &fsimg, None, epoch)
         }
     }
 }
@@ -433,6 +536,113 @@ impl Default for SquashfsOptions {
     }
 }
 
+/// EROFS compression algorithm
+#[derive(Clone, Debug)]
+#[allow(missing_docs)]
+pub enum ErofsCompressionAlgorithm {
+    Lz4,
+    Lz4hc,
+    Lzma,
+}
+
+impl fmt::Display for ErofsCompressionAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErofsCompressionAlgorithm::Lz4 => write!(f, "lz4"),
+            ErofsCompressionAlgorithm::Lz4hc => write!(f, "lz4hc"),
+            ErofsCompressionAlgorithm::Lzma => write!(f, "lzma"),
+        }
+    }
+}
+
+impl FromStr for ErofsCompressionAlgorithm {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "lz4" => Ok(ErofsCompressionAlgorithm::Lz4),
+            "lz4hc" => Ok(ErofsCompressionAlgorithm::Lz4hc),
+            "lzma" => Ok(ErofsCompressionAlgorithm::Lzma),
+            _ => Err(anyhow!("invalid compression algorithm").into()),
+        }
+    }
+}
+
+/// EROFS Options
+#[derive(Clone, Debug)]
+pub struct ErofsOptions {
+    /// Path to mkfs.erofs executable
+    pub mkfs_erofs: PathBuf,
+    /// The compression algorithm used. Uncompressed if `None`.
+    pub compression_algorithm: Option<ErofsCompressionAlgorithm>,
+}
+
+impl Default for ErofsOptions {
+    fn default() -> Self {
+        ErofsOptions {
+            mkfs_erofs: PathBuf::from(MKFS_EROFS),
+            compression_algorithm: None,
+        }
+    }
+}
+
+/// Filesystem image options
+#[derive(Clone, Debug)]
+pub enum FsOptions {
+    /// Create a squashfs image
+    Squashfs(SquashfsOptions),
+    /// Create an EROFS image
+    Erofs(ErofsOptions),
+}
+
+impl FsOptions {
+    /// Filesystem type created with this options
+    pub fn fs_type(&self) -> FsType {
+        match self {
+            FsOptions::Squashfs(_) => FsType::Squashfs,
+            FsOptions::Erofs(_) => FsType::Erofs,
+        }
+    }
+}
+
+impl Default for FsOptions {
+    fn default() -> Self {
+        FsOptions::Squashfs(SquashfsOptions::default())
+    }
+}
+
+impl From<SquashfsOptions> for FsOptions {
+    fn from(options: SquashfsOptions) -> Self {
+        FsOptions::Squashfs(options)
+    }
+}
+
+impl From<ErofsOptions> for FsOptions {
+    fn from(options: ErofsOptions) -> Self {
+        FsOptions::Erofs(options)
+    }
+}
+
+/// Options for packing an NPK
+#[derive(Clone, Debug, Default)]
+pub struct PackOptions {
+    /// Filesystem type and options for `mksquashfs` or `mkfs.erofs`
+    pub fs: FsOptions,
+    /// Normalize timestamps, ownership and archive metadata. Packing the same input twice
+    /// results in identical NPKs. Timestamps are set to `SOURCE_DATE_EPOCH` or 0. Roots with
+    /// extended attributes (e.g. file capabilities or SELinux labels) are rejected.
+    pub reproducible: bool,
+}
+
+impl From<FsOptions> for PackOptions {
+    fn from(fs: FsOptions) -> Self {
+        PackOptions {
+            fs,
+            reproducible: false,
+        }
+    }
+}
+
 /// Create an NPK for the northstar runtime.
 /// northstar-sextant collects the artifacts in a given container directory, creates and signs the necessary metadata
 /// and packs the results into a zipped NPK file.
@@ -453,10 +663,10 @@ impl Default for SquashfsOptions {
 /// --out target/northstar/repository \
 /// --key examples/keys/northstar.key \
 pub fn pack(manifest: &Path, root: &Path, out: &Path, key: Option<&Path>) -> Result<(), Error> {
-    pack_with(manifest, root, out, key, SquashfsOptions::default())
+    pack_with(manifest, root, out, key, PackOptions::default())
 }
 
-/// Create an NPK with special `squashfs` options
+/// Create an NPK with special filesystem options
 /// northstar-sextant collects the artifacts in a given container directory, creates and signs the necessary metadata
 /// and packs the results into a zipped NPK file.
 ///
@@ -465,7 +675,8 @@ pub fn pack(manifest: &Path, root: &Path, out: &Path, key: Option<&Path>) -> Res
 /// * `root` - Path to the container's root directory
 /// * `out` - Target directory or filename of the packed NPK
 /// * `key` - Path to the key used to sign the package
-/// * `squashfs_opts` - Options for `mksquashfs`
+/// * `options` - Filesystem type and options for `mksquashfs` or `mkfs.erofs` and
+///   reproducible packing
 ///
 /// # Example
 ///
@@ -478,12 +689,22 @@ pub fn pack(manifest: &Path, root: &Path, out: &Path, key: Option<&Path>) -> Res
 /// --key examples/keys/northstar.key \
 /// --comp xz \
 /// --block-size 65536 \
+/// --reproducible \
+///
+/// or with an EROFS image:
+///
+/// northstar-sextant pack \
+/// --manifest examples/hello/manifest.yaml \
+/// --root examples/hello/root \
+/// --out target/northstar/repository \
+/// --key examples/keys/northstar.key \
+/// --fs erofs \
 pub fn pack_with(
     manifest: &Path,
     root: &Path,
     out: &Path,
     key: Option<&Path>,
-    squashfs_opts: SquashfsOptions,
+    options: PackOptions,
 ) -> Result<(), Error> {
     let manifest = read_manifest(manifest)?;
     let name = manifest.name.clone();
@@ -492,7 +713,9 @@ pub fn pack_with(
     if let Some(key) = key {
         builder = builder.key(key);
     }
-    builder = builder.squashfs_opts(squashfs_opts);
+    builder = builder
+        .fs_opts(options.fs)
+        .reproducible(options.reproducible);
 
     let mut dest = out.to_path_buf();
     // Append filename from manifest if only a directory path was given
@@ -508,16 +731,25 @@ pub fn pack_with(
 
 /// Extract the npk content to `out`
 pub fn unpack(npk: &Path, out: &Path) -> Result<(), Error> {
-    unpack_with(npk, out, Path::new(UNSQUASHFS))
+    unpack_with(npk, out, Path::new(UNSQUASHFS), Path::new(FSCK_EROFS))
 }
 
-/// Extract the npk content to `out` with a give unsquashfs binary
-pub fn unpack_with(npk: &Path, out: &Path, unsquashfs: &Path) -> Result<(), Error> {
+/// Extract the npk content to `out` with a given unsquashfs or fsck.erofs binary
+pub fn unpack_with(
+    npk: &Path,
+    out: &Path,
+    unsquashfs: &Path,
+    fsck_erofs: &Path,
+) -> Result<(), Error> {
     let mut zip = open(npk)?;
+    let meta: Meta = serde_yaml::from_slice(zip.comment()).context("comment malformed")?;
     zip.extract(&out)
         .with_context(|| format!("failed to extract NPK to '{}'", &out.display()))?;
     let fsimg = out.join(&FS_IMG_NAME);
-    unpack_squashfs(&fsimg, out, unsquashfs)?;
+    match meta.fs {
+        FsType::Squashfs => unpack_squashfs(&fsimg, out, unsquashfs)?,
+        FsType::Erofs => unpack_erofs(&fsimg, out, fsck_erofs)?,
+    }
     Ok(())
 }
 
@@ -609,7 +841,13 @@ fn hashes_yaml(
 }
 
 /// Try to construct the signature yaml file
-fn signature(key: &Path, meta: &Meta, fsimg: &Path, manifest: &Manifest) -> Result<String, Error> {
+fn signature(
+    key: &Path,
+    meta: &Meta,
+    fsimg: &Path,
+    manifest: &Manifest,
+    reproducible: bool,
+) -> Result<String, Error> {
     let meta_hash =
         Sha256::digest(serde_yaml::to_string(&meta).context("failed to encode metadata")?);
     let manifest_hash = Sha256::digest(manifest.to_string().as_bytes());
@@ -620,7 +858,7 @@ fn signature(key: &Path, meta: &Meta, fsimg: &Path, manifest: &Manifest) -> Resu
         .with_context(|| format!("failed to read file size: '{}'", &fsimg.display()))?
         .len();
     // Calculate verity root hash
-    let fsimg_hash: &[u8] = &append_dm_verity_block(fsimg, fsimg_size)
+    let fsimg_hash: &[u8] = &append_dm_verity_block(fsimg, fsimg_size, reproducible)
         .context("failed to calculate verity root hash")?;
 
     // Format the signatures.yaml
@@ -634,12 +872,74 @@ fn signature(key: &Path, meta: &Meta, fsimg: &Path, manifest: &Manifest) -> Resu
     Ok(signature_yaml)
 }
 
+// Minimal set of chardevs and links created for a dev mount:
+// └─ dev
+//     ├── fd -> /proc/self/fd
+//     ├── full
+//     ├── null
+//     ├── random
+//     ├── stderr -> /proc/self/fd/2
+//     ├── stdin -> /proc/self/fd/0
+//     ├── stdout -> /proc/self/fd/1
+//     ├── tty
+//     ├── urandom
+//     └── zero
+
+/// Character devices (name, major, minor) created for a dev mount
+pub(crate) const DEV_NODES: &[(&str, u64, u64)] = &[
+    ("full", 1, 7),
+    ("null", 1, 3),
+    ("random", 1, 8),
+    ("tty", 5, 0),
+    ("urandom", 1, 9),
+    ("zero", 1, 5),
+];
+
+/// Symlinks (target, name) created for a dev mount
+const DEV_LINKS: &[(&str, &str)] = &[
+    ("/proc/self/fd", "fd"),
+    ("/proc/self/fd/0", "stdin"),
+    ("/proc/self/fd/1", "stdout"),
+    ("/proc/self/fd/2", "stderr"),
+];
+
+/// Mode of the mount point directory for `mount`
+fn mount_point_mode(mount: &Mount) -> u32 {
+    match mount {
+        Mount::Bind(Bind { options: flags, .. }) | Mount::Volume(Volume { options: flags, .. }) => {
+            if flags.contains(&MountOption::Rw) {
+                0o755
+            } else {
+                0o555
+            }
+        }
+        Mount::Persist(_) => 0o755,
+        Mount::Cgroup | Mount::Proc | Mount::Sysfs => 0o444,
+        Mount::Resource { .. } => 0o555,
+        Mount::Tmpfs { .. } => 0o755,
+        Mount::Dev => 0o755,
+    }
+}
+
+/// Mode of the node of `device`. The owner and group of the node get the permissions
+/// of the device.
+fn device_mode(device: &Device) -> u32 {
+    let mut mode = 0;
+    if device.permissions.read {
+        mode |= 0o440;
+    }
+    if device.permissions.write {
+        mode |= 0o220;
+    }
+    mode
+}
+
 /// Returns a temporary file with all the pseudo file definitions
-fn pseudo_files(manifest: &Manifest) -> Result<NamedTempFile, Error> {
+fn pseudo_files(manifest: &Manifest, root: &Path) -> Result<NamedTempFile, Error> {
     let uid = manifest.uid;
     let gid = manifest.gid;
 
-    let pseudo_directory = |dir: &Path, mode: u16| -> Vec<String> {
+    let pseudo_directory = |dir: &Path, mode: u32| -> Vec<String> {
         let mut pseudos = Vec::new();
         // Each directory level needs to be passed to mksquashfs e.g:
         // /dev d 755 x x x
@@ -647,81 +947,75 @@ fn pseudo_files(manifest: &Manifest) -> Result<NamedTempFile, Error> {
         let mut p = PathBuf::from("/");
         for d in dir.iter().skip(1) {
             p.push(d);
-            pseudos.push(format!("{} d {} {} {}", p.display(), mode, uid, gid));
+            pseudos.push(format!("{} d {:o} {} {}", p.display(), mode, uid, gid));
         }
         pseudos
     };
 
     // Create mountpoints as pseudofiles/dirs
-    let pseudos = manifest
+    let mut pseudos = manifest
         .mounts
         .iter()
         .flat_map(|(target, mount)| {
-            match mount {
-                Mount::Bind(Bind { options: flags, .. }) => {
-                    let mode = if flags.contains(&MountOption::Rw) {
-                        755
-                    } else {
-                        555
-                    };
-                    pseudo_directory(target.as_ref(), mode)
+            let mut pseudos = pseudo_directory(target.as_ref(), mount_point_mode(mount));
+            if let Mount::Dev = mount {
+                // Create chardevs
+                for (dev, major, minor) in DEV_NODES {
+                    let target: &Path = target.as_ref();
+                    let target = target.join(dev).display().to_string();
+                    pseudos.push(format!(
+                        "{} c {} {} {} {} {}",
+                        target, 666, uid, gid, major, minor
+                    ));
                 }
-                Mount::Persist => pseudo_directory(target.as_ref(), 755),
-                Mount::Proc | Mount::Sysfs => pseudo_directory(target.as_ref(), 444),
-                Mount::Resource { .. } => pseudo_directory(target.as_ref(), 555),
-                Mount::Tmpfs { .. } => pseudo_directory(target.as_ref(), 755),
-                Mount::Dev => {
-                    // Create a minimal set of chardevs:
-                    // └─ dev
-                    //     ├── fd -> /proc/self/fd
-                    //     ├── full
-                    //     ├── null
-                    //     ├── random
-                    //     ├── stderr -> /proc/self/fd/2
-                    //     ├── stdin -> /proc/self/fd/0
-                    //     ├── stdout -> /proc/self/fd/1
-                    //     ├── tty
-                    //     ├── urandom
-                    //     └── zero
-
-                    // Create /dev pseudo dir. This is needed in order to create pseudo chardev file in /dev
-                    let mut pseudos = pseudo_directory(target.as_ref(), 755);
-
-                    // Create chardevs
-                    for (dev, major, minor) in &[
-                        ("full", 1, 7),
-                        ("null", 1, 3),
-                        ("random", 1, 8),
-                        ("tty", 5, 0),
-                        ("urandom", 1, 9),
-                        ("zero", 1, 5),
-                    ] {
-                        let target: &Path = target.as_ref();
-                        let target = target.join(dev).display().to_string();
-                        pseudos.push(format!(
-                            "{} c {} {} {} {} {}",
-                            target, 666, uid, gid, major, minor
-                        ));
-                    }
-
-                    // Link fds
-                    pseudos.push(format!("/proc/self/fd d 777 {} {}", uid, gid));
-                    for (link, name) in &[
-                        ("/proc/self/fd", "fd"),
-                        ("/proc/self/fd/0", "stdin"),
-                        ("/proc/self/fd/1", "stdout"),
-                        ("/proc/self/fd/2", "stderr"),
-                    ] {
-                        let target: &Path = target.as_ref();
-                        let target = target.join(name).display().to_string();
-                        pseudos.push(format!("{} s {} {} {} {}", target, 777, uid, gid, link,));
-                    }
-                    pseudos
+
+                // Link fds
+                pseudos.push(format!("/proc/self/fd d 777 {} {}", uid, gid));
+                for (link, name) in DEV_LINKS {
+                    let target: &Path = target.as_ref();
+                    let target = target.join(name).display().to_string();
+                    pseudos.push(format!("{} s {} {} {} {}", target, 777, uid, gid, link,));
                 }
             }
+            pseudos
         })
         .collect::<Vec<String>>();
 
+    // Create the device nodes listed in the manifest
+    for device in &manifest.devices {
+        let path = Path::new(device.path.as_str());
+        if let Some(parent) = path.parent() {
+            pseudos.extend(pseudo_directory(parent, 0o755));
+        }
+        let r#type = match device.r#type {
+            DeviceType::Char => 'c',
+            DeviceType::Block => 'b',
+        };
+        pseudos.push(format!(
+            "{} {} {:o} {} {} {} {}",
+            path.display(),
+            r#type,
+            device_mode(device),
+            uid,
+            gid,
+            device.major,
+            device.minor
+        ));
+    }
+
+    // Create the mount points of the generated /etc files that are not part of the root
+    for file in &manifest.etc {
+        if !root.join("etc").exists() {
+            pseudos.push(format!("/etc d 755 {} {}", uid, gid));
+        }
+        if !root.join("etc").join(file.name()).exists() {
+            pseudos.push(format!("{} f 444 {} {} true", file.path(), uid, gid));
+        }
+    }
+
+    // Sort the entries to get a stable image. Parent directories precede their entries.
+    let pseudos = pseudos.into_iter().sorted().dedup().collect::<Vec<_>>();
+
     let mut pseudo_file_entries =
         NamedTempFile::new().context("failed to create temporary file")?;
 
@@ -737,8 +1031,9 @@ fn create_squashfs_img(
     root: &Path,
     image: &Path,
     squashfs_opts: &SquashfsOptions,
+    epoch: Option<u64>,
 ) -> Result<()> {
-    let pseudo_files = pseudo_files(manifest)?;
+    let pseudo_files = pseudo_files(manifest, root)?;
     let mksquashfs = &squashfs_opts.mksquashfs;
 
     // Check root
@@ -773,18 +1068,19 @@ fn create_squashfs_img(
             .unwrap_or_default()
     });
     let actual = Version::new(major, minor, 0);
-    let required = Version::new(
-        MKSQUASHFS_MAJOR_VERSION_MIN,
-        MKSQUASHFS_MINOR_VERSION_MIN,
-        0,
-    );
+    let minor_min = if epoch.is_some() {
+        MKSQUASHFS_MINOR_VERSION_MIN_REPRODUCIBLE
+    } else {
+        MKSQUASHFS_MINOR_VERSION_MIN
+    };
+    let required = Version::new(MKSQUASHFS_MAJOR_VERSION_MIN, minor_min, 0);
     if actual < required {
         bail!(
             "Detected mksquashfs version {}.{} is too old. The required minimum version is {}.{}",
             major,
             minor,
             MKSQUASHFS_MAJOR_VERSION_MIN,
-            MKSQUASHFS_MINOR_VERSION_MIN
+            minor_min
         );
     }
 
@@ -805,6 +1101,13 @@ fn create_squashfs_img(
     if let Some(block_size) = squashfs_opts.block_size {
         cmd.arg("-b").arg(format!("{}", block_size));
     }
+    if let Some(epoch) = epoch {
+        // Set the filesystem and all inode timestamps
+        cmd.arg("-mkfs-time")
+            .arg(epoch.to_string())
+            .arg("-all-time")
+            .arg(epoch.to_string());
+    }
     cmd.output()
         .with_context(|| format!("failed to execute '{}'", mksquashfs.display()))?;
     if !image.exists() {
@@ -835,17 +1138,262 @@ fn unpack_squashfs(image: &Path, out: &Path, unsquashfs: &Path) -> Result<()> {
     Ok(())
 }
 
+fn create_erofs_img(
+    manifest: &Manifest,
+    root: &Path,
+    image: &Path,
+    erofs_opts: &ErofsOptions,
+    epoch: Option<u64>,
+) -> Result<()> {
+    let mkfs_erofs = &erofs_opts.mkfs_erofs;
+
+    // Check root
+    if !root.exists() {
+        bail!("Root directory '{}' does not exist", &root.display());
+    }
+
+    // Check mkfs.erofs version
+    let output = Command::new(mkfs_erofs)
+        .arg("--version")
+        .output()
+        .with_context(|| format!("failed to execute '{}'", mkfs_erofs.display()))?;
+    let output = [output.stdout, output.stderr].concat();
+    let actual = String::from_utf8_lossy(&output)
+        .split_whitespace()
+        .find_map(|word| {
+            // Remove trailing subversion if present (e.g. 1.5-g3f1ef3a)
+            let mut major_minor = word
+                .split(|c: char| !c.is_ascii_digit() && c != '.')
+                .next()?
+                .split('.');
+            let major = major_minor.next()?.parse::<u64>().ok()?;
+            let minor = major_minor.next()?.parse::<u64>().ok()?;
+            Some(Version::new(major, minor, 0))
+        })
+        .ok_or_else(|| anyhow!("failed to parse '{}' version", mkfs_erofs.display()))?;
+    if actual < MKFS_EROFS_VERSION_MIN {
+        bail!(
+            "Detected mkfs.erofs version {} is too old. The required minimum version is {}",
+            actual,
+            MKFS_EROFS_VERSION_MIN
+        );
+    }
+
+    // mkfs.erofs has no pseudo file support and device nodes must be created in the
+    // staged root. Fail before copying the root if that is not permitted.
+    let device_nodes =
+        !manifest.devices.is_empty() || manifest.mounts.values().any(|m| matches!(m, Mount::Dev));
+    if device_nodes
+        && !caps::has_cap(None, caps::CapSet::Effective, caps::Capability::CAP_MKNOD)
+            .context("failed to read capabilities")?
+    {
+        bail!("Packing an EROFS image with device nodes (dev mount or devices) requires CAP_MKNOD. Use a squashfs image instead");
+    }
+
+    // Stage a copy of the root and add the mount points and device nodes.
+    let staging = tempfile::TempDir::new().context("failed to create temporary directory")?;
+    let staging_root = staging.path().join("root");
+    copy_dir(root, &staging_root)?;
+    stage_mount_points(manifest, &staging_root)?;
+
+    let mut cmd = Command::new(mkfs_erofs);
+    cmd.arg(format!("--force-uid={}", manifest.uid))
+        .arg(format!("--force-gid={}", manifest.gid));
+    if let Some(compression_algorithm) = &erofs_opts.compression_algorithm {
+        cmd.arg(format!("-z{}", compression_algorithm));
+    }
+    if let Some(epoch) = epoch {
+        // Set all timestamps and use a fixed uuid
+        cmd.arg(format!("-T{}", epoch))
+            .arg("--ignore-mtime")
+            .arg("-U00000000-0000-0000-0000-000000000000");
+    }
+    cmd.arg(image.display().to_string())
+        .arg(staging_root.display().to_string());
+    let output = cmd
+        .output()
+        .with_context(|| format!("failed to execute '{}'", mkfs_erofs.display()))?;
+    if !output.status.success() || !image.exists() {
+        bail!(
+            "'{}' failed to create '{}': {}",
+            mkfs_erofs.display(),
+            &image.display(),
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    Ok(())
+}
+
+/// Create mount points and device nodes of `manifest` in `root`
+fn stage_mount_points(manifest: &Manifest, root: &Path) -> Result<()> {
+    for (target, mount) in &manifest.mounts {
+        let target = root.join(target.trim_start_matches('/'));
+        fs::create_dir_all(&target)
+            .with_context(|| format!("failed to create {}", target.display()))?;
+        fs::set_permissions(&target, fs::Permissions::from_mode(mount_point_mode(mount)))
+            .with_context(|| format!("failed to set permissions of {}", target.display()))?;
+
+        if let Mount::Dev = mount {
+            for (dev, major, minor) in DEV_NODES {
+                let path = target.join(dev);
+                make_device_node(
+                    &path,
+                    nix::sys::stat::SFlag::S_IFCHR,
+                    0o666,
+                    nix::sys::stat::makedev(*major, *minor),
+                )?;
+            }
+            for (link, name) in DEV_LINKS {
+                let path = target.join(name);
+                std::os::unix::fs::symlink(link, &path)
+                    .with_context(|| format!("failed to create {}", path.display()))?;
+            }
+        }
+    }
+
+    for device in &manifest.devices {
+        let path = root.join(device.path.trim_start_matches('/'));
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("failed to create {}", parent.display()))?;
+        }
+        let kind = match device.r#type {
+            DeviceType::Char => nix::sys::stat::SFlag::S_IFCHR,
+            DeviceType::Block => nix::sys::stat::SFlag::S_IFBLK,
+        };
+        make_device_node(
+            &path,
+            kind,
+            device_mode(device),
+            nix::sys::stat::makedev(device.major.into(), device.minor.into()),
+        )?;
+    }
+
+    for file in &manifest.etc {
+        let etc = root.join("etc");
+        if !etc.exists() {
+            fs::create_dir_all(&etc)
+                .with_context(|| format!("failed to create {}", etc.display()))?;
+        }
+        let path = etc.join(file.name());
+        if !path.exists() {
+            fs::File::create(&path)
+                .with_context(|| format!("failed to create {}", path.display()))?;
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o444))
+                .with_context(|| format!("failed to set permissions of {}", path.display()))?;
+        }
+    }
+    Ok(())
+}
+
+/// Create a device node at `path` with `mode` regardless of the umask of the packer
+fn make_device_node(path: &Path, kind: nix::sys::stat::SFlag, mode: u32, dev: u64) -> Result<()> {
+    nix::sys::stat::mknod(
+        path,
+        kind,
+        nix::sys::stat::Mode::from_bits_truncate(mode),
+        dev,
+    )
+    .with_context(|| {
+        format!(
+            "failed to create {}. Creating device nodes for EROFS images requires CAP_MKNOD",
+            path.display()
+        )
+    })?;
+    fs::set_permissions(path, fs::Permissions::from_mode(mode))
+        .with_context(|| format!("failed to set permissions of {}", path.display()))
+}
+
+/// Recursively copy `src` to `dst`. Symlinks, FIFOs and device nodes are recreated.
+/// Sockets cannot be copied and are rejected.
+pub fn copy_dir(src: &Path, dst: &Path) -> Result<()> {
+    fs::create_dir_all(dst).with_context(|| format!("failed to create {}", dst.display()))?;
+    let permissions = fs::metadata(src)
+        .with_context(|| format!("failed to read metadata of {}", src.display()))?
+        .permissions();
+    fs::set_permissions(dst, permissions)
+        .with_context(|| format!("failed to set permissions of {}", dst.display()))?;
+
+    for entry in fs::read_dir(src).with_context(|| format!("failed to read {}", src.display()))? {
+        let entry = entry.context("failed to read directory entry")?;
+        let target = dst.join(entry.file_name());
+        let file_type = entry.file_type().context("failed to get file type")?;
+        if file_type.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else if file_type.is_symlink() {
+            let link = fs::read_link(entry.path()).context("failed to read link")?;
+            std::os::unix::fs::symlink(&link, &target)
+                .with_context(|| format!("failed to create symlink {}", target.display()))?;
+        } else if file_type.is_fifo() || file_type.is_char_device() || file_type.is_block_device() {
+            let metadata = entry.metadata().with_context(|| {
+                format!("failed to read metadata of {}", entry.path().display())
+            })?;
+            let kind = nix::sys::stat::SFlag::from_bits_truncate(
+                metadata.mode() & nix::sys::stat::SFlag::S_IFMT.bits(),
+            );
+            let mode = metadata.mode() & 0o7777;
+            nix::sys::stat::mknod(
+                &target,
+                kind,
+                nix::sys::stat::Mode::from_bits_truncate(mode),
+                metadata.rdev(),
+            )
+            .with_context(|| format!("failed to create {}", target.display()))?;
+            fs::set_permissions(&target, fs::Permissions::from_mode(mode))
+                .with_context(|| format!("failed to set permissions of {}", target.display()))?;
+        } else if file_type.is_socket() {
+            bail!("cannot copy socket {}", entry.path().display());
+        } else {
+            fs::copy(entry.path(), &target)
+                .with_context(|| format!("failed to copy {}", entry.path().display()))?;
+        }
+    }
+    Ok(())
+}
+
+fn unpack_erofs(image: &Path, out: &Path, fsck_erofs: &Path) -> Result<()> {
+    let erofs_root = out.join("erofs-root");
+
+    if !image.exists() {
+        bail!("EROFS image '{}' does not exist", &image.display());
+    }
+    let mut cmd = Command::new(fsck_erofs);
+    cmd.arg(format!("--extract={}", erofs_root.display()))
+        .arg(image.display().to_string());
+
+    let output = cmd
+        .output()
+        .with_context(|| format!("Error while executing '{}'", fsck_erofs.display(),))?;
+    if !output.status.success() {
+        bail!(
+            "'{}' failed to extract '{}': {}",
+            fsck_erofs.display(),
+            &image.display(),
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    Ok(())
+}
+
 fn write_npk<W: Write + Seek>(
     npk: W,
     meta: &Meta,
     manifest: &Manifest,
     fsimg: &Path,
     signature: Option<&str>,
+    epoch: Option<u64>,
 ) -> Result<()> {
     let mut fsimg =
         fs::File::open(&fsimg).with_context(|| format!("failed to open '{}'", &fsimg.display()))?;
-    let options =
+    let mut options =
         zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+    if let Some(epoch) = epoch {
+        options = options
+            .last_modified_time(zip_date_time(epoch))
+            .unix_permissions(0o644);
+    }
     let manifest_string =
         serde_yaml::to_string(&manifest).context("failed to serialize manifest")?;
     let meta_string = serde_yaml::to_string(&meta).context("failed to serialize meta")?;
@@ -875,6 +1423,79 @@ fn write_npk<W: Write + Seek>(
     Ok(())
 }
 
+/// Returns the first file in `root` that has extended attributes
+fn find_xattrs(root: &Path) -> Result<Option<PathBuf>> {
+    let path = CString::new(root.as_os_str().as_bytes()).context("invalid path")?;
+    // A null buffer of size 0 only queries the size of the attribute list
+    let size = unsafe { nix::libc::llistxattr(path.as_ptr(), std::ptr::null_mut(), 0) };
+    if size < 0 {
+        let error = io::Error::last_os_error();
+        if error.raw_os_error() != Some(nix::libc::ENOTSUP) {
+            return Err(error)
+                .with_context(|| format!("failed to list xattrs of {}", root.display()));
+        }
+    } else if size > 0 {
+        return Ok(Some(root.to_owned()));
+    }
+
+    let metadata = fs::symlink_metadata(root)
+        .with_context(|| format!("failed to read metadata of {}", root.display()))?;
+    if metadata.is_dir() {
+        for entry in
+            fs::read_dir(root).with_context(|| format!("failed to read {}", root.display()))?
+        {
+            let entry = entry.context("failed to read directory entry")?;
+            if let Some(path) = find_xattrs(&entry.path())? {
+                return Ok(Some(path));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Timestamp for reproducible packing. Read from `SOURCE_DATE_EPOCH` or 0 if unset.
+fn source_date_epoch() -> Result<u64> {
+    match std::env::var(SOURCE_DATE_EPOCH) {
+        Ok(epoch) => epoch
+            .trim()
+            .parse::<u64>()
+            .with_context(|| format!("invalid {}: {}", SOURCE_DATE_EPOCH, epoch)),
+        Err(_) => Ok(0),
+    }
+}
+
+/// Convert seconds since the epoch into a zip timestamp. Zip timestamps cannot represent
+/// dates before 1980 or after 2107. Those are mapped to the zip default (1980-01-01).
+fn zip_date_time(epoch: u64) -> zip::DateTime {
+    // Civil date from days since 1970-01-01. See http://howardhinnant.github.io/date_algorithms.html
+    let days = (epoch / 86400) as i64;
+    let seconds = epoch % 86400;
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    u16::try_from(year)
+        .ok()
+        .and_then(|year| {
+            zip::DateTime::from_date_and_time(
+                year,
+                month as u8,
+                day as u8,
+                (seconds / 3600) as u8,
+                (seconds % 3600 / 60) as u8,
+                (seconds % 60) as u8,
+            )
+            .ok()
+        })
+        .unwrap_or_default()
+}
+
 /// Open a Zip file
 fn open(path: &Path) -> Result<Zip<BufReader<fs::File>>> {
     let file =
@@ -1,20 +1,29 @@
 use super::{
     stats::{to_value, ContainerStats},
-    Container, EventTx, Pid,
+    Container, EventTx,
 };
 use crate::{
-    npk::manifest,
-    runtime::{CGroupEvent, ContainerEvent, Event, MemoryEvent},
+    npk::manifest::{self, devices::Device},
+    runtime::{devices, CGroupEvent, ContainerEvent, Event, MemoryEvent},
 };
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use cgroups_rs::{
-    memory::MemController, BlkIoDeviceResource, BlkIoDeviceThrottleResource, BlkIoResources,
-    Controller, CpuResources, Hierarchy, MemoryResources,
+    devices::{DevicePermissions, DeviceType, DevicesController},
+    memory::MemController,
+    BlkIoDeviceResource, BlkIoDeviceThrottleResource, BlkIoResources, Controller, CpuResources,
+    Hierarchy, MemoryResources,
 };
-use futures::stream::StreamExt;
+use futures::{future::BoxFuture, stream::StreamExt, FutureExt};
 use inotify::{Inotify, WatchMask};
+use itertools::Itertools;
 use log::{debug, info, warn};
-use std::{collections::HashMap, fmt::Debug, os::unix::io::AsRawFd, path::Path};
+use nix::unistd;
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    os::unix::io::AsRawFd,
+    path::{Path, PathBuf},
+};
 use tokio::{
     fs,
     io::{AsyncReadExt, AsyncWriteExt},
@@ -26,10 +35,15 @@ use tokio::{
 use tokio_eventfd::EventFd;
 use tokio_util::sync::CancellationToken;
 
+const PROCS: &str = "cgroup.procs";
+const SUBTREE_CONTROL: &str = "cgroup.subtree_control";
+const THREADS: &str = "cgroup.threads";
+
 /// Default runtime hierarchy that yields only implemented and supported controllers
-/// instead of the default list.
-fn hierarchy() -> Box<dyn Hierarchy> {
-    Box::new(RuntimeHierarchy::new())
+/// instead of the default list. The devices controller is only yielded if `devices`
+/// is set.
+fn hierarchy(devices: bool) -> Box<dyn Hierarchy> {
+    Box::new(RuntimeHierarchy::new(devices))
 }
 
 /// Create the top level cgroups used by northstar
@@ -37,7 +51,7 @@ pub async fn init(name: &Path) -> Result<()> {
     // TODO: Add check for supported controllers
 
     info!("Initializing cgroups with name {}", name.display());
-    let cgroup = cgroups_rs::Cgroup::new(hierarchy(), name);
+    let cgroup = cgroups_rs::Cgroup::new(hierarchy(true), name);
     debug!(
         "Using cgroups version {}",
         if cgroup.v2() { "2" } else { "1" }
@@ -47,7 +61,7 @@ pub async fn init(name: &Path) -> Result<()> {
 
 /// Shutdown the cgroups config by removing the dir
 pub async fn shutdown(dir: &Path) -> Result<()> {
-    cgroups_rs::Cgroup::new(hierarchy(), dir)
+    cgroups_rs::Cgroup::new(hierarchy(true), dir)
         .delete()
         .with_context(|| format!("failed to delete {} cgroup", dir.display()))
 }
@@ -56,13 +70,15 @@ pub async fn shutdown(dir: &Path) -> Result<()> {
 #[derive(Debug)]
 struct RuntimeHierarchy {
     inner: Box<dyn Hierarchy>,
+    devices: bool,
 }
 
 impl RuntimeHierarchy {
     /// Create a new instance
-    fn new() -> RuntimeHierarchy {
+    fn new(devices: bool) -> RuntimeHierarchy {
         RuntimeHierarchy {
             inner: cgroups_rs::hierarchies::auto(),
+            devices,
         }
     }
 }
@@ -79,7 +95,7 @@ impl Hierarchy for RuntimeHierarchy {
                 cgroups_rs::Subsystem::CpuSet(_) => false,
                 cgroups_rs::Subsystem::CpuAcct(_) => true,
                 cgroups_rs::Subsystem::Cpu(_) => true,
-                cgroups_rs::Subsystem::Devices(_) => false,
+                cgroups_rs::Subsystem::Devices(_) => self.devices,
                 cgroups_rs::Subsystem::Freezer(_) => false,
                 cgroups_rs::Subsystem::NetCls(_) => false,
                 cgroups_rs::Subsystem::BlkIo(_) => true,
@@ -108,6 +124,8 @@ impl Hierarchy for RuntimeHierarchy {
 #[derive(Debug)]
 pub struct CGroups {
     container: Container,
+    /// Directory of the cgroup in the unified hierarchy. Only valid for cgroup v2.
+    dir: PathBuf,
     cgroup: cgroups_rs::Cgroup,
     memory_monitor: MemoryMonitor,
 }
@@ -118,12 +136,14 @@ impl CGroups {
         tx: EventTx,
         container: &Container,
         config: &manifest::cgroups::CGroups,
-        pid: Pid,
+        devices: bool,
     ) -> Result<CGroups> {
         debug!("Creating cgroups for {}", container);
         let name: &str = container.name().as_ref();
-        let cgroup: cgroups_rs::Cgroup =
-            cgroups_rs::Cgroup::new(hierarchy(), Path::new(top_level_dir).join(name));
+        let path = Path::new(top_level_dir).join(name);
+        let hierarchy = hierarchy(devices);
+        let dir = hierarchy.root().join(&path);
+        let cgroup: cgroups_rs::Cgroup = cgroups_rs::Cgroup::new(hierarchy, path);
 
         let resources = cgroups_rs::Resources {
             memory: config.memory.clone().map(Into::into).unwrap_or_default(),
@@ -139,12 +159,6 @@ impl CGroups {
             .apply(&resources)
             .context("failed to configure cgroups")?;
 
-        // If adding the task fails it's a fault of the runtime or it's integration
-        // and not of the container
-        cgroup
-            .add_task(cgroups_rs::CgroupPid::from(pid as u64))
-            .expect("failed to assign pid");
-
         let memory_controller = cgroup
             .controller_of::<MemController>()
             .expect("failed to get memory controller");
@@ -157,18 +171,121 @@ impl CGroups {
 
         Ok(CGroups {
             container: container.clone(),
+            dir,
             cgroup,
             memory_monitor,
         })
     }
 
+    /// Paths of the `cgroup.procs` files of all hierarchies of this cgroup. Processes
+    /// are moved into the cgroup by writing their pid into these files.
+    pub(super) fn procs(&self) -> Vec<PathBuf> {
+        self.cgroup
+            .subsystems()
+            .iter()
+            .map(|s| s.to_controller().path().join(PROCS))
+            .sorted()
+            .dedup()
+            .collect()
+    }
+
+    /// Delegate the cgroup to `uid` and `gid`. The owner can create child cgroups,
+    /// move processes between them and enable controllers for them. Only cgroup v2
+    /// supports a safe delegation.
+    pub(super) fn delegate(&self, uid: u32, gid: u32) -> Result<()> {
+        if !self.cgroup.v2() {
+            bail!("cgroup delegation requires cgroup v2");
+        }
+
+        let uid = Some(unistd::Uid::from_raw(uid));
+        let gid = Some(unistd::Gid::from_raw(gid));
+        for dir in self.procs().iter().filter_map(|p| p.parent()) {
+            debug!("Delegating {} to {:?}:{:?}", dir.display(), uid, gid);
+            for file in [None, Some(PROCS), Some(SUBTREE_CONTROL), Some(THREADS)] {
+                let path = file.map(|f| dir.join(f)).unwrap_or_else(|| dir.to_owned());
+                unistd::chown(&path, uid, gid)
+                    .with_context(|| format!("failed to chown {}", path.display()))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Restrict the device access of the container to the nodes of a `dev` mount, pseudo
+    /// terminals and `devices`
+    pub(super) fn devices(&self, devices: &[Device]) -> Result<()> {
+        let rules = devices::rules(devices);
+
+        if self.cgroup.v2() {
+            debug!("Attaching device program to {}", self.dir.display());
+            return devices::attach_program(&self.dir, &rules);
+        }
+
+        let controller = self
+            .cgroup
+            .controller_of::<DevicesController>()
+            .context("devices controller is not available")?;
+        let all = [
+            DevicePermissions::Read,
+            DevicePermissions::Write,
+            DevicePermissions::MkNod,
+        ];
+        controller
+            .deny_device(DeviceType::All, -1, -1, &all)
+            .context("failed to deny device access")?;
+        for rule in rules {
+            let r#type = match rule.r#type {
+                manifest::devices::DeviceType::Char => DeviceType::Char,
+                manifest::devices::DeviceType::Block => DeviceType::Block,
+            };
+            let minor = rule.minor.map(i64::from).unwrap_or(-1);
+            let permissions = [
+                (rule.permissions.read, DevicePermissions::Read),
+                (rule.permissions.write, DevicePermissions::Write),
+                (rule.permissions.mknod, DevicePermissions::MkNod),
+            ]
+            .into_iter()
+            .filter_map(|(set, permission)| set.then_some(permission))
+            .collect::<Vec<_>>();
+            debug!(
+                "Allowing device {:?} {}:{} {}",
+                r#type, rule.major, minor, rule.permissions
+            );
+            controller
+                .allow_device(r#type, rule.major.into(), minor, &permissions)
+                .context("failed to allow device access")?;
+        }
+        Ok(())
+    }
+
     pub async fn destroy(self) {
+        let procs = self.procs();
+
         debug!("Stopping oom monitor of {}", self.container);
         self.memory_monitor.stop().await;
 
         info!("Destroying cgroup of {}", self.container);
-        assert!(self.cgroup.tasks().is_empty());
-        self.cgroup.delete().expect("failed to remove cgroups");
+        let tasks = self.cgroup.tasks();
+        if !tasks.is_empty() {
+            warn!(
+                "Cgroup of {} contains {} remaining tasks",
+                self.container,
+                tasks.len()
+            );
+        }
+
+        // Remove child cgroups created by the container if the cgroup is delegated
+        for dir in procs.iter().filter_map(|p| p.parent()) {
+            if let Err(e) = remove_children(dir).await {
+                warn!(
+                    "Failed to remove child cgroups of {}: {:#}",
+                    self.container, e
+                );
+            }
+        }
+
+        if let Err(e) = self.cgroup.delete() {
+            warn!("Failed to remove cgroup of {}: {}", self.container, e);
+        }
     }
 
     /// Gather statistics from controllers
@@ -206,6 +323,26 @@ impl CGroups {
     }
 }
 
+/// Remove all child cgroups of `dir` depth first. Cgroup directories can be removed
+/// with `rmdir` once they have no children and no processes.
+fn remove_children(dir: &Path) -> BoxFuture<'_, Result<()>> {
+    async move {
+        let mut entries = fs::read_dir(dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            if entry.file_type().await?.is_dir() {
+                let path = entry.path();
+                remove_children(&path).await?;
+                debug!("Removing child cgroup {}", path.display());
+                fs::remove_dir(&path)
+                    .await
+                    .with_context(|| format!("failed to remove {}", path.display()))?;
+            }
+        }
+        Ok(())
+    }
+    .boxed()
+}
+
 #[derive(Debug)]
 struct MemoryMonitor {
     token: CancellationToken,
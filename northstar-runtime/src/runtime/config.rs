@@ -1,7 +1,10 @@
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
+    fmt,
+    net::{IpAddr, Ipv4Addr},
     os::unix::prelude::{MetadataExt, PermissionsExt},
     path::{Path, PathBuf},
+    str::FromStr,
     time,
 };
 
@@ -11,7 +14,14 @@ use serde::{de::Error as SerdeError, Deserialize, Deserializer};
 use url::Url;
 
 use super::RepositoryId;
-use crate::common::non_nul_string::NonNulString;
+use crate::{
+    common::{name::Name, non_nul_string::NonNulString},
+    npk::manifest::{
+        mount::{deserialize_optional_size, deserialize_size},
+        validation,
+    },
+    seccomp::{Profile, Seccomp},
+};
 
 /// Runtime configuration
 #[derive(Clone, Debug, Deserialize)]
@@ -40,13 +50,276 @@ pub struct Config {
     /// Token validity
     #[serde(with = "humantime_serde", default = "default_token_validity")]
     pub token_validity: time::Duration,
+    /// Time between SIGTERM and SIGKILL when a container is stopped after exceeding
+    /// its deadline
+    #[serde(with = "humantime_serde", default = "default_stop_timeout")]
+    pub stop_timeout: time::Duration,
     /// Repositories
     #[serde(default)]
     pub repositories: HashMap<RepositoryId, Repository>,
+    /// User namespace id ranges. Containers can only opt into a user namespace if
+    /// this section is present.
+    pub user_namespace: Option<UserNamespace>,
+    /// Bridge for containers with `network: bridge`. Containers can only use the
+    /// bridge network if this section is present.
+    pub bridge: Option<Bridge>,
+    /// Seccomp profiles that containers can reference by name with `profile: !named <name>`.
+    /// The files contain a seccomp configuration in the format of the manifest.
+    #[serde(default)]
+    pub seccomp_profiles: HashMap<String, PathBuf>,
+    /// Size limited persist mounts
+    #[serde(default)]
+    pub persist: Persist,
+    /// Named volumes that containers mount with `type: volume`
+    #[serde(default)]
+    pub volumes: HashMap<Name, Volume>,
+    /// Syslog socket for container output with `syslog`
+    #[serde(default)]
+    pub syslog: Syslog,
+    /// Runtime log output
+    #[serde(default)]
+    pub log: Log,
     /// Debugging options
     pub debug: Option<Debug>,
 }
 
+/// Host uid and gid ranges used for containers with a user namespace. The ranges
+/// are split into slots of `size` ids. Each container with a user namespace gets a
+/// slot of its own and the uid and gid 0 of the container is mapped to the first
+/// id of the slot.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct UserNamespace {
+    /// First host uid of the range
+    pub uid: u32,
+    /// First host gid of the range
+    pub gid: u32,
+    /// Number of host uids and gids in the range
+    pub count: u32,
+    /// Number of ids mapped into a single container. Default: 65536
+    #[serde(default = "default_user_namespace_size")]
+    pub size: u32,
+}
+
+impl UserNamespace {
+    /// Number of containers that can run with a user namespace at the same time
+    pub fn slots(&self) -> u32 {
+        self.count / self.size
+    }
+}
+
+/// Bridge that connects the network namespaces of containers with the host. The
+/// bridge is created on startup if it does not exist. The first host address of
+/// `subnet` is assigned to the bridge and is the default gateway of the containers.
+/// Containers get the following addresses of the subnet assigned.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Bridge {
+    /// Name of the bridge interface. Default: northstar0
+    #[serde(default = "default_bridge_name")]
+    pub name: String,
+    /// IPv4 subnet in CIDR notation, e.g. "172.30.0.0/24"
+    #[serde(deserialize_with = "subnet")]
+    pub subnet: Subnet,
+    /// Nameservers in the resolv.conf of containers attached to the bridge. If empty the
+    /// nameservers of the host are used without the loopback addresses that are not
+    /// reachable from the network namespace of a container.
+    #[serde(default)]
+    pub nameservers: Vec<IpAddr>,
+}
+
+/// IPv4 subnet
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Subnet {
+    /// Network address
+    pub address: Ipv4Addr,
+    /// Prefix length
+    pub prefix: u8,
+}
+
+impl Subnet {
+    /// Number of addresses in the subnet including the network and broadcast address
+    pub fn size(&self) -> u64 {
+        1 << (32 - self.prefix as u32)
+    }
+
+    /// The `n`th address of the subnet
+    pub fn host(&self, n: u32) -> anyhow::Result<Ipv4Addr> {
+        if u64::from(n) >= self.size() {
+            bail!("host number {} is out of the range of {}", n, self);
+        }
+        Ok(Ipv4Addr::from(u32::from(self.address) + n))
+    }
+
+    /// The first host address of the subnet that is assigned to the bridge
+    pub fn gateway(&self) -> anyhow::Result<Ipv4Addr> {
+        self.host(1)
+    }
+}
+
+impl FromStr for Subnet {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (address, prefix) = s
+            .split_once('/')
+            .ok_or_else(|| anyhow::anyhow!("missing prefix length in {}", s))?;
+        let address = address.parse().context("invalid address")?;
+        let prefix = prefix.parse().context("invalid prefix length")?;
+        if prefix > 32 {
+            bail!("invalid prefix length {}", prefix);
+        }
+        let subnet = Subnet { address, prefix };
+        // The network address, gateway, one container and the broadcast address
+        if prefix > 30 {
+            bail!("subnet {} is too small", subnet);
+        }
+        let mask = u32::MAX.checked_shl(32 - prefix as u32).unwrap_or(0);
+        if u32::from(address) & !mask != 0 {
+            bail!("{} is not the network address of the subnet", subnet);
+        }
+        Ok(subnet)
+    }
+}
+
+impl fmt::Display for Subnet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.address, self.prefix)
+    }
+}
+
+/// Size limited persist mounts. The filesystem image of a container is created in
+/// `data_dir` and loop mounted on its persist directory while the container runs.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Persist {
+    /// Command that formats new images. The image path is passed as the last argument.
+    /// Default: mkfs.ext4
+    #[serde(default = "default_persist_mkfs")]
+    pub mkfs: PathBuf,
+    /// Additional mkfs command line flags
+    pub mkfs_flags: Option<String>,
+    /// Filesystem type created by `mkfs`. Default: ext4
+    #[serde(default = "default_persist_fstype")]
+    pub fstype: String,
+    /// Usage in percent of the size at which a notification is sent. Default: 90
+    #[serde(default = "default_persist_usage_threshold")]
+    pub usage_threshold: u8,
+    /// Interval of the usage check. Default: 10s
+    #[serde(with = "humantime_serde", default = "default_persist_usage_interval")]
+    pub usage_interval: time::Duration,
+}
+
+impl Default for Persist {
+    fn default() -> Self {
+        Persist {
+            mkfs: default_persist_mkfs(),
+            mkfs_flags: None,
+            fstype: default_persist_fstype(),
+            usage_threshold: default_persist_usage_threshold(),
+            usage_interval: default_persist_usage_interval(),
+        }
+    }
+}
+
+/// Named volume shared between containers. The backing directory is created if it does
+/// not exist and is owned by `uid` and `gid` with mode 0770. A volume with a size is a
+/// filesystem image in `data_dir` that is loop mounted on the backing directory while
+/// at least one container uses it. The image is formatted with the `persist` settings.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Volume {
+    /// Backing directory in the host filesystem
+    pub dir: PathBuf,
+    /// Size limit in bytes
+    #[serde(default, deserialize_with = "deserialize_optional_size")]
+    pub size: Option<u64>,
+    /// Owner of the backing directory. Default: 0
+    #[serde(default)]
+    pub uid: u32,
+    /// Group of the backing directory. Default: 0
+    #[serde(default)]
+    pub gid: u32,
+    /// Containers that are allowed to mount the volume
+    #[serde(default)]
+    pub containers: HashSet<Name>,
+}
+
+/// Socket that receives the container output that is configured with `syslog`
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Syslog {
+    /// Path of the datagram socket. Default: /dev/log
+    #[serde(default = "default_syslog_path")]
+    pub path: PathBuf,
+    /// Message format. Default: rfc3164
+    #[serde(default)]
+    pub format: SyslogFormat,
+}
+
+impl Default for Syslog {
+    fn default() -> Self {
+        Syslog {
+            path: default_syslog_path(),
+            format: SyslogFormat::default(),
+        }
+    }
+}
+
+/// Message format of the syslog socket
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyslogFormat {
+    /// RFC 3164 messages as sent by syslog(3) of the C library. Understood by journald
+    /// and syslog daemons on `/dev/log`.
+    #[default]
+    Rfc3164,
+    /// RFC 5424 messages with the container fields as structured data
+    Rfc5424,
+    /// Native journald protocol with the container fields as journal fields, e.g.
+    /// for `/run/systemd/journal/socket`
+    Journald,
+}
+
+/// Runtime log output. The log is written to stdout and optionally to `northstar.log`
+/// in `log_dir`.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Log {
+    /// Format of the log lines. Default: text
+    #[serde(default)]
+    pub format: LogFormat,
+    /// Write the log to a size rotated file in `log_dir` as well
+    pub file: Option<LogFile>,
+}
+
+/// Format of the runtime log lines
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogFormat {
+    /// Human readable lines
+    #[default]
+    Text,
+    /// JSON object per line with timestamp, level, target, container and message
+    Json,
+}
+
+/// Log file `northstar.log` in `log_dir`. The file is renamed to `northstar.log.1`
+/// when it exceeds `max_size`. Older files are shifted to `northstar.log.2` and so on.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LogFile {
+    /// Size in bytes or with unit (e.g. 10M) at which the file is rotated. Default: 10M
+    #[serde(
+        default = "default_log_file_max_size",
+        deserialize_with = "deserialize_size"
+    )]
+    pub max_size: u64,
+    /// Number of rotated files that are kept. Default: 3
+    #[serde(default = "default_log_file_max_files")]
+    pub max_files: u32,
+}
+
 /// Repository type
 #[derive(Clone, Debug, Deserialize)]
 pub enum RepositoryType {
@@ -85,6 +358,8 @@ pub struct Debug {
     pub strace: Option<debug::Strace>,
     /// perf options
     pub perf: Option<debug::Perf>,
+    /// Seccomp options
+    pub seccomp: Option<debug::Seccomp>,
 }
 
 /// Container debug facilities
@@ -125,6 +400,16 @@ pub mod debug {
         /// Optional additional flags
         pub flags: Option<String>,
     }
+
+    /// Seccomp debug options
+    #[derive(Clone, Debug, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    pub struct Seccomp {
+        /// Record the system calls of containers that are not allowed by their seccomp
+        /// configuration instead of killing the container
+        #[serde(default)]
+        pub learn: bool,
+    }
 }
 
 impl Config {
@@ -133,6 +418,84 @@ impl Config {
         check_rw_directory(&self.run_dir).context("checking run_dir")?;
         check_rw_directory(&self.data_dir).context("checking data_dir")?;
         check_rw_directory(&self.log_dir).context("checking log_dir")?;
+        if let Some(user_namespace) = &self.user_namespace {
+            user_namespace.check().context("checking user_namespace")?;
+        }
+        if let Some(bridge) = &self.bridge {
+            bridge.check().context("checking bridge")?;
+        }
+        if !(1..=100).contains(&self.persist.usage_threshold) {
+            bail!("persist usage_threshold must be between 1 and 100");
+        }
+        if let Some(LogFile { max_size: 0, .. }) = self.log.file {
+            bail!("log file max_size must not be zero");
+        }
+        for (name, volume) in &self.volumes {
+            if !volume.dir.is_absolute() {
+                bail!("volume {} directory must be absolute", name);
+            }
+            if volume.size == Some(0) {
+                bail!("volume {} size must not be zero", name);
+            }
+        }
+        for name in self.seccomp_profiles.keys() {
+            self.seccomp_profile(name)
+                .with_context(|| format!("checking seccomp profile {}", name))?;
+        }
+        Ok(())
+    }
+
+    /// Load the seccomp profile `name` from the file configured in `seccomp_profiles`
+    pub(crate) fn seccomp_profile(&self, name: &str) -> anyhow::Result<Seccomp> {
+        let path = self
+            .seccomp_profiles
+            .get(name)
+            .with_context(|| format!("unknown seccomp profile {}", name))?;
+        let file = std::fs::File::open(path)
+            .with_context(|| format!("failed to open {}", path.display()))?;
+        let seccomp: Seccomp = serde_yaml::from_reader(file)
+            .with_context(|| format!("failed to parse {}", path.display()))?;
+        if let Some(Profile::Named(_)) = seccomp.profile {
+            bail!("{} references another named profile", path.display());
+        }
+        validation::seccomp(&seccomp)
+            .map_err(|e| anyhow::anyhow!("invalid seccomp profile {}: {}", path.display(), e))?;
+        Ok(seccomp)
+    }
+}
+
+impl UserNamespace {
+    fn check(&self) -> anyhow::Result<()> {
+        if self.size == 0 {
+            bail!("size must be greater than 0");
+        }
+        if self.uid == 0 || self.gid == 0 {
+            bail!("uid and gid ranges must not include the host root");
+        }
+        if self.slots() == 0 {
+            bail!("count {} is less than size {}", self.count, self.size);
+        }
+        if self.uid.checked_add(self.count).is_none() || self.gid.checked_add(self.count).is_none()
+        {
+            bail!("uid or gid range exceeds the maximum id");
+        }
+        Ok(())
+    }
+}
+
+impl Bridge {
+    fn check(&self) -> anyhow::Result<()> {
+        // Interface names are limited to IFNAMSIZ - 1 characters
+        if self.name.is_empty() || self.name.len() > 15 {
+            bail!("invalid interface name {}", self.name);
+        }
+        if self
+            .name
+            .chars()
+            .any(|c| c == '/' || c == ':' || c.is_whitespace() || c.is_control())
+        {
+            bail!("invalid interface name {}", self.name);
+        }
         Ok(())
     }
 }
@@ -182,6 +545,15 @@ where
     }
 }
 
+/// Parse a subnet in CIDR notation
+fn subnet<'de, D>(deserializer: D) -> Result<Subnet, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let subnet = String::deserialize(deserializer)?;
+    Subnet::from_str(&subnet).map_err(|e| D::Error::custom(format!("{:#}", e)))
+}
+
 const fn default_device_mapper_timeout() -> time::Duration {
     time::Duration::from_secs(10)
 }
@@ -198,10 +570,50 @@ const fn default_notification_buffer_size() -> usize {
     128
 }
 
+const fn default_user_namespace_size() -> u32 {
+    65536
+}
+
+fn default_bridge_name() -> String {
+    "northstar0".into()
+}
+
 const fn default_token_validity() -> time::Duration {
     time::Duration::from_secs(60)
 }
 
+const fn default_stop_timeout() -> time::Duration {
+    time::Duration::from_secs(5)
+}
+
+fn default_persist_mkfs() -> PathBuf {
+    PathBuf::from("mkfs.ext4")
+}
+
+fn default_persist_fstype() -> String {
+    "ext4".into()
+}
+
+const fn default_persist_usage_threshold() -> u8 {
+    90
+}
+
+const fn default_persist_usage_interval() -> time::Duration {
+    time::Duration::from_secs(10)
+}
+
+fn default_syslog_path() -> PathBuf {
+    PathBuf::from("/dev/log")
+}
+
+const fn default_log_file_max_size() -> u64 {
+    10_000_000
+}
+
+const fn default_log_file_max_files() -> u32 {
+    3
+}
+
 #[test]
 #[allow(clippy::unwrap_used)]
 fn console_url() {
@@ -230,3 +642,305 @@ console = "http://localhost:4200"
 
     assert!(toml::from_str::<Config>(config).is_err());
 }
+
+#[test]
+#[allow(clippy::unwrap_used)]
+fn user_namespace() {
+    let config = r#"
+run_dir = "target/northstar/run"
+data_dir = "target/northstar/data"
+log_dir = "target/northstar/logs"
+cgroup = "northstar"
+
+[user_namespace]
+uid = 100000
+gid = 100000
+count = 655360
+"#;
+
+    let config = toml::from_str::<Config>(config).unwrap();
+    let user_namespace = config.user_namespace.unwrap();
+    assert_eq!(user_namespace.size, 65536);
+    assert_eq!(user_namespace.slots(), 10);
+    assert!(user_namespace.check().is_ok());
+
+    let user_namespace = UserNamespace {
+        count: 1000,
+        ..user_namespace
+    };
+    assert!(user_namespace.check().is_err());
+}
+
+#[test]
+#[allow(clippy::unwrap_used)]
+fn persist() {
+    let config = r#"
+run_dir = "target/northstar/run"
+data_dir = "target/northstar/data"
+log_dir = "target/northstar/logs"
+cgroup = "northstar"
+"#;
+    let config = toml::from_str::<Config>(config).unwrap();
+    assert_eq!(config.persist.mkfs, PathBuf::from("mkfs.ext4"));
+    assert_eq!(config.persist.fstype, "ext4");
+    assert_eq!(config.persist.usage_threshold, 90);
+
+    let config = r#"
+run_dir = "target/northstar/run"
+data_dir = "target/northstar/data"
+log_dir = "target/northstar/logs"
+cgroup = "northstar"
+
+[persist]
+mkfs = "/sbin/mkfs.f2fs"
+mkfs_flags = "-q"
+fstype = "f2fs"
+usage_threshold = 75
+usage_interval = "1m"
+"#;
+    let config = toml::from_str::<Config>(config).unwrap();
+    assert_eq!(config.persist.fstype, "f2fs");
+    assert_eq!(config.persist.usage_threshold, 75);
+    assert_eq!(config.persist.usage_interval, time::Duration::from_secs(60));
+}
+
+#[test]
+#[allow(clippy::unwrap_used)]
+fn bridge() {
+    let config = r#"
+run_dir = "target/northstar/run"
+data_dir = "target/northstar/data"
+log_dir = "target/northstar/logs"
+cgroup = "northstar"
+
+[bridge]
+subnet = "172.30.0.0/24"
+nameservers = ["8.8.8.8", "2001:4860:4860::8888"]
+"#;
+
+    let config = toml::from_str::<Config>(config).unwrap();
+    let bridge = config.bridge.unwrap();
+    assert_eq!(bridge.name, "northstar0");
+    assert_eq!(bridge.subnet.address, Ipv4Addr::new(172, 30, 0, 0));
+    assert_eq!(bridge.subnet.prefix, 24);
+    assert_eq!(bridge.subnet.size(), 256);
+    assert_eq!(
+        bridge.nameservers,
+        vec![
+            IpAddr::from([8, 8, 8, 8]),
+            "2001:4860:4860::8888".parse::<IpAddr>().unwrap()
+        ]
+    );
+    assert_eq!(
+        bridge.subnet.gateway().unwrap(),
+        Ipv4Addr::new(172, 30, 0, 1)
+    );
+    assert!(bridge.check().is_ok());
+
+    // Invalid name
+    let bridge = Bridge {
+        name: "northstar-bridge0".into(),
+        ..bridge
+    };
+    assert!(bridge.check().is_err());
+}
+
+#[test]
+#[allow(clippy::unwrap_used)]
+fn subnet() {
+    let subnet = "10.0.0.0/30".parse::<Subnet>().unwrap();
+    assert_eq!(subnet.size(), 4);
+    assert_eq!(subnet.gateway().unwrap(), Ipv4Addr::new(10, 0, 0, 1));
+    assert_eq!(subnet.host(3).unwrap(), Ipv4Addr::new(10, 0, 0, 3));
+    assert!(subnet.host(4).is_err());
+
+    let subnet = "0.0.0.0/0".parse::<Subnet>().unwrap();
+    assert_eq!(subnet.host(1).unwrap(), Ipv4Addr::new(0, 0, 0, 1));
+
+    // Missing or invalid prefix length
+    assert!("172.30.0.0".parse::<Subnet>().is_err());
+    assert!("172.30.0.0/33".parse::<Subnet>().is_err());
+    assert!("172.30.0.0/x".parse::<Subnet>().is_err());
+    // Host part set
+    assert!("10.0.0.5/24".parse::<Subnet>().is_err());
+    assert!("172.30.0.1/24".parse::<Subnet>().is_err());
+    // Too small
+    assert!("172.30.0.0/31".parse::<Subnet>().is_err());
+    assert!("172.30.0.0/32".parse::<Subnet>().is_err());
+}
+
+#[test]
+#[allow(clippy::unwrap_used)]
+fn seccomp_profiles() {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let dir = tmpdir.path();
+    let platform = dir.join("platform.yaml");
+    std::fs::write(
+        &platform,
+        "profile: default\ndefault_action: !errno 1\ndeny:\n  ptrace: kill_thread\n",
+    )
+    .unwrap();
+    let nested = dir.join("nested.yaml");
+    std::fs::write(&nested, "profile: !named platform\n").unwrap();
+
+    let config = format!(
+        r#"
+run_dir = "target/northstar/run"
+data_dir = "target/northstar/data"
+log_dir = "target/northstar/logs"
+cgroup = "northstar"
+
+[seccomp_profiles]
+platform = "{}"
+nested = "{}"
+"#,
+        platform.display(),
+        nested.display()
+    );
+
+    let config = toml::from_str::<Config>(&config).unwrap();
+    let profile = config.seccomp_profile("platform").unwrap();
+    assert_eq!(profile.profile, Some(Profile::Default));
+    assert_eq!(
+        profile.default_action,
+        Some(crate::seccomp::Action::Errno(1))
+    );
+    assert!(config.seccomp_profile("nested").is_err());
+    assert!(config.seccomp_profile("unknown").is_err());
+}
+
+#[test]
+#[allow(clippy::unwrap_used)]
+fn volumes() {
+    let config = r#"
+run_dir = "target/northstar/run"
+data_dir = "target/northstar/data"
+log_dir = "target/northstar/logs"
+cgroup = "northstar"
+
+[volumes.exchange]
+dir = "/data/exchange"
+size = "10MB"
+uid = 1000
+gid = 1000
+containers = ["producer", "consumer"]
+
+[volumes.cache]
+dir = "/data/cache"
+size = 4096
+"#;
+
+    let config = toml::from_str::<Config>(config).unwrap();
+    let exchange = &config.volumes[&"exchange".try_into().unwrap()];
+    assert_eq!(exchange.dir, Path::new("/data/exchange"));
+    assert_eq!(exchange.size, Some(10000000));
+    assert_eq!(exchange.uid, 1000);
+    assert_eq!(exchange.gid, 1000);
+    assert!(exchange
+        .containers
+        .contains(&"producer".try_into().unwrap()));
+    assert!(exchange
+        .containers
+        .contains(&"consumer".try_into().unwrap()));
+    let cache = &config.volumes[&"cache".try_into().unwrap()];
+    assert_eq!(cache.size, Some(4096));
+    assert_eq!(cache.uid, 0);
+    assert!(cache.containers.is_empty());
+
+    // Invalid volume name
+    let config = r#"
+run_dir = "target/northstar/run"
+data_dir = "target/northstar/data"
+log_dir = "target/northstar/logs"
+cgroup = "northstar"
+
+[volumes."ex/change"]
+dir = "/data/exchange"
+"#;
+    assert!(toml::from_str::<Config>(config).is_err());
+}
+
+#[test]
+#[allow(clippy::unwrap_used)]
+fn syslog() {
+    let config = r#"
+run_dir = "target/northstar/run"
+data_dir = "target/northstar/data"
+log_dir = "target/northstar/logs"
+cgroup = "northstar"
+"#;
+    let config = toml::from_str::<Config>(config).unwrap();
+    assert_eq!(config.syslog.path, Path::new("/dev/log"));
+    assert_eq!(config.syslog.format, SyslogFormat::Rfc3164);
+
+    let config = r#"
+run_dir = "target/northstar/run"
+data_dir = "target/northstar/data"
+log_dir = "target/northstar/logs"
+cgroup = "northstar"
+
+[syslog]
+path = "/run/systemd/journal/socket"
+format = "journald"
+"#;
+    let config = toml::from_str::<Config>(config).unwrap();
+    assert_eq!(config.syslog.path, Path::new("/run/systemd/journal/socket"));
+    assert_eq!(config.syslog.format, SyslogFormat::Journald);
+}
+
+#[test]
+#[allow(clippy::unwrap_used)]
+fn log() {
+    let config = r#"
+run_dir = "target/northstar/run"
+data_dir = "target/northstar/data"
+log_dir = "target/northstar/logs"
+cgroup = "northstar"
+"#;
+    let config = toml::from_str::<Config>(config).unwrap();
+    assert_eq!(config.log.format, LogFormat::Text);
+    assert!(config.log.file.is_none());
+
+    let config = r#"
+run_dir = "target/northstar/run"
+data_dir = "target/northstar/data"
+log_dir = "target/northstar/logs"
+cgroup = "northstar"
+
+[log]
+format = "json"
+
+[log.file]
+"#;
+    let config = toml::from_str::<Config>(config).unwrap();
+    assert_eq!(config.log.format, LogFormat::Json);
+    let file = config.log.file.unwrap();
+    assert_eq!(file.max_size, 10_000_000);
+    assert_eq!(file.max_files, 3);
+
+    let config = r#"
+run_dir = "target/northstar/run"
+data_dir = "target/northstar/data"
+log_dir = "target/northstar/logs"
+cgroup = "northstar"
+
+[log.file]
+max_size = "1K"
+max_files = 0
+"#;
+    let config = toml::from_str::<Config>(config).unwrap();
+    let file = config.log.file.unwrap();
+    assert_eq!(file.max_size, 1000);
+    assert_eq!(file.max_files, 0);
+
+    let config = r#"
+run_dir = "target/northstar/run"
+data_dir = "target/northstar/data"
+log_dir = "target/northstar/logs"
+cgroup = "northstar"
+
+[log]
+format = "yaml"
+"#;
+    assert!(toml::from_str::<Config>(config).is_err());
+}
@@ -11,14 +11,14 @@ use bytes::{Buf, Bytes};
 use futures::{
     future::join_all,
     stream::{self, FuturesUnordered},
-    Future, StreamExt,
+    Future, SinkExt, StreamExt,
 };
 use listener::Listener;
 use log::{debug, info, trace, warn};
 use semver::Comparator;
 use std::{cmp::min, fmt, path::Path, unreachable};
 use tokio::{
-    io::{self, AsyncRead, AsyncReadExt, AsyncWrite},
+    io::{self, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
     pin, select,
     sync::{broadcast, mpsc, oneshot},
     task, time,
@@ -45,6 +45,7 @@ const DEFAULT_NPK_STREAM_TIMEOUT: u64 = 5;
 pub(crate) enum Request {
     Request(model::Request),
     Install(RepositoryId, mpsc::Receiver<Bytes>),
+    Attach(Container, mpsc::Receiver<Bytes>, mpsc::Sender<Bytes>),
 }
 
 /// A console is responsible for monitoring and serving incoming client connections
@@ -290,7 +291,9 @@ impl Console {
                         Some(Ok(model::Message::Request { request })) => {
                             trace!("{}: --> {:?}", peer, request);
                             let response = match process_request(&peer, &mut stream, &stop, &configuration, &event_tx, token_validity, request).await {
-                                Ok(response) => response,
+                                Ok(Some(response)) => response,
+                                // The connection was attached to a terminal and is closed afterwards
+                                Ok(None) => break,
                                 Err(e) => {
                                     warn!("Failed to process request: {}", e);
                                     break;
@@ -330,6 +333,9 @@ impl Console {
 /// If the streamed NPK is not valid and parseable a `Error::Npk(..)` is returned.
 /// If the event loop is closed due to shutdown, this function will return `Error::EventLoopClosed`.
 ///
+/// `None` is returned if the connection was attached to the terminal of a container. The
+/// connection cannot be used for requests afterwards.
+///
 async fn process_request<S>(
     peer: &Peer,
     stream: &mut Framed<S>,
@@ -338,18 +344,21 @@ async fn process_request<S>(
     event_loop: &EventTx,
     token_validity: time::Duration,
     request: model::Request,
-) -> Result<model::Message>
+) -> Result<Option<model::Message>>
 where
-    S: AsyncRead + Unpin,
+    S: AsyncRead + AsyncWrite + Unpin,
 {
     let required_permission = match &request {
+        model::Request::Attach { .. } => Permission::Attach,
         model::Request::Ident { .. } => Permission::Ident,
         model::Request::Inspect { .. } => Permission::Inspect,
         model::Request::Install { .. } => Permission::Install,
         model::Request::Kill { .. } => Permission::Kill,
-        model::Request::List => Permission::List,
+        model::Request::List { .. } => Permission::List,
         model::Request::Mount { .. } => Permission::Mount,
         model::Request::Repositories => Permission::Repositories,
+        model::Request::Resize { .. } => Permission::Attach,
+        model::Request::Seccomp { .. } => Permission::Inspect,
         model::Request::Shutdown => Permission::Shutdown,
         model::Request::Start {
             arguments,
@@ -365,9 +374,9 @@ where
 
     let permissions = &configuration.permissions;
     if !permissions.contains(&required_permission) {
-        return Ok(model::Message::Response {
+        return Ok(Some(model::Message::Response {
             response: model::Response::PermissionDenied(request),
-        });
+        }));
     }
 
     let (reply_tx, reply_rx) = oneshot::channel();
@@ -408,7 +417,7 @@ where
             let event = Event::Console(request, reply_tx);
             event_loop.send(event).await?;
 
-            // The codec might have pulled bytes in the the read buffer of the connection.
+            // The codec might have pulled bytes into the read buffer of the connection.
             if !stream.read_buffer().is_empty() {
                 let available = stream.read_buffer().len();
                 // Limit the first read operation to `size` if there's more data available.
@@ -436,6 +445,38 @@ where
                 tx.send(buf).await.ok();
             }
         }
+        model::Request::Attach { container } => {
+            debug!("{}: Received attach request for {}", peer, container);
+
+            // The runtime sends the output of the terminal to `output_tx` and
+            // writes the bytes received on `input_rx` to the terminal
+            let (input_tx, input_rx) = mpsc::channel(10);
+            let (output_tx, output_rx) = mpsc::channel(10);
+            let request = Request::Attach(container, input_rx, output_tx);
+            trace!("    {:?} -> event loop", request);
+            let event = Event::Console(request, reply_tx);
+            event_loop.send(event).await?;
+
+            let response = select! {
+                reply = reply_rx => reply.context("failed to receive reply")?,
+                _ = stop.cancelled() => bail!("shutdown"),
+            };
+            trace!("    {:?} <- event loop", response);
+            if !matches!(
+                response,
+                model::Response::Attach(model::AttachResult::Ok { .. })
+            ) {
+                return Ok(Some(model::Message::Response { response }));
+            }
+
+            // Send the response and switch the connection to raw mode
+            stream
+                .send(model::Message::Response { response })
+                .await
+                .context("failed to send response")?;
+            attach(peer, stream, stop, input_tx, output_rx).await?;
+            return Ok(None);
+        }
         model::Request::TokenCreate { target, shared } => {
             let user = match peer {
                 Peer::Extern(_) => "extern",
@@ -490,7 +531,52 @@ where
         trace!("    {:?} <- event loop", response);
         response
     })
-    .map(|response| model::Message::Response { response })
+    .map(|response| Some(model::Message::Response { response }))
+}
+
+/// Forward the raw bytes of an attached connection to and from a terminal until one side
+/// closes.
+async fn attach<S>(
+    peer: &Peer,
+    stream: &mut Framed<S>,
+    stop: &CancellationToken,
+    input: mpsc::Sender<Bytes>,
+    mut output: mpsc::Receiver<Bytes>,
+) -> Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    // The codec might have pulled bytes into the read buffer of the connection.
+    if !stream.read_buffer().is_empty() {
+        let buffer = stream.read_buffer_mut().split().freeze();
+        input.send(buffer).await.ok();
+    }
+
+    let (reader, mut writer) = io::split(stream.get_mut());
+    let mut reader = ReaderStream::new(reader);
+    loop {
+        select! {
+            _ = stop.cancelled() => break,
+            buffer = reader.next() => match buffer {
+                Some(Ok(buffer)) => {
+                    if input.send(buffer).await.is_err() {
+                        break;
+                    }
+                }
+                _ => break,
+            },
+            buffer = output.recv() => match buffer {
+                Some(buffer) => writer
+                    .write_all(&buffer)
+                    .await
+                    .context("failed to write to connection")?,
+                None => break,
+            },
+        }
+    }
+
+    debug!("{}: Detached", peer);
+    Ok(())
 }
 
 /// Function to handle connections
@@ -614,8 +700,21 @@ impl From<(Container, ContainerEvent)> for model::Notification {
             ContainerEvent::Exit(status) => {
                 api::model::Notification::Exit(container, status.into())
             }
+            ContainerEvent::DeadlineExit(status) => api::model::Notification::Exit(
+                container,
+                api::model::ExitStatus::Deadline {
+                    status: Box::new(status.into()),
+                },
+            ),
             ContainerEvent::Installed => api::model::Notification::Install(container),
             ContainerEvent::Uninstalled => api::model::Notification::Uninstall(container),
+            ContainerEvent::Persist(usage) => api::model::Notification::Persist(
+                container,
+                api::model::PersistUsage {
+                    size: usage.size,
+                    used: usage.used,
+                },
+            ),
             ContainerEvent::CGroup(event) => match event {
                 super::CGroupEvent::Memory(memory) => api::model::Notification::CGroup(
                     container,
@@ -0,0 +1,307 @@
+//! Device access control via the device cgroup. Cgroup v1 uses the devices controller.
+//! Cgroup v2 uses a BPF program attached to the cgroup of the container.
+
+use crate::npk::{
+    manifest::devices::{Device, DeviceType, Permissions},
+    npk::DEV_NODES,
+};
+use anyhow::{Context, Result};
+use nix::{
+    errno::Errno,
+    fcntl::{self, OFlag},
+    libc,
+    sys::stat::Mode,
+};
+use std::{
+    mem::size_of,
+    os::unix::prelude::{AsRawFd, FromRawFd, OwnedFd, RawFd},
+    path::Path,
+};
+
+/// Major and minor number of the pty multiplexer /dev/ptmx
+const PTMX: (u32, u32) = (5, 2);
+/// Major number of pseudo terminals in /dev/pts
+const PTS_MAJOR: u32 = 136;
+
+/// Access rule of the device cgroup
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(super) struct Rule {
+    pub r#type: DeviceType,
+    pub major: u32,
+    /// Any minor number if None
+    pub minor: Option<u32>,
+    pub permissions: Permissions,
+}
+
+/// Rules granting access to the nodes of a `dev` mount, pseudo terminals and `devices`
+pub(super) fn rules(devices: &[Device]) -> Vec<Rule> {
+    let rw = Permissions {
+        read: true,
+        write: true,
+        mknod: false,
+    };
+    let defaults = DEV_NODES
+        .iter()
+        .map(|(_, major, minor)| (*major as u32, Some(*minor as u32)))
+        .chain([(PTMX.0, Some(PTMX.1)), (PTS_MAJOR, None)])
+        .map(|(major, minor)| Rule {
+            r#type: DeviceType::Char,
+            major,
+            minor,
+            permissions: rw,
+        });
+
+    defaults
+        .chain(devices.iter().map(|device| Rule {
+            r#type: device.r#type,
+            major: device.major,
+            minor: Some(device.minor),
+            permissions: device.permissions,
+        }))
+        .collect()
+}
+
+const BPF_PROG_LOAD: libc::c_int = 5;
+const BPF_PROG_ATTACH: libc::c_int = 8;
+const BPF_PROG_TYPE_CGROUP_DEVICE: u32 = 15;
+const BPF_CGROUP_DEVICE: u32 = 6;
+const BPF_F_ALLOW_MULTI: u32 = 2;
+
+const BPF_DEVCG_ACC_MKNOD: i32 = 1;
+const BPF_DEVCG_ACC_READ: i32 = 2;
+const BPF_DEVCG_ACC_WRITE: i32 = 4;
+const BPF_DEVCG_DEV_BLOCK: i32 = 1;
+const BPF_DEVCG_DEV_CHAR: i32 = 2;
+
+/// r = *(u32 *)(src + off)
+const LDX_W: u8 = 0x61;
+/// r = src
+const MOV64_X: u8 = 0xbf;
+/// r = imm
+const MOV64_K: u8 = 0xb7;
+/// r &= imm
+const AND32_K: u8 = 0x54;
+/// r >>= imm
+const RSH32_K: u8 = 0x74;
+/// if r != imm goto pc + off
+const JNE_K: u8 = 0x55;
+/// return r0
+const EXIT: u8 = 0x95;
+
+/// struct bpf_insn
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) struct Instruction {
+    code: u8,
+    /// Source register in the upper and destination register in the lower nibble
+    regs: u8,
+    off: i16,
+    imm: i32,
+}
+
+fn insn(code: u8, dst: u8, src: u8, off: i16, imm: i32) -> Instruction {
+    Instruction {
+        code,
+        regs: src << 4 | dst,
+        off,
+        imm,
+    }
+}
+
+/// Device program that allows access to the devices matching `rules`. The program is
+/// called with a struct bpf_cgroup_dev_ctx { access_type, major, minor } in r1 and
+/// returns 1 to allow the access.
+pub(super) fn program(rules: &[Rule]) -> Vec<Instruction> {
+    let mut program = vec![
+        // r2 = device type, r3 = requested access
+        insn(LDX_W, 2, 1, 0, 0),
+        insn(MOV64_X, 3, 2, 0, 0),
+        insn(AND32_K, 2, 0, 0, 0xffff),
+        insn(RSH32_K, 3, 0, 0, 16),
+        // r4 = major, r5 = minor
+        insn(LDX_W, 4, 1, 4, 0),
+        insn(LDX_W, 5, 1, 8, 0),
+    ];
+
+    for rule in rules {
+        let r#type = match rule.r#type {
+            DeviceType::Char => BPF_DEVCG_DEV_CHAR,
+            DeviceType::Block => BPF_DEVCG_DEV_BLOCK,
+        };
+        let mut access = 0;
+        if rule.permissions.read {
+            access |= BPF_DEVCG_ACC_READ;
+        }
+        if rule.permissions.write {
+            access |= BPF_DEVCG_ACC_WRITE;
+        }
+        if rule.permissions.mknod {
+            access |= BPF_DEVCG_ACC_MKNOD;
+        }
+
+        // Jumps are filled with the number of instructions to the next rule
+        let mut block = vec![
+            insn(JNE_K, 2, 0, 0, r#type),
+            // Requested access that is not allowed
+            insn(MOV64_X, 6, 3, 0, 0),
+            insn(AND32_K, 6, 0, 0, !access),
+            insn(JNE_K, 6, 0, 0, 0),
+            insn(JNE_K, 4, 0, 0, rule.major as i32),
+        ];
+        if let Some(minor) = rule.minor {
+            block.push(insn(JNE_K, 5, 0, 0, minor as i32));
+        }
+        block.push(insn(MOV64_K, 0, 0, 0, 1));
+        block.push(insn(EXIT, 0, 0, 0, 0));
+
+        let len = block.len();
+        for (n, instruction) in block.iter_mut().enumerate() {
+            if instruction.code == JNE_K {
+                instruction.off = (len - n - 1) as i16;
+            }
+        }
+        program.extend(block);
+    }
+
+    // Deny everything else
+    program.push(insn(MOV64_K, 0, 0, 0, 0));
+    program.push(insn(EXIT, 0, 0, 0, 0));
+    program
+}
+
+/// Load the device program for `rules` and attach it to the cgroup v2 directory `cgroup`
+pub(super) fn attach_program(cgroup: &Path, rules: &[Rule]) -> Result<()> {
+    /// Leading part of union bpf_attr for BPF_PROG_LOAD
+    #[repr(C)]
+    struct ProgLoadAttr {
+        prog_type: u32,
+        insn_cnt: u32,
+        insns: u64,
+        license: u64,
+        log_level: u32,
+        log_size: u32,
+        log_buf: u64,
+        kern_version: u32,
+        prog_flags: u32,
+    }
+
+    /// Leading part of union bpf_attr for BPF_PROG_ATTACH
+    #[repr(C)]
+    struct ProgAttachAttr {
+        target_fd: u32,
+        attach_bpf_fd: u32,
+        attach_type: u32,
+        attach_flags: u32,
+    }
+
+    let program = program(rules);
+    let license = b"Apache-2.0\0";
+    let attr = ProgLoadAttr {
+        prog_type: BPF_PROG_TYPE_CGROUP_DEVICE,
+        insn_cnt: program.len() as u32,
+        insns: program.as_ptr() as u64,
+        license: license.as_ptr() as u64,
+        log_level: 0,
+        log_size: 0,
+        log_buf: 0,
+        kern_version: 0,
+        prog_flags: 0,
+    };
+    let result = unsafe {
+        libc::syscall(
+            libc::SYS_bpf,
+            BPF_PROG_LOAD,
+            &attr,
+            size_of::<ProgLoadAttr>(),
+        )
+    };
+    let result = Errno::result(result).context("failed to load device program")?;
+    let program = unsafe { OwnedFd::from_raw_fd(result as RawFd) };
+
+    let flags = OFlag::O_RDONLY | OFlag::O_DIRECTORY | OFlag::O_CLOEXEC;
+    let cgroup = fcntl::open(cgroup, flags, Mode::empty())
+        .with_context(|| format!("failed to open {}", cgroup.display()))?;
+    let cgroup = unsafe { OwnedFd::from_raw_fd(cgroup) };
+
+    // The program stays attached until the cgroup is removed
+    let attr = ProgAttachAttr {
+        target_fd: cgroup.as_raw_fd() as u32,
+        attach_bpf_fd: program.as_raw_fd() as u32,
+        attach_type: BPF_CGROUP_DEVICE,
+        attach_flags: BPF_F_ALLOW_MULTI,
+    };
+    let result = unsafe {
+        libc::syscall(
+            libc::SYS_bpf,
+            BPF_PROG_ATTACH,
+            &attr,
+            size_of::<ProgAttachAttr>(),
+        )
+    };
+    Errno::result(result).context("failed to attach device program")?;
+    Ok(())
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod test {
+    use super::*;
+
+    /// Run `program` for a device access and return the result
+    fn run(program: &[Instruction], r#type: i32, access: i32, major: u32, minor: u32) -> u64 {
+        let ctx = [(access << 16 | r#type) as u32, major, minor];
+        let mut regs = [0u64; 11];
+        let mut pc = 0;
+        loop {
+            let i = program[pc];
+            let (dst, src) = ((i.regs & 0xf) as usize, (i.regs >> 4) as usize);
+            pc += 1;
+            match i.code {
+                LDX_W => {
+                    assert_eq!(src, 1);
+                    regs[dst] = ctx[i.off as usize / 4] as u64;
+                }
+                MOV64_X => regs[dst] = regs[src],
+                MOV64_K => regs[dst] = i.imm as i64 as u64,
+                AND32_K => regs[dst] = (regs[dst] as u32 & i.imm as u32) as u64,
+                RSH32_K => regs[dst] = (regs[dst] as u32 >> i.imm) as u64,
+                JNE_K if regs[dst] != i.imm as i64 as u64 => pc += i.off as usize,
+                JNE_K => (),
+                EXIT => break regs[0],
+                code => panic!("unsupported instruction {:#x}", code),
+            }
+        }
+    }
+
+    #[test]
+    fn device_program() {
+        let device = Device {
+            path: "/dev/ttyS1".try_into().unwrap(),
+            r#type: DeviceType::Char,
+            major: 4,
+            minor: 65,
+            permissions: "r".parse().unwrap(),
+        };
+        let program = program(&rules(&[device]));
+        let char = BPF_DEVCG_DEV_CHAR;
+
+        // Default nodes
+        assert_eq!(run(&program, char, BPF_DEVCG_ACC_READ, 1, 3), 1);
+        assert_eq!(run(&program, char, BPF_DEVCG_ACC_WRITE, 1, 3), 1);
+        assert_eq!(run(&program, char, BPF_DEVCG_ACC_MKNOD, 1, 3), 0);
+        assert_eq!(run(&program, char, BPF_DEVCG_ACC_READ, PTS_MAJOR, 7), 1);
+        assert_eq!(
+            run(&program, BPF_DEVCG_DEV_BLOCK, BPF_DEVCG_ACC_READ, 1, 3),
+            0
+        );
+
+        // Listed device
+        assert_eq!(run(&program, char, BPF_DEVCG_ACC_READ, 4, 65), 1);
+        let rw = BPF_DEVCG_ACC_READ | BPF_DEVCG_ACC_WRITE;
+        assert_eq!(run(&program, char, rw, 4, 65), 0);
+        assert_eq!(run(&program, char, BPF_DEVCG_ACC_READ, 4, 64), 0);
+
+        // Unlisted device
+        assert_eq!(run(&program, char, BPF_DEVCG_ACC_READ, 10, 200), 0);
+    }
+}
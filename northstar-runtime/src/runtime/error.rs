@@ -2,7 +2,8 @@ use thiserror::Error;
 
 use crate::{
     api::{self},
-    common::name::Name,
+    common::{name::Name, version::Version},
+    npk::manifest::arch::Arch,
     runtime::{Container, ExitStatus, RepositoryId},
 };
 
@@ -38,6 +39,10 @@ pub enum Error {
     InstallDuplicate(Container),
     #[error("critical container failure")]
     CriticalContainer(Container, ExitStatus),
+    #[error("container {0} is built for {1} but the runtime runs on {2}")]
+    ArchMismatch(Container, Arch, Arch),
+    #[error("container {0} requires runtime version {1} but runtime version is {2}")]
+    RuntimeVersionMismatch(Container, Version, Version),
 
     #[error(transparent)]
     Unexpected(#[from] anyhow::Error),
@@ -84,6 +89,18 @@ impl From<Error> for api::model::Error {
                 container,
                 status: status.into(),
             },
+            Error::ArchMismatch(container, arch, host) => api::model::Error::ArchMismatch {
+                container,
+                arch,
+                host,
+            },
+            Error::RuntimeVersionMismatch(container, required, runtime) => {
+                api::model::Error::RuntimeVersionMismatch {
+                    container,
+                    required,
+                    runtime,
+                }
+            }
             Error::Unexpected(error) => api::model::Error::Unexpected {
                 error: error.to_string(),
             },
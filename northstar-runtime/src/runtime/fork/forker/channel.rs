@@ -89,7 +89,7 @@ impl Channel {
 #[derive(Debug, Serialize, Deserialize)]
 enum SerdeMessage {
     CreateRequest {
-        init: Init,
+        init: Box<Init>,
     },
     /// Result of a container creation.
     CreateResult {
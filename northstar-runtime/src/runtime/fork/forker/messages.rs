@@ -14,7 +14,7 @@ pub enum Message {
     /// for a container. The console fd is optional and only present if configured
     /// in the manifest.
     CreateRequest {
-        init: Init,
+        init: Box<Init>,
         io: [OwnedFd; 3],
         console: Option<OwnedFd>,
     },
@@ -38,4 +38,9 @@ pub enum Notification {
         container: Container,
         exit_status: ExitStatus,
     },
+    /// System calls of `container` reported by the seccomp filter in learn mode
+    Seccomp {
+        container: Container,
+        syscalls: Vec<u32>,
+    },
 }
@@ -2,7 +2,7 @@ use self::channel::Channel;
 
 use super::{
     super::{error::Error, Pid},
-    init,
+    init::{self, UserNamespace},
     util::{self},
 };
 use crate::{
@@ -11,6 +11,7 @@ use crate::{
     runtime::{
         config::Config,
         ipc::{AsyncFramedUnixStream, FramedUnixStream},
+        network::Interface,
     },
 };
 use anyhow::{Context, Result};
@@ -22,6 +23,7 @@ use nix::{
 };
 use std::{
     os::unix::{net::UnixStream, prelude::OwnedFd},
+    path::PathBuf,
     process::exit,
 };
 use tokio::runtime;
@@ -104,6 +106,7 @@ impl Forker {
     }
 
     /// Send a request to the forker process to create a new container.
+    #[allow(clippy::too_many_arguments)]
     pub async fn create<'a, I: Iterator<Item = &'a Container> + Clone>(
         &mut self,
         container: &Container,
@@ -112,11 +115,23 @@ impl Forker {
         io: [OwnedFd; 3],
         console: Option<OwnedFd>,
         containers: I,
+        user_namespace: Option<UserNamespace>,
+        cgroups: Vec<PathBuf>,
+        interface: Option<Interface>,
     ) -> Result<Pid, Error> {
         debug_assert_eq!(manifest.console.is_some(), console.is_some());
 
         // Request
-        let init = init::build(config, manifest, containers).await?;
+        let init = init::build(
+            config,
+            manifest,
+            containers,
+            user_namespace,
+            cgroups,
+            interface,
+        )
+        .await?;
+        let init = Box::new(init);
         let request = Message::CreateRequest { init, io, console };
         self.channel.send(request).await;
 
@@ -1,6 +1,6 @@
 use super::{
     init,
-    init::Init,
+    init::{Init, UserNamespace},
     messages::{Message, Notification},
 };
 use crate::{
@@ -8,7 +8,7 @@ use crate::{
     runtime::{
         fork::{forker::channel::Channel, util::set_parent_death_signal},
         ipc::{AsyncFramedUnixStream, FramedUnixStream},
-        ExitStatus, Pid,
+        network, ExitStatus, Pid,
     },
 };
 use anyhow::{anyhow, Context, Result};
@@ -27,6 +27,7 @@ use nix::{
 };
 use std::{
     collections::HashMap,
+    fs,
     os::unix::{
         net::UnixStream,
         prelude::{AsRawFd, OwnedFd},
@@ -57,7 +58,7 @@ pub async fn run(
                     Some(Message::CreateRequest { init, console, io }) => {
                         debug!("Creating init process for {}", init.container);
                         let container = init.container.clone();
-                        match create(init, io, console).await {
+                        match create(*init, io, console).await {
                             Ok((pid, stream)) => {
                                 debug_assert!(!inits.contains_key(&container));
                                 inits.insert(container, (pid, stream));
@@ -89,7 +90,12 @@ pub async fn run(
                 }
             }
             exit = exits.next(), if !exits.is_empty() => {
-                let (container, exit_status) = exit.expect("invalid exit status");
+                let (container, exit_status, syscalls) = exit.expect("invalid exit status");
+                if let Some(syscalls) = syscalls {
+                    debug!("Forwarding {} learned syscalls of {}", syscalls.len(), container);
+                    let notification = Notification::Seccomp { container: container.clone(), syscalls };
+                    notifications.send(notification).await.expect("failed to send seccomp notification");
+                }
                 debug!("Forwarding exit status notification of {}: {}", container, exit_status);
                 let notification = Notification::Exit { container, exit_status };
                 notifications.send(notification).await.expect("failed to send exit notification");
@@ -109,6 +115,8 @@ async fn create(
 
     let (stream_parent, stream_child) =
         UnixStream::pair().context("failed to create socket pair")?;
+    let user_namespace = init.user_namespace.clone();
+    let interface = init.interface.clone();
 
     let trampoline_pid = match unsafe { fork().context("failed to fork") }? {
         ForkResult::Parent { child } => child.as_raw() as Pid,
@@ -127,17 +135,36 @@ async fn create(
             unistd::dup2(stderr.as_raw_fd(), STDERR_FILENO).expect("failed to dup2");
             drop(io);
 
-            // Create pid namespace
-            sched::unshare(sched::CloneFlags::CLONE_NEWPID)
-                .expect("failed to create pid namespace");
+            // Join the cgroups of the container. Init and all its children inherit the cgroups.
+            init.join_cgroups();
+
+            // Create user namespace
+            if init.user_namespace.is_some() {
+                init.enter_user_namespace(&mut stream);
+            }
+
+            // Create the network namespace of a container attached to the bridge. The
+            // forker creates the veth pair in this namespace before init is released.
+            if init.interface.is_some() {
+                sched::unshare(sched::CloneFlags::CLONE_NEWNET)
+                    .expect("failed to create network namespace");
+            }
+
+            // Create pid namespace. Init becomes pid 1 in the new namespace.
+            if init.pid_namespace {
+                sched::unshare(sched::CloneFlags::CLONE_NEWPID)
+                    .expect("failed to create pid namespace");
+            }
 
             // Fork the init process
             let init_pid = match unsafe { fork().expect("failed to fork") } {
                 ForkResult::Parent { child } => child.as_raw() as Pid,
                 ForkResult::Child => {
                     // Wait until the forker process received our pid sent
-                    // over by the trampoline.
-                    drop(stream.recv::<()>());
+                    // over by the trampoline. Exit if the forker gave up.
+                    if !matches!(stream.recv::<()>(), Ok(Some(()))) {
+                        exit(1);
+                    }
                     init.run(stream, console)
                 }
             };
@@ -151,14 +178,52 @@ async fn create(
     // Ensure to close the socket pair end of the child.
     drop(stream_child);
 
+    let mut stream = FramedUnixStream::new(stream_parent);
+
+    // Write the uid and gid maps of the user namespace created by the trampoline
+    if let Some(user_namespace) = user_namespace {
+        debug!("Waiting for user namespace of container {}", container);
+        let result = stream
+            .recv::<()>()
+            .map_err(anyhow::Error::from)
+            .and_then(|r| r.ok_or_else(|| anyhow!("stream closed")))
+            .and_then(|_| write_id_maps(trampoline_pid, &user_namespace))
+            .and_then(|_| stream.send(()).context("failed to confirm id maps"));
+        if let Err(e) = result {
+            kill_and_reap(&[trampoline_pid]);
+            return Err(e);
+        }
+    }
+
     // Wait for the trampoline to send over the PID of init.
     debug!("Waiting for init pid of container {}", container);
-    let mut stream = FramedUnixStream::new(stream_parent);
-    let pid = stream.recv()?.ok_or_else(|| anyhow!("stream closed"))?;
+    let pid = match stream
+        .recv()
+        .map_err(anyhow::Error::from)
+        .and_then(|pid| pid.ok_or_else(|| anyhow!("stream closed")))
+    {
+        Ok(pid) => pid,
+        Err(e) => {
+            kill_and_reap(&[trampoline_pid]);
+            return Err(e);
+        }
+    };
+
+    // Connect the network namespace of init to the bridge
+    if let Some(interface) = interface {
+        if let Err(e) = network::attach(&interface, pid) {
+            kill_and_reap(&[trampoline_pid, pid]);
+            network::detach(&interface).ok();
+            return Err(e);
+        }
+    }
 
     // Notifiy init that we have successfully received their PID and
     // release them into the wild.
-    stream.send(()).context("failed to notify init")?;
+    if let Err(e) = stream.send(()).context("failed to notify init") {
+        kill_and_reap(&[trampoline_pid, pid]);
+        return Err(e);
+    }
 
     // Reap the trampoline process
     debug!("Waiting for trampoline process {} to exit", trampoline_pid);
@@ -168,11 +233,51 @@ async fn create(
         Err(e) => panic!("failed to wait for the trampoline process: {}", e),
     }
 
+    // Wait for the result of the setup steps of init
+    debug!("Waiting for setup of container {}", container);
+    let setup = match stream.recv::<init::Message>() {
+        Ok(Some(init::Message::Setup { result })) => result.map_err(|e| anyhow!(e)),
+        Ok(Some(message)) => Err(anyhow!("unexpected message from init: {:?}", message)),
+        Ok(None) => Err(anyhow!("stream closed")),
+        Err(e) => Err(e.into()),
+    };
+    if let Err(e) = setup {
+        // Init exits after a failed setup and is reparented to the forker
+        kill_and_reap(&[pid]);
+        return Err(e);
+    }
+
     debug!("Created container {} with pid {}", container, pid);
 
     Ok((pid, stream))
 }
 
+/// Kill and reap the processes of a container that failed to be created. The processes
+/// must be gone before the runtime removes the cgroups of the container. The trampoline
+/// must be listed before init: init is reparented to the forker once the trampoline exited.
+fn kill_and_reap(pids: &[Pid]) {
+    let pids = pids
+        .iter()
+        .map(|pid| unistd::Pid::from_raw(*pid as i32))
+        .collect::<Vec<_>>();
+    for pid in &pids {
+        nix::sys::signal::kill(*pid, Some(Signal::SIGKILL)).ok();
+    }
+    for pid in pids {
+        waitpid(Some(pid), None).ok();
+    }
+}
+
+/// Map uid and gid 0 of the user namespace of `pid` to the host ids in `user_namespace`
+fn write_id_maps(pid: Pid, user_namespace: &UserNamespace) -> Result<()> {
+    let uid_map = format!("0 {} {}\n", user_namespace.uid, user_namespace.size);
+    let gid_map = format!("0 {} {}\n", user_namespace.gid, user_namespace.size);
+    debug!("Setting uid map of {} to {}", pid, uid_map.trim());
+    fs::write(format!("/proc/{}/uid_map", pid), uid_map).context("failed to write uid map")?;
+    debug!("Setting gid map of {} to {}", pid, gid_map.trim());
+    fs::write(format!("/proc/{}/gid_map", pid), gid_map).context("failed to write gid map")
+}
+
 /// Send a exec request to a container
 async fn exec(
     init_pid: Pid,
@@ -181,7 +286,10 @@ async fn exec(
     path: NonNulString,
     args: Vec<NonNulString>,
     env: Vec<NonNulString>,
-) -> (Message, impl Future<Output = (Container, ExitStatus)>) {
+) -> (
+    Message,
+    impl Future<Output = (Container, ExitStatus, Option<Vec<u32>>)>,
+) {
     let mut stream = stream;
 
     debug!(
@@ -219,26 +327,30 @@ async fn exec(
     // Construct a future that waits for init to signal a exit of it's child.
     // Afterwards reap the init process which should have exited already.
     let exit_status = async move {
-        let exit_status = match stream.recv().await {
-            Ok(Some(init::Message::Exit {
-                pid: _,
-                exit_status,
-            })) => exit_status,
-            Ok(m) => {
-                warn!("Unexpected message from init {:?}", m);
-                kill(init_pid);
-                ExitStatus::Exit(-1)
-            }
-            Err(e) => {
-                warn!("Unexpected error while waiting for exit status: {}", e);
-                kill(init_pid);
-                ExitStatus::Exit(-2)
+        let mut syscalls = None;
+        let exit_status = loop {
+            match stream.recv().await {
+                Ok(Some(init::Message::Syscalls { syscalls: s })) => syscalls = Some(s),
+                Ok(Some(init::Message::Exit {
+                    pid: _,
+                    exit_status,
+                })) => break exit_status,
+                Ok(m) => {
+                    warn!("Unexpected message from init {:?}", m);
+                    kill(init_pid);
+                    break ExitStatus::Exit(-1);
+                }
+                Err(e) => {
+                    warn!("Unexpected error while waiting for exit status: {}", e);
+                    kill(init_pid);
+                    break ExitStatus::Exit(-2);
+                }
             }
         };
 
         debug!("Reaping init process of {} ({})", container, init_pid);
         waitpid(unistd::Pid::from_raw(init_pid as i32), None).expect("failed to reap init process");
-        (container, exit_status)
+        (container, exit_status, syscalls)
     };
 
     (Message::ExecResult, exit_status)
@@ -1,23 +1,27 @@
-use super::{Init, Mount};
+use super::{landlock, Init, Mount, Overlay, UserNamespace};
 use crate::{
-    common::container::Container,
+    common::{container::Container, name::Name, non_nul_string::NonNulString},
     npk::{
         manifest,
-        manifest::{mount, Manifest},
+        manifest::{etc, mount, Manifest},
     },
-    runtime::{config::Config, error::Error, state::State},
+    runtime::{config::Config, error::Error, network::Interface, state::State},
     seccomp,
 };
 use anyhow::Context;
+use itertools::Itertools;
 use log::warn;
 use nix::{mount::MsFlags, unistd};
 use std::{
+    convert::TryFrom,
     ffi::{c_void, CString},
-    os::unix::prelude::PermissionsExt,
+    fs::Permissions,
+    net::{IpAddr, Ipv4Addr},
+    os::unix::prelude::{MetadataExt, PermissionsExt},
     path::{Path, PathBuf},
     ptr::null,
 };
-use tokio::fs;
+use tokio::{fs, task};
 
 trait PathExt {
     fn join_strip<T: AsRef<Path>>(&self, w: T) -> PathBuf;
@@ -27,6 +31,9 @@ pub async fn build<'a, I: Iterator<Item = &'a Container> + Clone>(
     config: &Config,
     manifest: &Manifest,
     containers: I,
+    user_namespace: Option<UserNamespace>,
+    cgroups: Vec<PathBuf>,
+    interface: Option<Interface>,
 ) -> Result<Init, Error> {
     let container = manifest.container();
     let root = config.run_dir.join(container.to_string());
@@ -35,10 +42,32 @@ pub async fn build<'a, I: Iterator<Item = &'a Container> + Clone>(
     let console = manifest.console.is_some();
     let gid = manifest.gid;
     let network = manifest.network.clone();
-    let groups = groups(manifest);
-    let mounts = prepare_mounts(config, &root, manifest, containers).await?;
+    let pid_namespace = manifest.pid_namespace;
+    let suppl_groups = groups(manifest);
+    let groups = suppl_groups.iter().map(|(_, gid)| *gid).collect();
+    let cwd = manifest
+        .cwd
+        .as_ref()
+        .map(|cwd| PathBuf::from(cwd.as_str()))
+        .unwrap_or_else(|| PathBuf::from("/"));
+    let umask = manifest.umask.map(|umask| umask.bits());
+    let tty = manifest.io.as_ref().map(|io| io.tty).unwrap_or_default();
+    let hostname = manifest.hostname.clone();
+    let domainname = manifest.domainname.clone();
+    let (overlay, mut mounts) =
+        prepare_mounts(config, &root, manifest, containers, user_namespace.as_ref()).await?;
+    mounts.extend(etc(config, manifest, &root, interface.as_ref(), &suppl_groups).await?);
     let rlimits = manifest.rlimits.clone();
-    let seccomp = seccomp_filter(manifest);
+    let sysctl = manifest.sysctl.clone();
+    let seccomp = seccomp_filter(config, manifest)?;
+    let no_new_privs =
+        manifest.no_new_privs || seccomp_without_no_new_privs(manifest, seccomp.as_ref());
+    let landlock = match &manifest.landlock {
+        Some(landlock) => landlock::ruleset(landlock, landlock::abi())
+            .map_err(|e| Error::StartContainerFailed(container.clone(), format!("{:#}", e)))?,
+        None => None,
+    };
+    let exec_context = selinux_exec_context(manifest);
     let uid = manifest.uid;
 
     Ok(Init {
@@ -46,20 +75,34 @@ pub async fn build<'a, I: Iterator<Item = &'a Container> + Clone>(
         root,
         uid,
         gid,
+        cwd,
+        umask,
+        no_new_privs,
+        tty,
+        hostname,
+        domainname,
+        overlay,
         mounts,
         groups,
         network,
         capabilities,
         rlimits,
+        sysctl,
         seccomp,
+        landlock,
+        exec_context,
         console,
+        user_namespace,
+        pid_namespace,
+        cgroups,
+        interface,
     })
 }
 
-/// Generate a list of supplementary gids if the groups info can be retrieved. This
-/// must happen before the init `clone` because the group information cannot be gathered
-/// without `/etc` etc...
-fn groups(manifest: &Manifest) -> Vec<u32> {
+/// Generate a list of supplementary group names and gids if the groups info can be
+/// retrieved. This must happen before the init `clone` because the group information
+/// cannot be gathered without `/etc` etc...
+fn groups(manifest: &Manifest) -> Vec<(&NonNulString, u32)> {
     let mut result = Vec::with_capacity(manifest.suppl_groups.len());
     for group in &manifest.suppl_groups {
         let cgroup: CString = group.clone().into();
@@ -70,32 +113,92 @@ fn groups(manifest: &Manifest) -> Vec<u32> {
         } else {
             let gid = unsafe { (*group_info).gr_gid };
             // TODO: Are there gids cannot use?
-            result.push(gid)
+            result.push((group, gid))
         }
     }
     result
 }
 
-/// Generate seccomp filter applied in init
-fn seccomp_filter(manifest: &Manifest) -> Option<seccomp::AllowList> {
-    manifest.seccomp.as_ref().map(|seccomp| {
-        seccomp::seccomp_filter(
-            seccomp.profile.as_ref(),
-            seccomp.allow.as_ref(),
-            &manifest.capabilities,
-        )
-    })
+/// Returns true if a seccomp filter is applied that cannot be installed without the no new
+/// privileges flag. This is the case for the filters generated in seccomp learn mode.
+fn seccomp_without_no_new_privs(manifest: &Manifest, seccomp: Option<&seccomp::AllowList>) -> bool {
+    let required = !manifest.no_new_privs
+        && seccomp.is_some()
+        && !manifest
+            .capabilities
+            .contains(&manifest::capabilities::Capability::CAP_SYS_ADMIN);
+    if required {
+        warn!(
+            "Setting no new privileges for the seccomp filter of {}",
+            manifest.name
+        );
+    }
+    required
+}
+
+/// SELinux context of the container process if SELinux is enabled on the host
+fn selinux_exec_context(manifest: &Manifest) -> Option<NonNulString> {
+    let exec_context = manifest.selinux.as_ref()?.exec_context.clone()?;
+    if Path::new("/sys/fs/selinux/enforce").exists() {
+        Some(exec_context)
+    } else {
+        warn!(
+            "SELinux is disabled. Ignoring exec context of {}",
+            manifest.name
+        );
+        None
+    }
+}
+
+/// Generate seccomp filter applied in init. Named profiles are loaded from the files
+/// configured in the runtime configuration and merged with the manifest. In learn mode a
+/// filter is generated for containers without a seccomp configuration as well.
+fn seccomp_filter(
+    config: &Config,
+    manifest: &Manifest,
+) -> Result<Option<seccomp::AllowList>, Error> {
+    let learn = config
+        .debug
+        .as_ref()
+        .and_then(|debug| debug.seccomp.as_ref())
+        .map(|seccomp| seccomp.learn)
+        .unwrap_or_default();
+
+    let seccomp = match manifest.seccomp.clone() {
+        Some(seccomp) => match &seccomp.profile {
+            Some(seccomp::Profile::Named(name)) => {
+                let profile = config.seccomp_profile(name.as_str());
+                profile.map(|profile| seccomp.merge(profile))
+            }
+            _ => Ok(seccomp),
+        },
+        None if learn => Ok(seccomp::Seccomp::default()),
+        None => return Ok(None),
+    };
+
+    seccomp
+        .and_then(|seccomp| seccomp::seccomp_filter(&seccomp, &manifest.capabilities, learn))
+        .map(Some)
+        .map_err(|e| Error::StartContainerFailed(manifest.container(), format!("{:#}", e)))
 }
 
 /// Iterate the mounts of a container and assemble a list of `mount` calls to be
-/// performed by init. Prepare an options persist dir. This fn fails if a resource
-/// is referenced that does not exist.
+/// performed by init. Prepare an options persist dir and the directory of the root
+/// overlay. This fn fails if a resource is referenced that does not exist.
 async fn prepare_mounts<'a, I: Iterator<Item = &'a Container> + Clone>(
     config: &Config,
     root: &Path,
     manifest: &Manifest,
     containers: I,
-) -> Result<Vec<Mount>, Error> {
+    user_namespace: Option<&UserNamespace>,
+) -> Result<(Option<Overlay>, Vec<Mount>), Error> {
+    let overlay = match &manifest.overlay {
+        Some(overlay_config) => {
+            Some(overlay(config, manifest, overlay_config, user_namespace).await?)
+        }
+        None => None,
+    };
+
     let mut mounts = vec![];
     let manifest_mounts = &manifest.mounts;
 
@@ -104,18 +207,13 @@ async fn prepare_mounts<'a, I: Iterator<Item = &'a Container> + Clone>(
             mount::Mount::Bind(manifest::mount::Bind { host, options }) => {
                 mounts.extend(bind(root, target.as_ref(), host.as_ref(), options));
             }
-            mount::Mount::Persist => {
-                mounts.push(
-                    persist(
-                        config,
-                        manifest,
-                        root,
-                        target.as_ref(),
-                        manifest.uid,
-                        manifest.gid,
-                    )
-                    .await?,
-                );
+            mount::Mount::Persist(_) => {
+                mounts
+                    .push(persist(config, manifest, root, target.as_ref(), user_namespace).await?);
+            }
+            mount::Mount::Cgroup => {
+                let delegate = manifest.cgroups.as_ref().map(|c| c.delegate) == Some(true);
+                mounts.extend(cgroup(root, target.as_ref(), delegate))
             }
             mount::Mount::Proc => mounts.push(proc(root, target.as_ref())),
             mount::Mount::Sysfs => mounts.push(sysfs(root, target.as_ref())),
@@ -142,11 +240,15 @@ async fn prepare_mounts<'a, I: Iterator<Item = &'a Container> + Clone>(
             mount::Mount::Tmpfs(mount::Tmpfs { size }) => {
                 mounts.push(tmpfs(root, target.as_ref(), *size))
             }
+            mount::Mount::Volume(manifest::mount::Volume { name, options }) => {
+                let volume = config.volumes.get(name).expect("failed to locate volume"); // Already checked in State::start()
+                mounts.extend(bind(root, target.as_ref(), &volume.dir, options));
+            }
             mount::Mount::Dev => {}
         }
     }
 
-    Ok(mounts)
+    Ok((overlay, mounts))
 }
 
 fn proc(root: &Path, target: &Path) -> Mount {
@@ -161,6 +263,28 @@ fn proc(root: &Path, target: &Path) -> Mount {
     Mount::new(Some(source), target, FSTYPE, flags, None)
 }
 
+fn cgroup(root: &Path, target: &Path, delegate: bool) -> Option<Mount> {
+    if !cgroups_rs::hierarchies::is_cgroup2_unified_mode() {
+        warn!(
+            "Skipping cgroup mount on {}: cgroup v2 is not available",
+            target.display()
+        );
+        return None;
+    }
+
+    log::debug!(
+        "Adding cgroup2 on {} with options {}nosuid, noexec and nodev",
+        target.display(),
+        if delegate { "" } else { "ro, " }
+    );
+    let source = PathBuf::from("cgroup2");
+    let target = root.join_strip(target);
+    const FSTYPE: Option<&'static str> = Some("cgroup2");
+    let mut flags = MsFlags::MS_NOSUID | MsFlags::MS_NOEXEC | MsFlags::MS_NODEV;
+    flags.set(MsFlags::MS_RDONLY, !delegate);
+    Some(Mount::new(Some(source), target, FSTYPE, flags, None))
+}
+
 fn sysfs(root: &Path, target: &Path) -> Mount {
     log::debug!(
         "Adding sysfs on {} with options ro, nosuid, noexec and nodev",
@@ -230,8 +354,7 @@ async fn persist(
     manifest: &Manifest,
     root: &Path,
     target: &Path,
-    uid: u16,
-    gid: u16,
+    user_namespace: Option<&UserNamespace>,
 ) -> Result<Mount, Error> {
     // Note that the version is intentionally not part of the path. This allows
     // upgrades with persistent data migration
@@ -244,28 +367,16 @@ async fn persist(
             .with_context(|| format!("failed to create directory {}", source.display()))?;
     }
 
-    log::debug!("Chowning {} to {}:{}", source.display(), uid, gid);
-    unistd::chown(
-        source.as_os_str(),
-        Some(unistd::Uid::from_raw(uid.into())),
-        Some(unistd::Gid::from_raw(gid.into())),
-    )
-    .context(format!(
-        "failed to chown {} to {}:{}",
-        source.display(),
-        uid,
-        gid
-    ))?;
-
-    log::debug!("Chmod {} to 700", source.display());
-    let mut permissions = fs::metadata(&source)
-        .await
-        .with_context(|| format!("failed to get permissions of {}", source.display()))?
-        .permissions();
-    permissions.set_mode(0o700);
-    fs::set_permissions(&source, permissions)
-        .await
-        .with_context(|| format!("failed to set permission on {}", source.display()))?;
+    // With a user namespace the ids of the container are shifted by the start of the
+    // id range mapped into the namespace
+    let (uid, gid) = match user_namespace {
+        Some(user_namespace) => (
+            user_namespace.uid + manifest.uid as u32,
+            user_namespace.gid + manifest.gid as u32,
+        ),
+        None => (manifest.uid.into(), manifest.gid.into()),
+    };
+    take_ownership(config, &source, uid, gid, user_namespace).await?;
 
     log::debug!(
         "Adding {} on {} with options nodev, nosuid and noexec",
@@ -284,6 +395,288 @@ async fn persist(
     ))
 }
 
+/// Prepare the directory of the upper and work directory of the root overlay. The
+/// directory of a persistent overlay is `<name>:overlay` in the data dir. Container
+/// names cannot contain a colon and do not clash with the persist directories.
+async fn overlay(
+    config: &Config,
+    manifest: &Manifest,
+    overlay: &mount::Overlay,
+    user_namespace: Option<&UserNamespace>,
+) -> Result<Overlay, Error> {
+    match overlay {
+        mount::Overlay::Tmpfs(mount::Tmpfs { size }) => {
+            let dir = config
+                .run_dir
+                .join(format!("{}:overlay", manifest.container()));
+            if !dir.exists() {
+                log::debug!("Creating {}", dir.display());
+                fs::create_dir_all(&dir)
+                    .await
+                    .with_context(|| format!("failed to create directory {}", dir.display()))?;
+            }
+
+            log::debug!(
+                "Adding overlay with tmpfs of size {}",
+                bytesize::ByteSize::b(*size)
+            );
+            let data = format!("size={},mode=0700", size);
+            let tmpfs = Mount::new(
+                None,
+                dir.clone(),
+                Some("tmpfs"),
+                MsFlags::empty(),
+                Some(data),
+            );
+            Ok(Overlay {
+                dir,
+                tmpfs: Some(tmpfs),
+            })
+        }
+        mount::Overlay::Persist => {
+            let dir = overlay_dir(config, &manifest.name);
+            if !dir.exists() {
+                log::debug!("Creating {}", dir.display());
+                fs::create_dir_all(&dir)
+                    .await
+                    .with_context(|| format!("failed to create directory {}", dir.display()))?;
+            }
+
+            // The overlay is owned by root of the container
+            let (uid, gid) = user_namespace
+                .map(|user_namespace| (user_namespace.uid, user_namespace.gid))
+                .unwrap_or((0, 0));
+            take_ownership(config, &dir, uid, gid, user_namespace).await?;
+
+            log::debug!("Adding overlay in {}", dir.display());
+            Ok(Overlay { dir, tmpfs: None })
+        }
+    }
+}
+
+/// Generate the /etc files of `manifest` in the run dir and bind mount them read only
+/// on their mount points in the root of the container
+async fn etc(
+    config: &Config,
+    manifest: &Manifest,
+    root: &Path,
+    interface: Option<&Interface>,
+    groups: &[(&NonNulString, u32)],
+) -> Result<Vec<Mount>, Error> {
+    if manifest.etc.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let dir = config.run_dir.join(format!("{}:etc", manifest.container()));
+    if !dir.exists() {
+        log::debug!("Creating {}", dir.display());
+        fs::create_dir_all(&dir)
+            .await
+            .with_context(|| format!("failed to create directory {}", dir.display()))?;
+    }
+
+    let mut mounts = Vec::with_capacity(manifest.etc.len() * 2);
+    for file in manifest.etc.iter().sorted() {
+        let target = root.join_strip(file.path());
+        if !target.is_file() {
+            warn!(
+                "Skipping {} of {}: mount point is missing",
+                file.path(),
+                manifest.container()
+            );
+            continue;
+        }
+
+        let content = match file {
+            etc::File::Hosts => hosts(manifest, interface),
+            etc::File::ResolvConf => resolv_conf(config, interface).await?,
+            etc::File::Passwd => passwd(manifest),
+            etc::File::Group => group(manifest, groups),
+        };
+        let source = dir.join(file.name());
+        fs::write(&source, content)
+            .await
+            .with_context(|| format!("failed to write {}", source.display()))?;
+        fs::set_permissions(&source, Permissions::from_mode(0o444))
+            .await
+            .with_context(|| format!("failed to set permissions of {}", source.display()))?;
+
+        log::debug!("Adding {} on {} read only", source.display(), file.path());
+        let flags = MsFlags::MS_BIND | MsFlags::MS_NOSUID | MsFlags::MS_NODEV | MsFlags::MS_NOEXEC;
+        mounts.push(Mount::new(
+            Some(source.clone()),
+            target.clone(),
+            None,
+            flags,
+            None,
+        ));
+        let flags = flags | MsFlags::MS_REMOUNT | MsFlags::MS_RDONLY;
+        mounts.push(Mount::new(Some(source), target, None, flags, None));
+    }
+    Ok(mounts)
+}
+
+/// Hosts file with localhost and the hostname of the container. The hostname resolves
+/// to the address on the bridge if the container is attached to it.
+fn hosts(manifest: &Manifest, interface: Option<&Interface>) -> String {
+    let mut hosts = "127.0.0.1\tlocalhost\n::1\tlocalhost\n".to_string();
+    if let Some(hostname) = &manifest.hostname {
+        let address = interface
+            .map(|interface| interface.address)
+            .unwrap_or_else(|| Ipv4Addr::new(127, 0, 1, 1));
+        hosts.push_str(&format!("{}\t{}\n", address, hostname));
+    }
+    hosts
+}
+
+/// Resolver configuration of the host. Containers attached to the bridge get the
+/// nameservers configured for the bridge, or the nameservers of the host without the
+/// loopback addresses that are not reachable from their network namespace.
+async fn resolv_conf(config: &Config, interface: Option<&Interface>) -> anyhow::Result<String> {
+    let nameservers = match (interface, &config.bridge) {
+        (Some(_), Some(bridge)) => &bridge.nameservers,
+        _ => {
+            return fs::read_to_string("/etc/resolv.conf")
+                .await
+                .context("failed to read /etc/resolv.conf")
+        }
+    };
+
+    if !nameservers.is_empty() {
+        return Ok(nameservers
+            .iter()
+            .map(|nameserver| format!("nameserver {}\n", nameserver))
+            .collect());
+    }
+
+    let host = fs::read_to_string("/etc/resolv.conf")
+        .await
+        .context("failed to read /etc/resolv.conf")?;
+    Ok(host
+        .lines()
+        .filter(|line| {
+            let mut fields = line.split_whitespace();
+            match (fields.next(), fields.next().map(str::parse::<IpAddr>)) {
+                (Some("nameserver"), Some(Ok(address))) => !address.is_loopback(),
+                _ => true,
+            }
+        })
+        .map(|line| format!("{}\n", line))
+        .collect())
+}
+
+/// User database with root and the container user named after the container
+fn passwd(manifest: &Manifest) -> String {
+    format!(
+        "root:x:0:0:root:/:/bin/false\n{name}:x:{}:{}:{name}:/:/bin/false\n",
+        manifest.uid,
+        manifest.gid,
+        name = manifest.name
+    )
+}
+
+/// Group database with root, the group of the container and its supplementary groups
+fn group(manifest: &Manifest, groups: &[(&NonNulString, u32)]) -> String {
+    let mut group = format!("root:x:0:\n{}:x:{}:\n", manifest.name, manifest.gid);
+    for (name, gid) in groups {
+        if *gid != 0 && *gid != manifest.gid as u32 {
+            group.push_str(&format!("{}:x:{}:{}\n", name, gid, manifest.name));
+        }
+    }
+    group
+}
+
+/// Directory of the persistent root overlay of container `name`
+fn overlay_dir(config: &Config, name: &Name) -> PathBuf {
+    config.data_dir.join(format!("{}:overlay", name))
+}
+
+/// Change the owner of `dir` to `uid` and `gid` and the permissions to 700. The
+/// content is shifted if the directory is owned by a different id range.
+async fn take_ownership(
+    config: &Config,
+    dir: &Path,
+    uid: u32,
+    gid: u32,
+    user_namespace: Option<&UserNamespace>,
+) -> Result<(), Error> {
+    // The directory is owned by the previous id range if the container
+    // got a different range than last time or switched from or to a user namespace.
+    // Shift the ownership of the content.
+    let metadata = fs::metadata(dir)
+        .await
+        .with_context(|| format!("failed to stat {}", dir.display()))?;
+    let in_range = config
+        .user_namespace
+        .as_ref()
+        .map(|range| (range.uid..range.uid + range.count).contains(&metadata.uid()))
+        .unwrap_or(false);
+    let shift_uid = uid as i64 - metadata.uid() as i64;
+    let shift_gid = gid as i64 - metadata.gid() as i64;
+    if (user_namespace.is_some() || in_range) && (shift_uid != 0 || shift_gid != 0) {
+        log::debug!(
+            "Shifting ownership of {} by {}:{}",
+            dir.display(),
+            shift_uid,
+            shift_gid
+        );
+        let path = dir.to_owned();
+        task::spawn_blocking(move || shift_ownership(&path, shift_uid, shift_gid))
+            .await
+            .context("failed to join ownership task")?
+            .with_context(|| format!("failed to shift ownership of {}", dir.display()))?;
+    }
+
+    log::debug!("Chowning {} to {}:{}", dir.display(), uid, gid);
+    unistd::chown(
+        dir.as_os_str(),
+        Some(unistd::Uid::from_raw(uid)),
+        Some(unistd::Gid::from_raw(gid)),
+    )
+    .context(format!(
+        "failed to chown {} to {}:{}",
+        dir.display(),
+        uid,
+        gid
+    ))?;
+
+    log::debug!("Chmod {} to 700", dir.display());
+    let mut permissions = fs::metadata(dir)
+        .await
+        .with_context(|| format!("failed to get permissions of {}", dir.display()))?
+        .permissions();
+    permissions.set_mode(0o700);
+    fs::set_permissions(dir, permissions)
+        .await
+        .with_context(|| format!("failed to set permission on {}", dir.display()))?;
+    Ok(())
+}
+
+/// Recursively change the owner of the content of `dir` by `uid` and `gid`. Ids that
+/// would become negative are left untouched. Symlinks are not followed.
+fn shift_ownership(dir: &Path, uid: i64, gid: i64) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let metadata = std::fs::symlink_metadata(&path)?;
+        let shift = |id: u32, by: i64| u32::try_from(id as i64 + by).ok();
+        if let (Some(new_uid), Some(new_gid)) =
+            (shift(metadata.uid(), uid), shift(metadata.gid(), gid))
+        {
+            unistd::fchownat(
+                None,
+                &path,
+                Some(unistd::Uid::from_raw(new_uid)),
+                Some(unistd::Gid::from_raw(new_gid)),
+                unistd::FchownatFlags::NoFollowSymlink,
+            )?;
+        }
+        if metadata.is_dir() {
+            shift_ownership(&path, uid, gid)?;
+        }
+    }
+    Ok(())
+}
+
 fn resource(
     root: &Path,
     target: &Path,
@@ -0,0 +1,259 @@
+//! Landlock filesystem access restrictions. The ruleset is resolved against the Landlock ABI
+//! of the kernel when the container is created and applied by init before the exec.
+
+use crate::npk::manifest::landlock::{Access, Landlock};
+use anyhow::{bail, Result};
+use log::{debug, warn};
+use nix::{
+    errno::Errno,
+    fcntl::{self, OFlag},
+    libc,
+    sys::stat::{self, Mode},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    os::unix::prelude::{AsRawFd, FromRawFd, OwnedFd, RawFd},
+    path::PathBuf,
+    ptr::null,
+};
+
+const SYS_LANDLOCK_CREATE_RULESET: libc::c_long = 444;
+const SYS_LANDLOCK_ADD_RULE: libc::c_long = 445;
+const SYS_LANDLOCK_RESTRICT_SELF: libc::c_long = 446;
+
+const LANDLOCK_CREATE_RULESET_VERSION: u32 = 1;
+const LANDLOCK_RULE_PATH_BENEATH: libc::c_int = 1;
+
+const ACCESS_FS_EXECUTE: u64 = 1 << 0;
+const ACCESS_FS_WRITE_FILE: u64 = 1 << 1;
+const ACCESS_FS_READ_FILE: u64 = 1 << 2;
+const ACCESS_FS_READ_DIR: u64 = 1 << 3;
+const ACCESS_FS_REMOVE_DIR: u64 = 1 << 4;
+const ACCESS_FS_REMOVE_FILE: u64 = 1 << 5;
+const ACCESS_FS_MAKE_CHAR: u64 = 1 << 6;
+const ACCESS_FS_MAKE_DIR: u64 = 1 << 7;
+const ACCESS_FS_MAKE_REG: u64 = 1 << 8;
+const ACCESS_FS_MAKE_SOCK: u64 = 1 << 9;
+const ACCESS_FS_MAKE_FIFO: u64 = 1 << 10;
+const ACCESS_FS_MAKE_BLOCK: u64 = 1 << 11;
+const ACCESS_FS_MAKE_SYM: u64 = 1 << 12;
+const ACCESS_FS_REFER: u64 = 1 << 13;
+const ACCESS_FS_TRUNCATE: u64 = 1 << 14;
+
+/// Access rights that apply to files. Rules for files must not contain other rights.
+const ACCESS_FILE: u64 =
+    ACCESS_FS_EXECUTE | ACCESS_FS_WRITE_FILE | ACCESS_FS_READ_FILE | ACCESS_FS_TRUNCATE;
+
+/// struct landlock_ruleset_attr
+#[repr(C)]
+struct RulesetAttr {
+    handled_access_fs: u64,
+}
+
+/// struct landlock_path_beneath_attr
+#[repr(C, packed)]
+struct PathBeneathAttr {
+    allowed_access: u64,
+    parent_fd: i32,
+}
+
+/// Landlock ruleset resolved against the ABI of the kernel
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Ruleset {
+    /// Fail if a path cannot be added to the ruleset
+    strict: bool,
+    /// Access rights that are restricted
+    handled: u64,
+    /// Allowed access rights per path
+    rules: Vec<(PathBuf, u64)>,
+}
+
+/// Landlock ABI version of the running kernel. None if Landlock is not supported or disabled.
+pub fn abi() -> Option<u32> {
+    let result = unsafe {
+        libc::syscall(
+            SYS_LANDLOCK_CREATE_RULESET,
+            null::<RulesetAttr>(),
+            0usize,
+            LANDLOCK_CREATE_RULESET_VERSION,
+        )
+    };
+    Errno::result(result).ok().map(|abi| abi as u32)
+}
+
+/// Access rights supported by the Landlock `abi`
+fn supported(abi: u32) -> u64 {
+    match abi {
+        1 => (ACCESS_FS_MAKE_SYM << 1) - 1,
+        2 => (ACCESS_FS_REFER << 1) - 1,
+        _ => (ACCESS_FS_TRUNCATE << 1) - 1,
+    }
+}
+
+/// Bitmask of `access`
+fn mask(access: &Access) -> u64 {
+    match access {
+        Access::Read => ACCESS_FS_READ_FILE | ACCESS_FS_READ_DIR,
+        Access::Write => ACCESS_FS_WRITE_FILE,
+        Access::Execute => ACCESS_FS_EXECUTE,
+        Access::ReadFile => ACCESS_FS_READ_FILE,
+        Access::ReadDir => ACCESS_FS_READ_DIR,
+        Access::RemoveDir => ACCESS_FS_REMOVE_DIR,
+        Access::RemoveFile => ACCESS_FS_REMOVE_FILE,
+        Access::MakeChar => ACCESS_FS_MAKE_CHAR,
+        Access::MakeDir => ACCESS_FS_MAKE_DIR,
+        Access::MakeReg => ACCESS_FS_MAKE_REG,
+        Access::MakeSock => ACCESS_FS_MAKE_SOCK,
+        Access::MakeFifo => ACCESS_FS_MAKE_FIFO,
+        Access::MakeBlock => ACCESS_FS_MAKE_BLOCK,
+        Access::MakeSym => ACCESS_FS_MAKE_SYM,
+        Access::Refer => ACCESS_FS_REFER,
+        Access::Truncate => ACCESS_FS_TRUNCATE,
+    }
+}
+
+/// Resolve `landlock` against the Landlock `abi` of the kernel. Returns None if Landlock is
+/// not available and `landlock` is not strict. Unsupported access rights are dropped unless
+/// `landlock` is strict.
+pub fn ruleset(landlock: &Landlock, abi: Option<u32>) -> Result<Option<Ruleset>> {
+    let abi = match abi {
+        Some(abi) => abi,
+        None if landlock.strict => bail!("landlock is not supported by the kernel"),
+        None => {
+            warn!("Landlock is not supported by the kernel. Skipping restrictions");
+            return Ok(None);
+        }
+    };
+
+    let handled = supported(abi);
+    let mut rules = Vec::with_capacity(landlock.paths.len());
+    for (path, access) in &landlock.paths {
+        let access = access.iter().map(mask).fold(0, |acc, mask| acc | mask);
+        if access & !handled != 0 {
+            if landlock.strict {
+                bail!(
+                    "access rights of {} are not supported by landlock ABI {}",
+                    path,
+                    abi
+                );
+            }
+            warn!(
+                "Dropping access rights of {} not supported by landlock ABI {}",
+                path, abi
+            );
+        }
+        rules.push((PathBuf::from(path.as_str()), access & handled));
+    }
+
+    Ok(Some(Ruleset {
+        strict: landlock.strict,
+        handled,
+        rules,
+    }))
+}
+
+impl Ruleset {
+    /// Restrict the calling thread and its future children. Requires no new privileges to be
+    /// set or CAP_SYS_ADMIN.
+    pub fn apply(&self) -> Result<()> {
+        let attr = RulesetAttr {
+            handled_access_fs: self.handled,
+        };
+        let result = unsafe {
+            libc::syscall(
+                SYS_LANDLOCK_CREATE_RULESET,
+                &attr,
+                std::mem::size_of::<RulesetAttr>(),
+                0u32,
+            )
+        };
+        let ruleset = unsafe { OwnedFd::from_raw_fd(Errno::result(result)? as RawFd) };
+
+        for (path, access) in &self.rules {
+            let flags = OFlag::O_PATH | OFlag::O_CLOEXEC;
+            let fd = match fcntl::open(path, flags, Mode::empty()) {
+                Ok(fd) => unsafe { OwnedFd::from_raw_fd(fd) },
+                Err(e) if self.strict => bail!("failed to open {}: {}", path.display(), e),
+                Err(e) => {
+                    warn!("Skipping landlock rule for {}: {}", path.display(), e);
+                    continue;
+                }
+            };
+
+            // Directory rights cannot be granted on files
+            let is_dir = stat::fstat(fd.as_raw_fd())?.st_mode & libc::S_IFMT == libc::S_IFDIR;
+            let access = if is_dir {
+                *access
+            } else {
+                access & ACCESS_FILE
+            };
+            if access == 0 {
+                debug!("Skipping landlock rule for {}", path.display());
+                continue;
+            }
+
+            debug!("Adding landlock rule {:#x} for {}", access, path.display());
+            let attr = PathBeneathAttr {
+                allowed_access: access,
+                parent_fd: fd.as_raw_fd(),
+            };
+            let result = unsafe {
+                libc::syscall(
+                    SYS_LANDLOCK_ADD_RULE,
+                    ruleset.as_raw_fd(),
+                    LANDLOCK_RULE_PATH_BENEATH,
+                    &attr,
+                    0u32,
+                )
+            };
+            Errno::result(result)?;
+        }
+
+        let result =
+            unsafe { libc::syscall(SYS_LANDLOCK_RESTRICT_SELF, ruleset.as_raw_fd(), 0u32) };
+        Errno::result(result)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::{HashMap, HashSet};
+
+    #[test]
+    fn ruleset() -> Result<()> {
+        let mut landlock = Landlock {
+            strict: false,
+            paths: HashMap::from([(
+                "/data".try_into()?,
+                HashSet::from([Access::Read, Access::Truncate]),
+            )]),
+        };
+
+        // Landlock is not available
+        assert!(super::ruleset(&landlock, None)?.is_none());
+
+        // Unsupported rights are dropped
+        let ruleset = super::ruleset(&landlock, Some(1))?.expect("missing ruleset");
+        assert_eq!(ruleset.handled, (1 << 13) - 1);
+        assert_eq!(
+            ruleset.rules,
+            vec![(
+                PathBuf::from("/data"),
+                ACCESS_FS_READ_FILE | ACCESS_FS_READ_DIR
+            )]
+        );
+        let ruleset = super::ruleset(&landlock, Some(3))?.expect("missing ruleset");
+        assert_eq!(
+            ruleset.rules[0].1,
+            ACCESS_FS_READ_FILE | ACCESS_FS_READ_DIR | ACCESS_FS_TRUNCATE
+        );
+
+        landlock.strict = true;
+        assert!(super::ruleset(&landlock, None).is_err());
+        assert!(super::ruleset(&landlock, Some(2)).is_err());
+        assert!(super::ruleset(&landlock, Some(3)).is_ok());
+        Ok(())
+    }
+}
@@ -0,0 +1,192 @@
+//! Seccomp learn mode. System calls that are not allowed by the filter of a container are
+//! reported to init via the listener of the filter and continued.
+
+use crate::runtime::Pid;
+use log::{debug, warn};
+use nix::{
+    errno::Errno,
+    fcntl::{self, FcntlArg, OFlag},
+    libc, unistd,
+};
+use std::{
+    collections::BTreeSet,
+    os::unix::prelude::{AsRawFd, FromRawFd, OwnedFd, RawFd},
+    thread,
+    time::Duration,
+};
+
+const SYS_PIDFD_OPEN: libc::c_long = 434;
+const SYS_PIDFD_GETFD: libc::c_long = 438;
+
+const SECCOMP_IOCTL_NOTIF_RECV: u32 = 0xc0502100;
+const SECCOMP_IOCTL_NOTIF_SEND: u32 = 0xc0182101;
+const SECCOMP_IOCTL_NOTIF_ID_VALID: u32 = 0x80082102;
+const SECCOMP_USER_NOTIF_FLAG_CONTINUE: u32 = 1;
+
+/// struct seccomp_data
+#[repr(C)]
+#[derive(Default)]
+#[allow(unused)]
+struct SeccompData {
+    nr: i32,
+    arch: u32,
+    instruction_pointer: u64,
+    args: [u64; 6],
+}
+
+/// struct seccomp_notif
+#[repr(C)]
+#[derive(Default)]
+#[allow(unused)]
+struct SeccompNotif {
+    id: u64,
+    pid: u32,
+    flags: u32,
+    data: SeccompData,
+}
+
+/// struct seccomp_notif_resp
+#[repr(C)]
+struct SeccompNotifResp {
+    id: u64,
+    val: i64,
+    error: i32,
+    flags: u32,
+}
+
+/// Reserve a fd number for the listener of the filter applied by a child forked after
+/// this call. The child moves its listener onto the reserved number with `claim_fd`.
+pub fn reserve_fd() -> OwnedFd {
+    let fd = fcntl::fcntl(libc::STDIN_FILENO, FcntlArg::F_DUPFD_CLOEXEC(0)).expect("failed to dup");
+    unsafe { OwnedFd::from_raw_fd(fd) }
+}
+
+/// Move the listener `fd` onto the `reserved` fd number
+pub fn claim_fd(fd: RawFd, reserved: RawFd) -> Result<(), Errno> {
+    if fd != reserved {
+        unistd::dup3(fd, reserved, OFlag::O_CLOEXEC)?;
+        unistd::close(fd)?;
+    }
+    Ok(())
+}
+
+/// Listener of the seccomp filter applied by a child of init
+pub struct Listener {
+    listener: OwnedFd,
+    pidfd: OwnedFd,
+}
+
+impl Listener {
+    /// Get the listener `fd` of the filter applied by the child `pid`. Waits until the
+    /// child applied its filter.
+    pub fn new(pid: Pid, fd: RawFd) -> Result<Listener, Errno> {
+        let pidfd = Errno::result(unsafe { libc::syscall(SYS_PIDFD_OPEN, pid, 0) })?;
+        let pidfd = unsafe { OwnedFd::from_raw_fd(pidfd as RawFd) };
+
+        loop {
+            let result = unsafe { libc::syscall(SYS_PIDFD_GETFD, pidfd.as_raw_fd(), fd, 0) };
+            match Errno::result(result) {
+                Ok(listener) => {
+                    let listener = unsafe { OwnedFd::from_raw_fd(listener as RawFd) };
+                    // A listener rejects the unknown notification id 0 with ENOENT
+                    let id = 0u64;
+                    let result = unsafe {
+                        libc::ioctl(listener.as_raw_fd(), SECCOMP_IOCTL_NOTIF_ID_VALID as _, &id)
+                    };
+                    if Errno::result(result) == Err(Errno::ENOENT) {
+                        debug!("Received seccomp listener of {}", pid);
+                        return Ok(Listener { listener, pidfd });
+                    }
+                }
+                Err(Errno::EBADF) if !exited(&pidfd) => (),
+                Err(Errno::EBADF) => return Err(Errno::ESRCH),
+                Err(e) => return Err(e),
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    /// Continue the reported system calls until the child exited or the listener fails.
+    /// Returns the system calls reported after the first `execve` of the child.
+    pub fn run(self) -> BTreeSet<u32> {
+        let mut syscalls = BTreeSet::new();
+        let mut exec = false;
+
+        loop {
+            let mut fds = [
+                libc::pollfd {
+                    fd: self.listener.as_raw_fd(),
+                    events: libc::POLLIN,
+                    revents: 0,
+                },
+                libc::pollfd {
+                    fd: self.pidfd.as_raw_fd(),
+                    events: libc::POLLIN,
+                    revents: 0,
+                },
+            ];
+            match Errno::result(unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as _, -1) }) {
+                Ok(_) | Err(Errno::EINTR) => (),
+                Err(e) => {
+                    // The child continues with the default action of the filter once the
+                    // listener is closed
+                    warn!("Failed to poll seccomp listener: {}. Stopping learning", e);
+                    break syscalls;
+                }
+            }
+
+            if fds[0].revents & libc::POLLIN != 0 {
+                if let Some(nr) = self.continue_syscall() {
+                    if exec {
+                        syscalls.insert(nr);
+                    } else if nr as libc::c_long == libc::SYS_execve {
+                        // Everything before is done by the runtime
+                        exec = true;
+                    }
+                }
+            } else if fds[0].revents & libc::POLLHUP != 0 || fds[1].revents != 0 {
+                break syscalls;
+            }
+        }
+    }
+
+    /// Receive a notification and let the system call continue
+    fn continue_syscall(&self) -> Option<u32> {
+        let mut notification = SeccompNotif::default();
+        let fd = self.listener.as_raw_fd();
+        let result = unsafe { libc::ioctl(fd, SECCOMP_IOCTL_NOTIF_RECV as _, &mut notification) };
+        match Errno::result(result) {
+            Ok(_) => (),
+            // The reporting process is gone
+            Err(Errno::ENOENT) | Err(Errno::EINTR) => return None,
+            Err(e) => {
+                warn!("Failed to receive seccomp notification: {}", e);
+                return None;
+            }
+        }
+
+        let response = SeccompNotifResp {
+            id: notification.id,
+            val: 0,
+            error: 0,
+            flags: SECCOMP_USER_NOTIF_FLAG_CONTINUE,
+        };
+        let result = unsafe { libc::ioctl(fd, SECCOMP_IOCTL_NOTIF_SEND as _, &response) };
+        match Errno::result(result) {
+            Ok(_) | Err(Errno::ENOENT) => (),
+            Err(e) => warn!("Failed to continue system call: {}", e),
+        }
+
+        Some(notification.data.nr as u32)
+    }
+}
+
+/// True if the process referred to by `pidfd` exited
+fn exited(pidfd: &OwnedFd) -> bool {
+    let mut fd = libc::pollfd {
+        fd: pidfd.as_raw_fd(),
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    unsafe { libc::poll(&mut fd, 1, 0) > 0 }
+}
@@ -8,10 +8,12 @@ use crate::{
     runtime::{
         fork::util::{self, set_child_subreaper, set_process_name},
         ipc::FramedUnixStream,
+        network::{self, Interface},
         ExitStatus, Pid,
     },
     seccomp::AllowList,
 };
+use anyhow::Context;
 pub use builder::build;
 use itertools::Itertools;
 use log::{debug, info, warn};
@@ -33,20 +35,28 @@ use std::{
     collections::{HashMap, HashSet},
     env,
     ffi::CString,
-    os::unix::prelude::{AsRawFd, OwnedFd},
+    fs::Permissions,
+    os::unix::prelude::{AsRawFd, OwnedFd, PermissionsExt},
     path::{Path, PathBuf},
     process::exit,
 };
 
 mod builder;
+mod landlock;
+mod learn;
 
 // Message from the forker to init and response
 #[derive(Debug, Serialize, Deserialize)]
 pub enum Message {
+    /// Result of the setup steps of init that depend on the manifest. Sent once after
+    /// init is released by the forker and before init waits for the exec request.
+    Setup { result: Result<(), String> },
     /// The init process forked a new child with `pid`
     Forked { pid: Pid },
     /// A child of init exited with `exit_status`
     Exit { pid: Pid, exit_status: ExitStatus },
+    /// System calls reported by the seccomp filter in learn mode. Sent before `Exit`.
+    Syscalls { syscalls: Vec<u32> },
     /// Exec a new process
     Exec {
         path: NonNulString,
@@ -61,13 +71,36 @@ pub struct Init {
     pub root: PathBuf,
     pub uid: u16,
     pub gid: u16,
+    pub cwd: PathBuf,
+    pub umask: Option<u32>,
+    pub no_new_privs: bool,
+    pub tty: bool,
+    pub hostname: Option<NonNulString>,
+    pub domainname: Option<NonNulString>,
+    pub overlay: Option<Overlay>,
     pub mounts: Vec<Mount>,
     pub groups: Vec<u32>,
     pub network: Option<Network>,
     pub capabilities: HashSet<Capability>,
     pub rlimits: HashMap<RLimitResource, RLimitValue>,
+    pub sysctl: HashMap<NonNulString, NonNulString>,
     pub seccomp: Option<AllowList>,
+    pub landlock: Option<landlock::Ruleset>,
+    pub exec_context: Option<NonNulString>,
     pub console: bool,
+    pub user_namespace: Option<UserNamespace>,
+    pub pid_namespace: bool,
+    pub cgroups: Vec<PathBuf>,
+    pub interface: Option<Interface>,
+}
+
+/// Host id ranges mapped into the user namespace of a container. The uid and
+/// gid 0 of the container are mapped to `uid` and `gid` on the host.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UserNamespace {
+    pub uid: u32,
+    pub gid: u32,
+    pub size: u32,
 }
 
 impl Init {
@@ -83,24 +116,56 @@ impl Init {
         debug!("Setting session id");
         unistd::setsid().expect("failed to call setsid");
 
-        // Enter network namespace
-        self.network();
+        // Make the pseudo terminal on stdin the controlling terminal of the session
+        if self.tty {
+            Self::set_controlling_terminal();
+        }
+
+        // Enter network namespace. Named network namespaces are joined by the
+        // trampoline before the user namespace is created.
+        if self.user_namespace.is_none() || !self.network_is_named() {
+            self.network();
+        }
 
-        // Enter mount namespace
-        debug!("Entering mount, IPC and UTS namespace");
+        // Enter mount namespace. The cgroup namespace is rooted at the cgroup of the container.
+        debug!("Entering mount, IPC, UTS and cgroup namespace");
         sched::unshare(
-            CloneFlags::CLONE_NEWNS | CloneFlags::CLONE_NEWIPC | CloneFlags::CLONE_NEWUTS,
+            CloneFlags::CLONE_NEWNS
+                | CloneFlags::CLONE_NEWIPC
+                | CloneFlags::CLONE_NEWUTS
+                | CloneFlags::CLONE_NEWCGROUP,
         )
         .expect("failed to unshare");
 
+        // Hostname and domain name of the UTS namespace
+        self.set_hostname();
+
+        // Kernel parameters of the namespaces. Errors of the setup steps are reported
+        // to the forker that fails the start of the container.
+        if let Err(e) = self.set_sysctl() {
+            Self::setup_failed(&mut stream, e);
+        }
+
+        // Writable overlay over the root filesystem. Must be mounted before the mounts
+        // that target the root.
+        if let Some(overlay) = &self.overlay {
+            debug!("Mounting overlay on {}", self.root.display());
+            overlay.mount(&self.root, self.user_namespace.is_some());
+        }
+
         // Perform all mounts passed in mounts
         self.mount();
 
+        // SELinux context of the container process. Must be set before the pivot root
+        // because /proc might not be mounted in the container.
+        self.set_exec_context();
+
         // Set the root to the containers root mount point
         debug!("Pivot rooting to {}", self.root.display());
         self.pivot_rootfs(&self.root);
 
-        // Set current working directory to root
+        // Set current working directory to root. The working directory of the
+        // container process is set after the uid and gid are changed.
         debug!("Setting current working directory to root");
         env::set_current_dir("/").expect("failed to set cwd to /");
 
@@ -113,8 +178,27 @@ impl Init {
         // Apply resource limits
         self.set_rlimits();
 
+        // Working directory and file mode creation mask of the container process
+        if let Err(e) = self.set_cwd() {
+            Self::setup_failed(&mut stream, e);
+        }
+        self.set_umask();
+
+        // Report the successful setup to the forker
+        stream
+            .send(Message::Setup { result: Ok(()) })
+            .expect("failed to send setup result");
+
         // No new privileges
-        Self::set_no_new_privs(true);
+        if self.no_new_privs {
+            Self::set_no_new_privs(true);
+        }
+
+        // Restrict filesystem access
+        if let Some(landlock) = &self.landlock {
+            debug!("Applying landlock ruleset");
+            landlock.apply().expect("failed to apply landlock ruleset");
+        }
 
         // Capabilities
         self.drop_privileges();
@@ -140,6 +224,12 @@ impl Init {
                         env.push(s);
                     }
 
+                    // In learn mode the child moves the listener of its seccomp filter onto
+                    // a fd number reserved before the fork. Init fetches it from the child.
+                    let learn = self.seccomp.as_ref().filter(|filter| filter.learn());
+                    let reserved_fd = learn.map(|_| learn::reserve_fd());
+                    let listener_fd = reserved_fd.as_ref().map(AsRawFd::as_raw_fd);
+
                     // Start new process inside the container
                     let pid = match unsafe { fork().expect("failed to fork") } {
                         ForkResult::Parent { child } => child.as_raw() as Pid,
@@ -147,7 +237,15 @@ impl Init {
                             util::set_parent_death_signal(Signal::SIGKILL);
 
                             // Set seccomp filter
-                            if let Some(ref filter) = self.seccomp {
+                            if let (Some(filter), Some(listener_fd)) = (learn, listener_fd) {
+                                // Allow init to get the listener fd
+                                unsafe { libc::prctl(libc::PR_SET_DUMPABLE, 1) };
+                                let fd = filter
+                                    .apply_with_listener()
+                                    .expect("failed to apply seccomp filter.");
+                                learn::claim_fd(fd, listener_fd)
+                                    .expect("failed to move seccomp listener");
+                            } else if let Some(ref filter) = self.seccomp {
                                 filter.apply().expect("failed to apply seccomp filter.");
                             }
 
@@ -166,11 +264,30 @@ impl Init {
 
                     // Close the console fd used in the container binary only.
                     drop(console);
+                    // The reserved number is taken by the listener in the child only
+                    drop(reserved_fd);
 
                     // Inform the forker that we forked.
                     let message = Message::Forked { pid };
                     stream.send(&message).expect("failed to send fork result");
 
+                    // Continue the reported system calls until the child exits
+                    if let Some(listener_fd) = listener_fd {
+                        let syscalls = match learn::Listener::new(pid, listener_fd) {
+                            Ok(listener) => listener.run().into_iter().collect(),
+                            Err(e) => {
+                                // The child blocks on the first reported system call
+                                warn!("Failed to get seccomp listener of {}: {}", pid, e);
+                                let pid = unistd::Pid::from_raw(pid as i32);
+                                nix::sys::signal::kill(pid, Some(Signal::SIGKILL)).ok();
+                                Vec::new()
+                            }
+                        };
+                        stream
+                            .send(Message::Syscalls { syscalls })
+                            .expect("channel error");
+                    }
+
                     // Wait for the child to exit
                     let exit_status = loop {
                         debug!("Waiting for child process {} to exit", pid);
@@ -208,6 +325,41 @@ impl Init {
         }
     }
 
+    /// Move the calling process into the cgroups of the container
+    pub fn join_cgroups(&self) {
+        let pid = unistd::getpid().to_string();
+        for procs in &self.cgroups {
+            debug!("Joining cgroup {}", procs.display());
+            std::fs::write(procs, &pid).expect("failed to join cgroup");
+        }
+    }
+
+    /// Create a new user namespace. The uid and gid maps are written by the forker
+    /// after this process requested them on `stream`. Afterwards switch to the root
+    /// user of the namespace.
+    pub fn enter_user_namespace(&self, stream: &mut FramedUnixStream) {
+        // Named network namespaces are owned by the initial user namespace and
+        // cannot be entered from within the new user namespace.
+        if self.network_is_named() {
+            self.network();
+        }
+
+        debug!("Entering user namespace");
+        unshare(CloneFlags::CLONE_NEWUSER).expect("failed to create user namespace");
+
+        // Request the id mappings and wait until they are written
+        stream.send(()).expect("failed to request id maps");
+        stream
+            .recv::<()>()
+            .expect("failed to receive id map confirmation")
+            .expect("stream closed");
+
+        let root = unistd::Gid::from_raw(0);
+        unistd::setresgid(root, root, root).expect("failed to set resgid");
+        let root = unistd::Uid::from_raw(0);
+        unistd::setresuid(root, root, root).expect("failed to set resuid");
+    }
+
     /// Set uid/gid
     fn set_ids(&self) {
         let uid = self.uid;
@@ -311,6 +463,76 @@ impl Init {
         }
     }
 
+    fn set_cwd(&self) -> anyhow::Result<()> {
+        debug!(
+            "Setting current working directory to {}",
+            self.cwd.display()
+        );
+        env::set_current_dir(&self.cwd)
+            .with_context(|| format!("failed to set cwd to {}", self.cwd.display()))
+    }
+
+    fn set_umask(&self) {
+        if let Some(umask) = self.umask {
+            debug!("Setting umask to {:04o}", umask);
+            nix::sys::stat::umask(Mode::from_bits_truncate(umask));
+        }
+    }
+
+    fn set_controlling_terminal() {
+        debug!("Setting controlling terminal");
+        let result = unsafe { libc::ioctl(libc::STDIN_FILENO, libc::TIOCSCTTY, 0) };
+        Errno::result(result)
+            .map(drop)
+            .expect("failed to set controlling terminal");
+    }
+
+    fn set_hostname(&self) {
+        if let Some(hostname) = &self.hostname {
+            debug!("Setting hostname to {}", hostname);
+            unistd::sethostname(hostname.as_str()).expect("failed to set hostname");
+        }
+        if let Some(domainname) = &self.domainname {
+            debug!("Setting domain name to {}", domainname);
+            let result = unsafe {
+                libc::setdomainname(domainname.as_ptr() as *const libc::c_char, domainname.len())
+            };
+            Errno::result(result)
+                .map(drop)
+                .expect("failed to set domain name");
+        }
+    }
+
+    /// Write the sysctl settings of the container to /proc/sys. The settings apply to
+    /// the namespaces of init and must be written after the namespaces are entered.
+    /// Report a failed setup step to the forker and exit
+    fn setup_failed(stream: &mut FramedUnixStream, error: anyhow::Error) -> ! {
+        let result = Err(format!("{:#}", error));
+        stream
+            .send(Message::Setup { result })
+            .expect("failed to send setup result");
+        exit(1)
+    }
+
+    fn set_sysctl(&self) -> anyhow::Result<()> {
+        for (key, value) in self.sysctl.iter().sorted() {
+            debug!("Setting sysctl {} to {}", key, value);
+            let path = Path::new("/proc/sys").join(key.replace('.', "/"));
+            std::fs::write(&path, value.as_bytes())
+                .with_context(|| format!("failed to set sysctl {} to {}", key, value))?;
+        }
+        Ok(())
+    }
+
+    /// Set the SELinux context of the next execve of this process and its children
+    fn set_exec_context(&self) {
+        if let Some(context) = &self.exec_context {
+            debug!("Setting SELinux exec context {}", context);
+            std::fs::write("/proc/self/attr/exec", context.as_bytes())
+                .expect("failed to set SELinux exec context");
+        }
+    }
+
     fn set_no_new_privs(value: bool) {
         #[cfg(target_os = "android")]
         pub const PR_SET_NO_NEW_PRIVS: libc::c_int = 38;
@@ -324,6 +546,10 @@ impl Init {
             .expect("failed to set PR_SET_NO_NEW_PRIVS")
     }
 
+    fn network_is_named(&self) -> bool {
+        matches!(self.network, Some(Network::Namespace(_)))
+    }
+
     fn network(&self) {
         match &self.network {
             Some(Network::Host) => {
@@ -348,6 +574,16 @@ impl Init {
                     warn!("Failed to attach to network namespace \"{}\"", namespace);
                 }
             }
+            Some(Network::Bridge) => {
+                // The network namespace is created by the trampoline and the container
+                // end of the veth pair is moved into it by the forker.
+                let interface = self.interface.as_ref().expect("missing bridge interface");
+                debug!(
+                    "Configuring bridge network with address {}/{}",
+                    interface.address, interface.prefix
+                );
+                network::configure(interface).expect("failed to configure network");
+            }
             None => {
                 debug!("Unsharing network namespace");
                 unshare(CloneFlags::CLONE_NEWNET).expect("failed to unshare");
@@ -497,3 +733,68 @@ impl Mount {
         .expect(&self.error_msg);
     }
 }
+
+/// Writable overlay over the root filesystem of a container
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Overlay {
+    /// Directory containing the upper and work directory
+    pub dir: PathBuf,
+    /// Tmpfs mounted on `dir` for an overlay that is discarded when the container exits
+    pub tmpfs: Option<Mount>,
+}
+
+impl Overlay {
+    /// Mount the overlay on `root` with `root` as lower directory
+    pub(super) fn mount(&self, root: &Path, user_namespace: bool) {
+        if let Some(tmpfs) = &self.tmpfs {
+            tmpfs.mount();
+        }
+
+        let upper = self.dir.join("upper");
+        let work = self.dir.join("work");
+        for dir in [&upper, &work] {
+            if !dir.exists() {
+                std::fs::create_dir(dir).expect("failed to create overlay directory");
+            }
+        }
+
+        // The root directory of the overlay has the permissions of the upper directory
+        let mode = std::fs::metadata(root)
+            .expect("failed to stat root")
+            .permissions()
+            .mode();
+        std::fs::set_permissions(&upper, Permissions::from_mode(mode & 0o7777))
+            .expect("failed to set permissions of overlay directory");
+
+        // Extended attributes of overlays mounted in a user namespace are in the user namespace.
+        // The `userxattr` option is available since Linux 5.11.
+        let mut data = format!(
+            "lowerdir={},upperdir={},workdir={}",
+            escape_overlay_option(root),
+            escape_overlay_option(&upper),
+            escape_overlay_option(&work)
+        );
+        if user_namespace {
+            data.push_str(",userxattr");
+        }
+        let source = PathBuf::from("overlay");
+        let flags = mount::MsFlags::MS_NOSUID;
+        Mount::new(
+            Some(source),
+            root.to_owned(),
+            Some("overlay"),
+            flags,
+            Some(data),
+        )
+        .mount();
+    }
+}
+
+/// Escape the separators of overlay mount options. Container roots contain a colon.
+fn escape_overlay_option(path: &Path) -> String {
+    path.display()
+        .to_string()
+        .replace('\\', "\\\\")
+        .replace(',', "\\,")
+        .replace(':', "\\:")
+}
@@ -3,3 +3,4 @@ mod init;
 mod util;
 
 pub use forker::{start, Forker, Notification, Streams};
+pub use init::UserNamespace;
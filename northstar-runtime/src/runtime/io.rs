@@ -1,78 +1,447 @@
-use std::os::unix::{
-    net::UnixStream as StdUnixStream,
-    prelude::{FromRawFd, OwnedFd},
+use std::{
+    future::pending,
+    os::unix::{
+        net::UnixStream as StdUnixStream,
+        prelude::{AsRawFd, FromRawFd, OwnedFd, RawFd},
+    },
+    sync::Arc,
 };
 
+use super::{
+    config,
+    syslog::{Entry, Syslog},
+    Pid,
+};
 use crate::{
     common::container::Container,
-    npk::manifest::{self, io::Output},
+    npk::manifest::{
+        self,
+        io::{Level, Log, Output},
+    },
+};
+use bytes::Bytes;
+use log::{debug, warn};
+use nix::{
+    errno::Errno,
+    fcntl::{self, FcntlArg, FdFlag, OFlag},
+    libc,
+    pty::{self, Winsize},
+    sys::stat::Mode,
+    unistd,
 };
-use log::debug;
-use nix::{fcntl::OFlag, sys::stat::Mode};
 use tokio::{
-    io::{self, copy_buf, AsyncBufReadExt, AsyncWrite},
+    io::{self, copy_buf, unix::AsyncFd, AsyncBufReadExt, AsyncWrite, AsyncWriteExt},
     net::UnixStream,
+    select,
+    signal::unix::{signal, Signal, SignalKind},
+    sync::{mpsc, watch},
     task::{self},
 };
 
 /// Buffer size for stdout/stderr forwarding
 const BUFFER_SIZE: usize = 16 * 4048;
 
+/// Number of pending attach requests of a pseudo terminal
+const ATTACH_BUFFER_SIZE: usize = 4;
+
 pub struct ContainerIo {
     pub io: [OwnedFd; 3],
+    pub tty: Option<Tty>,
+    /// Pid of the container reported in syslog entries. Set once the container is created.
+    pub pid: watch::Sender<Option<Pid>>,
+}
+
+/// Input and output channel of a console connection attached to a pseudo terminal
+type Session = (mpsc::Receiver<Bytes>, mpsc::Sender<Bytes>);
+
+/// Master side of the pseudo terminal of a container
+#[derive(Debug)]
+pub struct Tty {
+    master: Arc<AsyncFd<OwnedFd>>,
+    attach_tx: mpsc::Sender<Session>,
+}
+
+impl Tty {
+    /// Attach a console connection. The output of the terminal is sent to `output` and
+    /// the data received on `input` is written to the terminal until one of the channels
+    /// is closed. A previously attached connection is detached.
+    pub fn attach(
+        &self,
+        input: mpsc::Receiver<Bytes>,
+        output: mpsc::Sender<Bytes>,
+    ) -> io::Result<()> {
+        self.attach_tx
+            .try_send((input, output))
+            .map_err(|_| io::Error::new(io::ErrorKind::Other, "terminal is closed or busy"))
+    }
+
+    /// Set the window size of the terminal. The foreground process group of the container
+    /// receives a SIGWINCH.
+    pub fn resize(&self, rows: u16, cols: u16) -> io::Result<()> {
+        let size = Winsize {
+            ws_row: rows,
+            ws_col: cols,
+            ws_xpixel: 0,
+            ws_ypixel: 0,
+        };
+        set_window_size(self.master.as_raw_fd(), &size)
+    }
 }
 
 /// Create a new pty handle if configured in the manifest or open /dev/null instead.
-pub async fn open(container: &Container, io: &manifest::io::Io) -> io::Result<ContainerIo> {
-    debug!(
-        "Container {} stdout is {}",
-        container,
-        serde_plain::to_string(&io.stdout).expect("internal error")
-    );
-    debug!(
-        "Container {} stderr is {}",
-        container,
-        serde_plain::to_string(&io.stderr).expect("internal error")
-    );
+pub async fn open(
+    container: &Container,
+    io: &manifest::io::Io,
+    syslog: &config::Syslog,
+) -> io::Result<ContainerIo> {
+    debug!("Container {} stdout is {}", container, io.stdout);
+    debug!("Container {} stderr is {}", container, io.stderr);
+
+    let (pid, pid_rx) = watch::channel(None);
+
+    if io.tty {
+        let (io, tty) = open_tty(container, &io.stdout, syslog, pid_rx)?;
+        return Ok(ContainerIo {
+            io,
+            tty: Some(tty),
+            pid,
+        });
+    }
 
     // Open dev null - needed in any case for stdin
     let dev_null = nix::fcntl::open("/dev/null", OFlag::O_RDWR, Mode::empty())
         .map_err(|err| io::Error::from_raw_os_error(err as i32))
         .map(|fd| unsafe { OwnedFd::from_raw_fd(fd) })?;
-    let stdout = forward(&io.stdout, &dev_null, container, tokio::io::stdout())?;
-    let stderr = forward(&io.stderr, &dev_null, container, tokio::io::stderr())?;
+    let stdout = forward(
+        &io.stdout,
+        "stdout",
+        &dev_null,
+        container,
+        syslog,
+        pid_rx.clone(),
+        tokio::io::stdout(),
+    )?;
+    let stderr = forward(
+        &io.stderr,
+        "stderr",
+        &dev_null,
+        container,
+        syslog,
+        pid_rx,
+        tokio::io::stderr(),
+    )?;
     let io = [dev_null, stdout, stderr];
 
-    Ok(ContainerIo { io })
+    Ok(ContainerIo { io, tty: None, pid })
+}
+
+/// Allocate a pseudo terminal. The slave side is used for stdin, stdout and stderr. The
+/// output of the master side is forwarded to an attached console connection or to
+/// `destination` if no connection is attached.
+fn open_tty(
+    container: &Container,
+    destination: &Output,
+    syslog: &config::Syslog,
+    pid: watch::Receiver<Option<Pid>>,
+) -> io::Result<([OwnedFd; 3], Tty)> {
+    debug!("Container {} uses a pseudo terminal", container);
+
+    // The runtime terminal determines the window size if the output is inherited
+    let inherit =
+        *destination == Output::Inherit && unistd::isatty(libc::STDOUT_FILENO) == Ok(true);
+    let size = if inherit {
+        Some(window_size(libc::STDOUT_FILENO)?)
+    } else {
+        None
+    };
+
+    let pty = pty::openpty(size.as_ref(), None).map_err(errno)?;
+    let master = unsafe { OwnedFd::from_raw_fd(pty.master) };
+    let slave = unsafe { OwnedFd::from_raw_fd(pty.slave) };
+
+    // Do not leak the terminal into processes spawned by the runtime
+    for fd in [&master, &slave] {
+        fcntl::fcntl(fd.as_raw_fd(), FcntlArg::F_SETFD(FdFlag::FD_CLOEXEC)).map_err(errno)?;
+    }
+    fcntl::fcntl(master.as_raw_fd(), FcntlArg::F_SETFL(OFlag::O_NONBLOCK)).map_err(errno)?;
+
+    let lines = match destination {
+        Output::Pipe(log) => Some(Lines::new(container, "stdout", log, None, pid)?),
+        Output::Syslog(log) => Some(Lines::new(container, "stdout", log, Some(syslog), pid)?),
+        Output::Discard | Output::Inherit => None,
+    };
+    let master = Arc::new(AsyncFd::new(master)?);
+    let (attach_tx, attach_rx) = mpsc::channel(ATTACH_BUFFER_SIZE);
+    let window_change = if inherit {
+        Some(signal(SignalKind::window_change())?)
+    } else {
+        None
+    };
+    task::spawn(forward_tty(
+        container.clone(),
+        master.clone(),
+        destination.clone(),
+        lines,
+        attach_rx,
+        window_change,
+    ));
+
+    let io = [slave.try_clone()?, slave.try_clone()?, slave];
+    Ok((io, Tty { master, attach_tx }))
+}
+
+/// Forward the output of the terminal to the attached console connection or `destination`
+/// and the input of the attached connection to the terminal. The task ends when all file
+/// descriptors of the slave side are closed.
+async fn forward_tty(
+    container: Container,
+    master: Arc<AsyncFd<OwnedFd>>,
+    destination: Output,
+    mut lines: Option<Lines>,
+    mut attach_rx: mpsc::Receiver<Session>,
+    mut window_change: Option<Signal>,
+) {
+    let mut session: Option<Session> = None;
+    let mut line = Vec::new();
+    let mut buffer = vec![0u8; BUFFER_SIZE];
+
+    loop {
+        select! {
+            Some(attached) = attach_rx.recv() => {
+                debug!("Attached to the terminal of {}", container);
+                session = Some(attached);
+            }
+            input = async {
+                match session.as_mut() {
+                    Some((input, _)) => input.recv().await,
+                    None => pending().await,
+                }
+            } => match input {
+                Some(input) => {
+                    if let Err(e) = write_all(&master, &input).await {
+                        warn!("Failed to write to the terminal of {}: {}", container, e);
+                    }
+                }
+                None => {
+                    debug!("Detached from the terminal of {}", container);
+                    session = None;
+                }
+            },
+            _ = async {
+                match window_change.as_mut() {
+                    Some(window_change) => window_change.recv().await,
+                    None => pending().await,
+                }
+            } => {
+                if let Err(e) = window_size(libc::STDOUT_FILENO)
+                    .and_then(|size| set_window_size(master.as_raw_fd(), &size))
+                {
+                    warn!("Failed to resize the terminal of {}: {}", container, e);
+                }
+            }
+            read = read(&master, &mut buffer) => {
+                let output = match read {
+                    Ok(0) | Err(_) => break,
+                    Ok(n) => &buffer[..n],
+                };
+
+                if let Some((_, tx)) = &session {
+                    if tx.send(Bytes::copy_from_slice(output)).await.is_ok() {
+                        continue;
+                    }
+                    debug!("Detached from the terminal of {}", container);
+                    session = None;
+                }
+
+                match (&destination, lines.as_mut()) {
+                    (Output::Inherit, _) => {
+                        let mut stdout = tokio::io::stdout();
+                        stdout.write_all(output).await.ok();
+                        stdout.flush().await.ok();
+                    }
+                    (_, Some(lines)) => {
+                        line.extend_from_slice(output);
+                        while let Some(n) = line.iter().position(|b| *b == b'\n') {
+                            let l = String::from_utf8_lossy(&line[..n]).into_owned();
+                            lines.write(l.trim_end_matches('\r')).await;
+                            line.drain(..=n);
+                        }
+                    }
+                    _ => (),
+                }
+            }
+        }
+    }
+    debug!("Terminal of {} closed", container);
+}
+
+/// Read from the master side of the terminal. A closed slave side is reported as EOF.
+async fn read(fd: &AsyncFd<OwnedFd>, buffer: &mut [u8]) -> io::Result<usize> {
+    loop {
+        let mut guard = fd.readable().await?;
+        match guard.try_io(|fd| unistd::read(fd.as_raw_fd(), buffer).map_err(errno)) {
+            Ok(Err(e)) if e.raw_os_error() == Some(libc::EIO) => break Ok(0),
+            Ok(result) => break result,
+            Err(_would_block) => continue,
+        }
+    }
+}
+
+/// Write `buffer` to the master side of the terminal
+async fn write_all(fd: &AsyncFd<OwnedFd>, mut buffer: &[u8]) -> io::Result<()> {
+    while !buffer.is_empty() {
+        let mut guard = fd.writable().await?;
+        match guard.try_io(|fd| unistd::write(fd.as_raw_fd(), buffer).map_err(errno)) {
+            Ok(result) => buffer = &buffer[result?..],
+            Err(_would_block) => continue,
+        }
+    }
+    Ok(())
+}
+
+/// Get the window size of the terminal `fd`
+fn window_size(fd: RawFd) -> io::Result<Winsize> {
+    let mut size = Winsize {
+        ws_row: 0,
+        ws_col: 0,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    };
+    let result = unsafe { libc::ioctl(fd, libc::TIOCGWINSZ, &mut size) };
+    Errno::result(result).map(|_| size).map_err(errno)
+}
+
+/// Set the window size of the terminal `fd`
+fn set_window_size(fd: RawFd, size: &Winsize) -> io::Result<()> {
+    let result = unsafe { libc::ioctl(fd, libc::TIOCSWINSZ, size) };
+    Errno::result(result).map(drop).map_err(errno)
+}
+
+fn errno(err: Errno) -> io::Error {
+    io::Error::from_raw_os_error(err as i32)
 }
 
-/// Spawn a task that forwards stdout/stderr to the logging system or stdout/stderr
+/// Spawn a task that forwards stdout/stderr to the logging system, syslog or stdout/stderr
 fn forward<W: AsyncWrite + Send + Sync + Unpin + 'static>(
     destination: &Output,
+    stream: &'static str,
     dev_null: &OwnedFd,
     container: &Container,
+    syslog: &config::Syslog,
+    pid: watch::Receiver<Option<Pid>>,
     mut out: W,
 ) -> io::Result<OwnedFd> {
-    match destination {
-        Output::Discard => dev_null.try_clone(),
-        Output::Pipe => {
-            let (read, write) = StdUnixStream::pair()?;
-            read.set_nonblocking(true)?;
-            let mut lines = io::BufReader::new(UnixStream::from_std(read)?).lines();
-            let target = container.to_string();
-            task::spawn(async move {
-                while let Ok(Some(line)) = lines.next_line().await {
-                    log::debug!(target: &target, "{}", line);
-                }
-            });
-            Ok(write.into())
-        }
+    let mut lines = match destination {
+        Output::Discard => return dev_null.try_clone(),
+        Output::Pipe(log) => Lines::new(container, stream, log, None, pid)?,
+        Output::Syslog(log) => Lines::new(container, stream, log, Some(syslog), pid)?,
         Output::Inherit => {
             let (read, write) = StdUnixStream::pair()?;
             read.set_nonblocking(true)?;
             let mut read = io::BufReader::with_capacity(BUFFER_SIZE, UnixStream::from_std(read)?);
             task::spawn(async move { copy_buf(&mut read, &mut out).await });
-            Ok(write.into())
+            return Ok(write.into());
+        }
+    };
+
+    let (read, write) = StdUnixStream::pair()?;
+    read.set_nonblocking(true)?;
+    let mut reader = io::BufReader::new(UnixStream::from_std(read)?).lines();
+    task::spawn(async move {
+        while let Ok(Some(line)) = reader.next_line().await {
+            lines.write(&line).await;
+        }
+    });
+    Ok(write.into())
+}
+
+/// Writes the lines of `pipe` and `syslog` outputs
+struct Lines {
+    container: Container,
+    stream: &'static str,
+    level: Level,
+    tag: String,
+    /// Module path of log records. The container formatted as string.
+    module_path: String,
+    /// Send the lines to syslog instead of the logging system
+    syslog: Option<Syslog>,
+    pid: watch::Receiver<Option<Pid>>,
+    /// Syslog sending failed before. Failures are reported once.
+    failed: bool,
+}
+
+impl Lines {
+    fn new(
+        container: &Container,
+        stream: &'static str,
+        log: &Log,
+        syslog: Option<&config::Syslog>,
+        pid: watch::Receiver<Option<Pid>>,
+    ) -> io::Result<Lines> {
+        // The default tag of syslog entries is the name and log lines use the container
+        let tag = match (&log.tag, syslog) {
+            (Some(tag), _) => tag.to_string(),
+            (None, Some(_)) => container.name().to_string(),
+            (None, None) => container.to_string(),
+        };
+        Ok(Lines {
+            container: container.clone(),
+            stream,
+            level: log.level,
+            tag,
+            module_path: container.to_string(),
+            syslog: syslog.map(Syslog::new).transpose()?,
+            pid,
+            failed: false,
+        })
+    }
+
+    async fn write(&mut self, line: &str) {
+        let syslog = match &self.syslog {
+            Some(syslog) => syslog,
+            None => {
+                let level = match self.level {
+                    Level::Error => log::Level::Error,
+                    Level::Warn => log::Level::Warn,
+                    Level::Info => log::Level::Info,
+                    Level::Debug => log::Level::Debug,
+                    Level::Trace => log::Level::Trace,
+                };
+                // The container is the module path of the record. The target is the
+                // tag which is not necessarily the container.
+                if level <= log::STATIC_MAX_LEVEL && level <= log::max_level() {
+                    log::logger().log(
+                        &log::Record::builder()
+                            .args(format_args!("{}", line))
+                            .level(level)
+                            .target(&self.tag)
+                            .module_path(Some(&self.module_path))
+                            .build(),
+                    );
+                }
+                return;
+            }
+        };
+
+        let pid = *self.pid.borrow();
+        let entry = Entry {
+            container: &self.container,
+            pid,
+            stream: self.stream,
+            level: self.level,
+            tag: &self.tag,
+            message: line,
+        };
+        match syslog.send(&entry).await {
+            Ok(()) => self.failed = false,
+            Err(e) if !self.failed => {
+                warn!(
+                    "Failed to send output of {} to syslog: {}",
+                    self.container, e
+                );
+                self.failed = true;
+            }
+            Err(_) => (),
         }
     }
 }
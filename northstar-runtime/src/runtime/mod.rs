@@ -1,5 +1,7 @@
 use self::fork::Streams;
-use crate::{api, api::model::Container, runtime::ipc::AsyncFramedUnixStream};
+use crate::{
+    api, api::model::Container, common::version::Version, runtime::ipc::AsyncFramedUnixStream,
+};
 use async_stream::stream;
 use config::Config;
 use console::{Configuration, Permissions};
@@ -17,6 +19,7 @@ use nix::{
     },
     unistd,
 };
+use pkg_version::{pkg_version_major, pkg_version_minor, pkg_version_patch};
 use serde::{Deserialize, Serialize};
 use state::State;
 use std::{convert::TryFrom, future::Future, path::Path};
@@ -32,16 +35,20 @@ use tokio_util::sync::{CancellationToken, DropGuard};
 mod cgroups;
 mod console;
 mod debug;
+mod devices;
 mod error;
 mod fork;
 mod io;
 mod ipc;
 mod key;
 mod mount;
+mod network;
 mod repository;
 mod state;
 mod stats;
+mod syslog;
 mod token;
+mod volume;
 
 /// Runtime configuration
 pub mod config;
@@ -52,6 +59,14 @@ type RepositoryId = String;
 type ExitCode = i32;
 type Pid = u32;
 
+/// Runtime version. The prerelease of the package version is not part of it: a runtime
+/// built from 0.6.1-pre satisfies a `min_runtime_version` of 0.6.1.
+pub const VERSION: Version = Version::new(
+    pkg_version_major!(),
+    pkg_version_minor!(),
+    pkg_version_patch!(),
+);
+
 /// Environment variable name passed to the container with the containers name
 const ENV_NAME: &str = "NORTHSTAR_NAME";
 /// Environment variable name passed to the container with the containers version
@@ -69,6 +84,10 @@ enum Event {
     Shutdown,
     /// Container event
     Container(Container, ContainerEvent),
+    /// System calls of a container recorded in seccomp learn mode
+    Seccomp(Container, Vec<u32>),
+    /// Container with pid exceeded its deadline and shall be stopped with signal
+    Deadline(Container, Pid, Signal),
 }
 
 #[derive(Clone, Debug)]
@@ -77,12 +96,16 @@ enum ContainerEvent {
     Started,
     /// Container exited with status
     Exit(ExitStatus),
+    /// Container exited with status after it was stopped for exceeding its deadline
+    DeadlineExit(ExitStatus),
     /// Container is installed
     Installed,
     /// Container is uninstalled
     Uninstalled,
     /// CGroup event
     CGroup(CGroupEvent),
+    /// Usage of the persist mount crossed the configured threshold
+    Persist(volume::Usage),
 }
 
 /// Events generated by cgroup controllers
@@ -308,10 +331,15 @@ async fn run(
         loop {
             select! {
                 Some(event) = event_rx.recv() => yield event,
-                Ok(Some(fork::Notification::Exit { container, exit_status })) = exit_notifications.recv() => {
-                    let event = ContainerEvent::Exit(exit_status);
-                    yield Event::Container(container, event);
-                }
+                Ok(Some(notification)) = exit_notifications.recv() => match notification {
+                    fork::Notification::Exit { container, exit_status } => {
+                        let event = ContainerEvent::Exit(exit_status);
+                        yield Event::Container(container, event);
+                    }
+                    fork::Notification::Seccomp { container, syscalls } => {
+                        yield Event::Seccomp(container, syscalls);
+                    }
+                },
                 else => unimplemented!(),
             }
         }
@@ -343,6 +371,13 @@ async fn run(
                     }
                     // Container event
                     Event::Container(container, event) => state.on_event(&container, &event, false).await,
+                    // Seccomp learn mode
+                    Event::Seccomp(container, syscalls) => {
+                        state.on_seccomp(&container, syscalls);
+                        Ok(())
+                    }
+                    // Deadline of a container
+                    Event::Deadline(container, pid, signal) => state.on_deadline(&container, pid, signal).await,
                 } {
                     break Err(e);
                 }
@@ -1,7 +1,11 @@
 use super::{key::PublicKey, repository::Npk};
 use crate::{
     common::version::Version,
-    npk::{dm_verity::VerityHeader, manifest::selinux::Selinux, npk::Hashes},
+    npk::{
+        dm_verity::VerityHeader,
+        manifest::selinux::Selinux,
+        npk::{FsType, Hashes},
+    },
 };
 use anyhow::{anyhow, bail, Context, Result};
 use devicemapper::{DevId, DmName, DmOptions};
@@ -18,8 +22,6 @@ use tokio::{task, time};
 
 pub use nix::mount::MsFlags as MountFlags;
 
-const FS_TYPE: &str = "squashfs";
-
 #[cfg(not(target_os = "android"))]
 const DEVICE_MAPPER_DEV: &str = "/dev/dm-";
 #[cfg(target_os = "android")]
@@ -80,6 +82,7 @@ impl MountControl {
         let fd = npk.as_raw_fd();
         let fsimg_size = npk.fsimg_size();
         let fsimg_offset = npk.fsimg_offset();
+        let fs_type = npk.meta().fs;
         let name = npk.manifest().name.clone();
         let version = npk.manifest().version.clone();
         let verity_header = npk.verity_header().cloned();
@@ -98,6 +101,7 @@ impl MountControl {
                 fd,
                 fsimg_offset,
                 fsimg_size,
+                fs_type,
                 &version,
                 verity_header,
                 selinux,
@@ -124,6 +128,71 @@ impl MountControl {
         })
     }
 
+    /// Attach the filesystem image `image` to a loop device and mount it read write on `target`
+    pub(super) fn mount_image(
+        &self,
+        image: &Path,
+        target: &Path,
+        fstype: &str,
+    ) -> impl Future<Output = Result<()>> {
+        let lc = self.lc.clone();
+        let image = image.to_owned();
+        let target = target.to_owned();
+        let fstype = fstype.to_owned();
+        let lo_timeout = self.lo_timeout;
+
+        task::spawn_blocking(move || {
+            let start = time::Instant::now();
+            let file = std::fs::OpenOptions::new()
+                .read(true)
+                .write(true)
+                .open(&image)
+                .with_context(|| format!("failed to open {}", image.display()))?;
+
+            // The loop device is detached with the umount or when the mount fails
+            let loop_device = loop {
+                let loop_device = lc.next_free()?;
+                if loop_device
+                    .with()
+                    .autoclear(true)
+                    .attach_fd(file.as_raw_fd())
+                    .is_ok()
+                {
+                    break loop_device;
+                }
+                if start.elapsed() > lo_timeout {
+                    bail!(
+                        "failed to acquire loop device: timeout after {}",
+                        format_duration(lo_timeout)
+                    );
+                }
+            };
+            let device = loop_device
+                .path()
+                .ok_or_else(|| anyhow!("failed to get loop device path"))?;
+
+            debug!(
+                "Mounting {} fs on {} to {}",
+                fstype,
+                device.display(),
+                target.display(),
+            );
+            let flags = MountFlags::MS_NOSUID | MountFlags::MS_NODEV | MountFlags::MS_NOEXEC;
+            nix::mount::mount(
+                Some(&device),
+                &target,
+                Some(fstype.as_str()),
+                flags,
+                None::<&str>,
+            )
+            .with_context(|| format!("failed to mount {}", device.display()))
+        })
+        .map(|r| match r {
+            Ok(r) => r,
+            Err(e) => panic!("task error: {}", e),
+        })
+    }
+
     /// Umount target
     pub(super) fn umount(target: &Path) -> impl Future<Output = Result<()>> {
         let target = target.to_owned();
@@ -161,6 +230,7 @@ fn mount(
     fd: RawFd,
     fsimg_offset: u64,
     fsimg_size: u64,
+    fs_type: FsType,
     version: &Version,
     verity_header: Option<VerityHeader>,
     selinux: Option<Selinux>,
@@ -254,13 +324,14 @@ fn mount(
     // Finally mount
     debug!(
         "Mounting {} fs on {} to {}",
-        FS_TYPE,
+        fs_type,
         device.display(),
         target.display(),
     );
     let flags = MountFlags::MS_RDONLY | MountFlags::MS_NOSUID;
     let source = Some(&device);
-    let fstype = Some(FS_TYPE);
+    let fstype = fs_type.to_string();
+    let fstype = Some(fstype.as_str());
     let data = if let Some(selinux) = selinux {
         if Path::new("/sys/fs/selinux/enforce").exists() {
             Some(format!("{}{}", "context=", selinux.context.as_str()))
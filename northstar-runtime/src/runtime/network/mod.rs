@@ -0,0 +1,180 @@
+use super::{config, Pid};
+use anyhow::{anyhow, Context, Result};
+use log::{debug, info, warn};
+use netlink::Netlink;
+use nix::errno::Errno;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashSet, fs, net::Ipv4Addr, os::unix::prelude::AsRawFd};
+
+mod netlink;
+
+/// Name of the interface in the network namespace of a container
+const CONTAINER_INTERFACE: &str = "eth0";
+/// Prefix of the host end of the veth pairs
+const HOST_INTERFACE_PREFIX: &str = "nsv";
+
+/// Network interface of a container attached to the bridge
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Interface {
+    /// Name of the bridge
+    pub bridge: String,
+    /// Name of the host end of the veth pair
+    pub host: String,
+    /// Address of the container
+    pub address: Ipv4Addr,
+    /// Prefix length of the subnet
+    pub prefix: u8,
+    /// Address of the bridge and default gateway
+    pub gateway: Ipv4Addr,
+}
+
+/// Runtime managed bridge and the address allocation of the containers attached to it
+#[derive(Debug)]
+pub(super) struct Bridge {
+    config: config::Bridge,
+    /// True if the bridge was created by the runtime and is removed on shutdown
+    created: bool,
+    /// Subnet host numbers in use by containers
+    allocated: HashSet<u32>,
+}
+
+impl Bridge {
+    /// Create the bridge described in `config` if it does not exist, assign the gateway
+    /// address and bring it up.
+    pub fn new(config: &config::Bridge) -> Result<Bridge> {
+        let mut netlink = Netlink::new().context("failed to open netlink socket")?;
+        let name = config.name.as_str();
+
+        let created = match netlink.create_bridge(name) {
+            Ok(()) => {
+                info!("Created bridge {}", name);
+                true
+            }
+            Err(Errno::EEXIST) => {
+                debug!("Using existing bridge {}", name);
+                false
+            }
+            Err(e) => return Err(e).context(format!("failed to create bridge {}", name)),
+        };
+
+        let gateway = config.subnet.gateway()?;
+        match netlink.add_address(name, gateway, config.subnet.prefix) {
+            Ok(()) | Err(Errno::EEXIST) => (),
+            Err(e) => {
+                return Err(e).context(format!("failed to add address {} to {}", gateway, name))
+            }
+        }
+        netlink
+            .set_up(name)
+            .context(format!("failed to bring up {}", name))?;
+
+        Ok(Bridge {
+            config: config.clone(),
+            created,
+            allocated: HashSet::new(),
+        })
+    }
+
+    /// Allocate an address and a host interface name for a container
+    pub fn allocate(&mut self) -> Result<Interface> {
+        let subnet = &self.config.subnet;
+        // Skip the network address and the gateway. The last address is the broadcast address.
+        let n = (2..(subnet.size() - 1) as u32)
+            .find(|n| !self.allocated.contains(n))
+            .ok_or_else(|| anyhow!("no free address in {}", subnet))?;
+        self.allocated.insert(n);
+
+        Ok(Interface {
+            bridge: self.config.name.clone(),
+            host: format!("{}{}", HOST_INTERFACE_PREFIX, n),
+            address: subnet.host(n)?,
+            prefix: subnet.prefix,
+            gateway: subnet.gateway()?,
+        })
+    }
+
+    /// Remove the host end of the veth pair of `interface` and release its address
+    pub fn release(&mut self, interface: &Interface) {
+        if let Err(e) = detach(interface) {
+            warn!("Failed to remove {}: {}", interface.host, e);
+        }
+        let n = u32::from(interface.address) - u32::from(self.config.subnet.address);
+        self.allocated.remove(&n);
+    }
+
+    /// Remove the bridge if it was created by the runtime
+    pub fn shutdown(self) -> Result<()> {
+        if self.created {
+            info!("Removing bridge {}", self.config.name);
+            Netlink::new()
+                .and_then(|mut netlink| netlink.delete_link(&self.config.name))
+                .context(format!("failed to remove bridge {}", self.config.name))?;
+        }
+        Ok(())
+    }
+}
+
+/// Create a veth pair for `interface`. The container end is created in the network
+/// namespace of `pid` and the host end is attached to the bridge.
+pub fn attach(interface: &Interface, pid: Pid) -> Result<()> {
+    let netns = format!("/proc/{}/ns/net", pid);
+    let netns = fs::File::open(&netns).context(format!("failed to open {}", netns))?;
+    let mut netlink = Netlink::new().context("failed to open netlink socket")?;
+
+    // Remove a stale host interface with the same name
+    match netlink.delete_link(&interface.host) {
+        Ok(()) => warn!("Removed stale interface {}", interface.host),
+        Err(Errno::ENODEV) => (),
+        Err(e) => return Err(e).context(format!("failed to remove {}", interface.host)),
+    }
+
+    debug!(
+        "Creating veth pair {} and {} of {}",
+        interface.host, CONTAINER_INTERFACE, pid
+    );
+    netlink
+        .create_veth(&interface.host, CONTAINER_INTERFACE, netns.as_raw_fd())
+        .context(format!("failed to create veth pair {}", interface.host))?;
+    netlink
+        .set_master(&interface.host, &interface.bridge)
+        .context(format!(
+            "failed to attach {} to {}",
+            interface.host, interface.bridge
+        ))?;
+    netlink
+        .set_up(&interface.host)
+        .context(format!("failed to bring up {}", interface.host))
+}
+
+/// Remove the host end of the veth pair of `interface`. The container end is removed
+/// along with it. The kernel removes the pair when the network namespace of the
+/// container is gone, so a missing interface is not an error.
+pub fn detach(interface: &Interface) -> Result<()> {
+    let mut netlink = Netlink::new().context("failed to open netlink socket")?;
+    match netlink.delete_link(&interface.host) {
+        Ok(()) | Err(Errno::ENODEV) => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Configure the loopback and container interface in the network namespace of the
+/// calling process.
+pub fn configure(interface: &Interface) -> Result<()> {
+    let mut netlink = Netlink::new().context("failed to open netlink socket")?;
+    netlink.set_up("lo").context("failed to bring up lo")?;
+    netlink
+        .add_address(CONTAINER_INTERFACE, interface.address, interface.prefix)
+        .context(format!(
+            "failed to add address {} to {}",
+            interface.address, CONTAINER_INTERFACE
+        ))?;
+    netlink
+        .set_up(CONTAINER_INTERFACE)
+        .context(format!("failed to bring up {}", CONTAINER_INTERFACE))?;
+    netlink
+        .add_default_route(interface.gateway)
+        .context(format!(
+            "failed to add default route via {}",
+            interface.gateway
+        ))
+}
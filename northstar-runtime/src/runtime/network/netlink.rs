@@ -0,0 +1,291 @@
+//! Minimal rtnetlink client with the operations needed to connect containers to a bridge
+
+use nix::{
+    errno::Errno,
+    libc::AF_INET,
+    net::if_::if_nametoindex,
+    sys::socket::{
+        bind, recv, send, socket, AddressFamily, MsgFlags, NetlinkAddr, SockFlag, SockProtocol,
+        SockType,
+    },
+};
+use std::{
+    net::Ipv4Addr,
+    os::unix::prelude::{AsRawFd, FromRawFd, OwnedFd, RawFd},
+};
+
+// Message types
+const NLMSG_ERROR: u16 = 2;
+const RTM_NEWLINK: u16 = 16;
+const RTM_DELLINK: u16 = 17;
+const RTM_NEWADDR: u16 = 20;
+const RTM_NEWROUTE: u16 = 24;
+
+// Message flags
+const NLM_F_REQUEST: u16 = 0x1;
+const NLM_F_ACK: u16 = 0x4;
+const NLM_F_EXCL: u16 = 0x200;
+const NLM_F_CREATE: u16 = 0x400;
+
+// Link attributes
+const IFLA_IFNAME: u16 = 3;
+const IFLA_MASTER: u16 = 10;
+const IFLA_LINKINFO: u16 = 18;
+const IFLA_NET_NS_FD: u16 = 28;
+const IFLA_INFO_KIND: u16 = 1;
+const IFLA_INFO_DATA: u16 = 2;
+const VETH_INFO_PEER: u16 = 1;
+
+// Address attributes
+const IFA_ADDRESS: u16 = 1;
+const IFA_LOCAL: u16 = 2;
+const IFA_BROADCAST: u16 = 4;
+
+// Route attributes and values
+const RTA_GATEWAY: u16 = 5;
+const RT_TABLE_MAIN: u8 = 254;
+const RTPROT_BOOT: u8 = 3;
+const RT_SCOPE_UNIVERSE: u8 = 0;
+const RTN_UNICAST: u8 = 1;
+
+const IFF_UP: u32 = 0x1;
+
+/// Size of struct nlmsghdr
+const NLMSG_HDRLEN: usize = 16;
+
+/// Route netlink socket of the network namespace of the calling thread
+#[derive(Debug)]
+pub struct Netlink {
+    fd: OwnedFd,
+    seq: u32,
+}
+
+impl Netlink {
+    /// Open a route netlink socket
+    pub fn new() -> Result<Netlink, Errno> {
+        let fd = socket(
+            AddressFamily::Netlink,
+            SockType::Raw,
+            SockFlag::SOCK_CLOEXEC,
+            SockProtocol::NetlinkRoute,
+        )?;
+        let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+        bind(fd.as_raw_fd(), &NetlinkAddr::new(0, 0))?;
+        Ok(Netlink { fd, seq: 0 })
+    }
+
+    /// Create a bridge named `name`
+    pub fn create_bridge(&mut self, name: &str) -> Result<(), Errno> {
+        let mut message = Message::new(
+            RTM_NEWLINK,
+            NLM_F_REQUEST | NLM_F_ACK | NLM_F_CREATE | NLM_F_EXCL,
+        );
+        message.push(&ifinfomsg(0, 0, 0));
+        message.attribute(IFLA_IFNAME, &cstr(name));
+        message.nested(IFLA_LINKINFO, |message| {
+            message.attribute(IFLA_INFO_KIND, b"bridge");
+        });
+        self.request(message)
+    }
+
+    /// Create a veth pair `name` and `peer`. The `peer` end is created in the network
+    /// namespace referred to by `netns`.
+    pub fn create_veth(&mut self, name: &str, peer: &str, netns: RawFd) -> Result<(), Errno> {
+        let mut message = Message::new(
+            RTM_NEWLINK,
+            NLM_F_REQUEST | NLM_F_ACK | NLM_F_CREATE | NLM_F_EXCL,
+        );
+        message.push(&ifinfomsg(0, 0, 0));
+        message.attribute(IFLA_IFNAME, &cstr(name));
+        message.nested(IFLA_LINKINFO, |message| {
+            message.attribute(IFLA_INFO_KIND, b"veth");
+            message.nested(IFLA_INFO_DATA, |message| {
+                message.nested(VETH_INFO_PEER, |message| {
+                    message.push(&ifinfomsg(0, 0, 0));
+                    message.attribute(IFLA_IFNAME, &cstr(peer));
+                    message.attribute(IFLA_NET_NS_FD, &(netns as u32).to_ne_bytes());
+                });
+            });
+        });
+        self.request(message)
+    }
+
+    /// Delete the link `name`
+    pub fn delete_link(&mut self, name: &str) -> Result<(), Errno> {
+        let index = if_nametoindex(name)?;
+        let mut message = Message::new(RTM_DELLINK, NLM_F_REQUEST | NLM_F_ACK);
+        message.push(&ifinfomsg(index, 0, 0));
+        self.request(message)
+    }
+
+    /// Attach the link `name` to the bridge `master`
+    pub fn set_master(&mut self, name: &str, master: &str) -> Result<(), Errno> {
+        let index = if_nametoindex(name)?;
+        let master = if_nametoindex(master)?;
+        let mut message = Message::new(RTM_NEWLINK, NLM_F_REQUEST | NLM_F_ACK);
+        message.push(&ifinfomsg(index, 0, 0));
+        message.attribute(IFLA_MASTER, &master.to_ne_bytes());
+        self.request(message)
+    }
+
+    /// Bring the link `name` up
+    pub fn set_up(&mut self, name: &str) -> Result<(), Errno> {
+        let index = if_nametoindex(name)?;
+        let mut message = Message::new(RTM_NEWLINK, NLM_F_REQUEST | NLM_F_ACK);
+        message.push(&ifinfomsg(index, IFF_UP, IFF_UP));
+        self.request(message)
+    }
+
+    /// Add `address` with `prefix` to the link `name`
+    pub fn add_address(&mut self, name: &str, address: Ipv4Addr, prefix: u8) -> Result<(), Errno> {
+        let index = if_nametoindex(name)?;
+        let mask = u32::MAX.checked_shl(32 - prefix as u32).unwrap_or(0);
+        let broadcast = Ipv4Addr::from(u32::from(address) | !mask);
+
+        let mut message = Message::new(
+            RTM_NEWADDR,
+            NLM_F_REQUEST | NLM_F_ACK | NLM_F_CREATE | NLM_F_EXCL,
+        );
+        // struct ifaddrmsg
+        message.push(&[AF_INET as u8, prefix, 0, RT_SCOPE_UNIVERSE]);
+        message.push(&index.to_ne_bytes());
+        message.attribute(IFA_LOCAL, &address.octets());
+        message.attribute(IFA_ADDRESS, &address.octets());
+        message.attribute(IFA_BROADCAST, &broadcast.octets());
+        self.request(message)
+    }
+
+    /// Add a default route via `gateway`
+    pub fn add_default_route(&mut self, gateway: Ipv4Addr) -> Result<(), Errno> {
+        let mut message = Message::new(
+            RTM_NEWROUTE,
+            NLM_F_REQUEST | NLM_F_ACK | NLM_F_CREATE | NLM_F_EXCL,
+        );
+        // struct rtmsg
+        message.push(&[
+            AF_INET as u8,
+            0,
+            0,
+            0,
+            RT_TABLE_MAIN,
+            RTPROT_BOOT,
+            RT_SCOPE_UNIVERSE,
+            RTN_UNICAST,
+        ]);
+        message.push(&0u32.to_ne_bytes());
+        message.attribute(RTA_GATEWAY, &gateway.octets());
+        self.request(message)
+    }
+
+    /// Send `message` and wait for the acknowledgement
+    fn request(&mut self, message: Message) -> Result<(), Errno> {
+        self.seq = self.seq.wrapping_add(1);
+        let seq = self.seq;
+        let message = message.finish(seq);
+        send(self.fd.as_raw_fd(), &message, MsgFlags::empty())?;
+
+        let mut buffer = [0u8; 8192];
+        loop {
+            let len = recv(self.fd.as_raw_fd(), &mut buffer, MsgFlags::empty())?;
+            let mut messages = &buffer[..len];
+            while messages.len() >= NLMSG_HDRLEN {
+                let message_len = read_u32(messages, 0) as usize;
+                let message_type = read_u16(messages, 4);
+                let message_seq = read_u32(messages, 8);
+                if message_len < NLMSG_HDRLEN || message_len > messages.len() {
+                    return Err(Errno::EBADMSG);
+                }
+
+                if message_type == NLMSG_ERROR && message_seq == seq {
+                    if message_len < NLMSG_HDRLEN + 4 {
+                        return Err(Errno::EBADMSG);
+                    }
+                    let error = read_u32(messages, 16) as i32;
+                    return match error {
+                        0 => Ok(()),
+                        error => Err(Errno::from_i32(-error)),
+                    };
+                }
+
+                messages = &messages[align(message_len).min(messages.len())..];
+            }
+        }
+    }
+}
+
+/// Netlink message under construction
+struct Message {
+    buffer: Vec<u8>,
+}
+
+impl Message {
+    fn new(message_type: u16, flags: u16) -> Message {
+        let mut buffer = vec![0u8; NLMSG_HDRLEN];
+        buffer[4..6].copy_from_slice(&message_type.to_ne_bytes());
+        buffer[6..8].copy_from_slice(&flags.to_ne_bytes());
+        Message { buffer }
+    }
+
+    /// Append `data` padded to the netlink alignment
+    fn push(&mut self, data: &[u8]) {
+        self.buffer.extend_from_slice(data);
+        self.buffer.resize(align(self.buffer.len()), 0);
+    }
+
+    /// Append an attribute of `attribute_type` with `data`
+    fn attribute(&mut self, attribute_type: u16, data: &[u8]) {
+        let len = (4 + data.len()) as u16;
+        self.buffer.extend_from_slice(&len.to_ne_bytes());
+        self.buffer.extend_from_slice(&attribute_type.to_ne_bytes());
+        self.push(data);
+    }
+
+    /// Append an attribute of `attribute_type` with the content written by `f`
+    fn nested(&mut self, attribute_type: u16, f: impl FnOnce(&mut Message)) {
+        let start = self.buffer.len();
+        self.attribute(attribute_type, &[]);
+        f(self);
+        let len = (self.buffer.len() - start) as u16;
+        self.buffer[start..start + 2].copy_from_slice(&len.to_ne_bytes());
+    }
+
+    /// Set the length and sequence number and return the encoded message
+    fn finish(mut self, seq: u32) -> Vec<u8> {
+        let len = self.buffer.len() as u32;
+        self.buffer[0..4].copy_from_slice(&len.to_ne_bytes());
+        self.buffer[8..12].copy_from_slice(&seq.to_ne_bytes());
+        self.buffer
+    }
+}
+
+/// Encode a struct ifinfomsg
+fn ifinfomsg(index: u32, flags: u32, change: u32) -> [u8; 16] {
+    let mut buffer = [0u8; 16];
+    buffer[4..8].copy_from_slice(&index.to_ne_bytes());
+    buffer[8..12].copy_from_slice(&flags.to_ne_bytes());
+    buffer[12..16].copy_from_slice(&change.to_ne_bytes());
+    buffer
+}
+
+fn read_u16(buffer: &[u8], offset: usize) -> u16 {
+    u16::from_ne_bytes([buffer[offset], buffer[offset + 1]])
+}
+
+fn read_u32(buffer: &[u8], offset: usize) -> u32 {
+    u32::from_ne_bytes([
+        buffer[offset],
+        buffer[offset + 1],
+        buffer[offset + 2],
+        buffer[offset + 3],
+    ])
+}
+
+/// Nul terminated string attribute payload
+fn cstr(s: &str) -> Vec<u8> {
+    s.bytes().chain(std::iter::once(0)).collect()
+}
+
+/// Round `len` up to the netlink alignment of 4 bytes
+const fn align(len: usize) -> usize {
+    (len + 3) & !3
+}
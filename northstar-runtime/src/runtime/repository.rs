@@ -1,8 +1,15 @@
 use super::{
+    error::Error,
     key::{self, PublicKey},
-    Container,
+    Container, VERSION,
+};
+use crate::{
+    npk::{
+        manifest::{arch::Arch, Manifest},
+        npk::Npk as NpkNpk,
+    },
+    runtime::ipc::RawFdExt,
 };
-use crate::{npk::npk::Npk as NpkNpk, runtime::ipc::RawFdExt};
 use anyhow::{bail, Context, Result};
 use bytes::Bytes;
 use futures::{future::try_join_all, FutureExt};
@@ -45,6 +52,26 @@ pub(super) trait Repository: fmt::Debug {
     fn containers(&self) -> Vec<&Npk>;
 }
 
+/// Check that the container described by `manifest` is built for an architecture that runs
+/// on the runtime host and does not require a newer runtime version. The prerelease of the
+/// runtime version is ignored.
+pub(super) fn check_compatibility(manifest: &Manifest) -> Result<(), Error> {
+    if let (Some(arch), Some(host)) = (manifest.arch, Arch::host()) {
+        if !arch.runs_on(host) {
+            return Err(Error::ArchMismatch(manifest.container(), arch, host));
+        }
+    }
+
+    match &manifest.min_runtime_version {
+        Some(required) if required > &VERSION => Err(Error::RuntimeVersionMismatch(
+            manifest.container(),
+            required.clone(),
+            VERSION,
+        )),
+        _ => Ok(()),
+    }
+}
+
 /// Repository backed by a directory
 #[derive(Debug)]
 pub(super) struct DirRepository {
@@ -148,6 +175,15 @@ impl Repository for DirRepository {
         let container = npk.manifest().container();
         info!("Loaded {} from {}", container, dest.display());
 
+        // Check if the container can run on this runtime
+        if let Err(e) = check_compatibility(npk.manifest()) {
+            warn!("Rejecting {}: {}", container, e);
+            fs::remove_file(&dest)
+                .await
+                .with_context(|| format!("failed to remove {}", dest.display()))?;
+            return Err(e.into());
+        }
+
         // Check of the container is present
         if self.containers.contains_key(&container) {
             warn!("Container {} is already present in repository", container);
@@ -260,6 +296,12 @@ impl Repository for MemRepository {
         let container = npk.manifest().container();
         info!("Loaded {} from memfd", container);
 
+        // Check if the container can run on this runtime
+        if let Err(e) = check_compatibility(npk.manifest()) {
+            warn!("Rejecting {}: {}", container, e);
+            return Err(e.into());
+        }
+
         if self.containers.contains_key(&container) {
             warn!(
                 "Container {} is already present in repository. Dropping...",
@@ -290,3 +332,42 @@ impl Repository for MemRepository {
         self.key.as_ref()
     }
 }
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod test {
+    use super::*;
+    use crate::common::version::Version;
+    use std::str::FromStr;
+
+    fn manifest(min_runtime_version: &Version) -> Manifest {
+        Manifest::from_str(&format!(
+            "name: hello\nversion: 0.0.1\ninit: /hello\nuid: 1000\ngid: 1000\nmin_runtime_version: {}",
+            min_runtime_version
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn min_runtime_version() {
+        // The prerelease of the runtime version is ignored
+        assert!(check_compatibility(&manifest(&VERSION)).is_ok());
+        let older = Version::new(VERSION.major, VERSION.minor, 0);
+        assert!(check_compatibility(&manifest(&older)).is_ok());
+        let newer = Version::new(VERSION.major, VERSION.minor, VERSION.patch + 1);
+        assert!(matches!(
+            check_compatibility(&manifest(&newer)),
+            Err(Error::RuntimeVersionMismatch(..))
+        ));
+    }
+
+    #[test]
+    fn arch_compatibility() {
+        assert!(Arch::X86_64.runs_on(Arch::X86_64));
+        assert!(Arch::X86.runs_on(Arch::X86_64));
+        assert!(Arch::Arm.runs_on(Arch::Aarch64));
+        assert!(!Arch::X86_64.runs_on(Arch::X86));
+        assert!(!Arch::Aarch64.runs_on(Arch::Arm));
+        assert!(!Arch::Aarch64.runs_on(Arch::X86_64));
+    }
+}
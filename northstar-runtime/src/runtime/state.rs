@@ -3,18 +3,21 @@ use super::{
     config::{Config, RepositoryType},
     console::Request,
     error::Error,
-    fork::Forker,
+    fork::{Forker, UserNamespace},
     io,
     mount::MountControl,
-    repository::{DirRepository, MemRepository, Npk},
+    network::{self, Interface},
+    repository::{self, DirRepository, MemRepository, Npk},
+    volume::{self, Volume},
     Container, ContainerEvent, Event, EventTx, ExitStatus, NotificationTx, Pid, RepositoryId,
 };
 use crate::{
     api::{self, model},
-    common::{name::Name, non_nul_string::NonNulString, version::VersionReq},
+    common::{name::Name, non_nul_string::NonNulString, selector::Selector, version::VersionReq},
     npk::manifest::{
         autostart::Autostart,
-        mount::{Mount, Resource},
+        mount::{self, Mount, Persist, Resource},
+        network::Network,
         Manifest,
     },
     runtime::{
@@ -22,8 +25,9 @@ use crate::{
         io::ContainerIo,
         CGroupEvent, ENV_CONSOLE, ENV_CONTAINER, ENV_NAME, ENV_VERSION,
     },
+    seccomp,
 };
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use bytes::Bytes;
 use futures::{
     future::{join_all, ready, Either},
@@ -32,13 +36,17 @@ use futures::{
 use humantime::format_duration;
 use itertools::Itertools;
 use log::{debug, error, info, warn};
-use nix::sys::signal::Signal;
+use nix::{sys::signal::Signal, unistd};
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeSet, HashMap, HashSet},
     convert::TryFrom,
     fmt::Debug,
+    fs::Permissions,
     iter::{once, FromIterator},
-    os::unix::{net::UnixStream as StdUnixStream, prelude::OwnedFd},
+    os::unix::{
+        net::UnixStream as StdUnixStream,
+        prelude::{MetadataExt, OwnedFd, PermissionsExt},
+    },
     path::PathBuf,
     sync::Arc,
 };
@@ -64,6 +72,12 @@ pub(super) struct State {
     forker: Forker,
     containers: HashMap<Container, ContainerState>,
     repositories: HashMap<RepositoryId, Repository>,
+    /// User namespace id range slots assigned to container names
+    user_namespaces: HashMap<Name, u32>,
+    /// Bridge for containers with bridge networking
+    bridge: Option<network::Bridge>,
+    /// Mounted images of named volumes with a size
+    volumes: HashMap<Name, Volume>,
 }
 
 #[derive(Debug, Default)]
@@ -74,6 +88,8 @@ pub(super) struct ContainerState {
     pub root: Option<PathBuf>,
     /// Process information when started
     pub process: Option<ContainerContext>,
+    /// System calls recorded in seccomp learn mode
+    pub seccomp: Option<BTreeSet<u32>>,
 }
 
 impl ContainerState {
@@ -88,11 +104,21 @@ pub(super) struct ContainerContext {
     started: time::Instant,
     debug: super::debug::Debug,
     cgroups: cgroups::CGroups,
+    /// Interface of the container if attached to the bridge
+    interface: Option<Interface>,
+    /// Size limited persist mount
+    volume: Option<Volume>,
     stop: CancellationToken,
     /// Resources used by this container. This list differs from
     /// manifest because the manifest just containers version
     /// requirements and not concrete resources.
     resources: HashSet<Container>,
+    /// Named volumes used by this container
+    volumes: HashSet<Name>,
+    /// Pseudo terminal of the container
+    tty: Option<io::Tty>,
+    /// The container exceeded its deadline and is stopped
+    deadline_exceeded: bool,
 }
 
 impl ContainerContext {
@@ -106,6 +132,10 @@ impl ContainerContext {
             .expect("failed to destroy debug utilities");
 
         self.cgroups.destroy().await;
+
+        if let Some(volume) = self.volume {
+            volume.destroy().await;
+        }
     }
 }
 
@@ -128,6 +158,14 @@ impl State {
             .expect("failed to initialize mount control"),
         );
 
+        // Setup the bridge for containers with bridge networking
+        let bridge = config
+            .bridge
+            .as_ref()
+            .map(network::Bridge::new)
+            .transpose()
+            .context("failed to initialize bridge")?;
+
         let mut state = State {
             events_tx,
             notification_tx,
@@ -136,6 +174,9 @@ impl State {
             config,
             forker,
             mount_control,
+            user_namespaces: HashMap::new(),
+            bridge,
+            volumes: HashMap::new(),
         };
 
         // Initialize repositories. This populates self.containers and self.repositories
@@ -271,7 +312,7 @@ impl State {
         for (container, autostart) in autostarts {
             info!("Autostarting {} ({:?})", container, autostart);
             if let Err(e) = self
-                .start(&container, &[], &HashMap::with_capacity(0))
+                .start(&container, &[], &HashMap::with_capacity(0), None)
                 .await
             {
                 Self::warn_autostart_failure(&container, &autostart, e)?
@@ -348,11 +389,13 @@ impl State {
     /// `container`: Container to start
     /// `args_extra`: Optional command line arguments that overwrite the values from the manifest
     /// `env_extra`: Optional env variables that overwrite the values from the manifest
+    /// `deadline`: Optional maximum runtime that overwrites the value from the manifest
     pub(super) async fn start(
         &mut self,
         container: &Container,
         args_extra: &[NonNulString],
         env_extra: &HashMap<NonNulString, NonNulString>,
+        deadline: Option<time::Duration>,
     ) -> Result<(), Error> {
         let start = time::Instant::now();
         info!("Trying to start {}", container);
@@ -396,6 +439,9 @@ impl State {
             Error::StartContainerResource(container.clone())
         })?;
 
+        // Check if the container is built for this architecture and runtime version
+        repository::check_compatibility(&manifest)?;
+
         // Containers that need to be mounted before container can be started
         let mut need_mount = HashSet::new();
         // Resources use by this container
@@ -436,6 +482,41 @@ impl State {
             }
         }
 
+        // Check that the container is allowed to mount the named volumes it uses
+        let volumes = manifest
+            .mounts
+            .values()
+            .filter_map(|m| match m {
+                Mount::Volume(mount::Volume { name, .. }) => Some(name.clone()),
+                _ => None,
+            })
+            .collect::<HashSet<_>>();
+        for name in &volumes {
+            match self.config.volumes.get(name) {
+                Some(volume) if volume.containers.contains(container.name()) => (),
+                Some(_) => {
+                    warn!(
+                        "Container {} is not allowed to mount volume {}",
+                        container, name
+                    );
+                    return Err(Error::StartContainerFailed(
+                        container.clone(),
+                        format!("volume {} is not allowed", name),
+                    ));
+                }
+                None => {
+                    warn!(
+                        "Volume {} of container {} is not configured",
+                        name, container
+                    );
+                    return Err(Error::StartContainerFailed(
+                        container.clone(),
+                        format!("volume {} is not configured", name),
+                    ));
+                }
+            }
+        }
+
         // Mount containers
         if !need_mount.is_empty() {
             info!(
@@ -452,6 +533,10 @@ impl State {
             }
         }
 
+        // User namespace id ranges. Resolved before any console or io setup that would
+        // need to be torn down if there is no free range.
+        let user_namespace = self.user_namespace(&manifest).await?;
+
         // Spawn process
         info!("Creating {}", container);
 
@@ -495,32 +580,167 @@ impl State {
         };
 
         // Open a file handle for stdin, stdout and stderr according to the manifest
-        let ContainerIo { io } = io::open(container, &manifest.io.clone().unwrap_or_default())
+        let ContainerIo {
+            io,
+            tty,
+            pid: pid_tx,
+        } = io::open(
+            container,
+            &manifest.io.clone().unwrap_or_default(),
+            &self.config.syslog,
+        )
+        .await
+        .expect("IO setup error");
+
+        // CGroups. The processes of the container are moved into the cgroups before
+        // init is forked.
+        let cgroups = {
+            let config = manifest.cgroups.clone().unwrap_or_default();
+            let events_tx = self.events_tx.clone();
+            let devices = !manifest.devices.is_empty();
+
+            // Creating a cgroup is a northstar internal thing. If it fails it's not recoverable.
+            cgroups::CGroups::new(&self.config.cgroup, events_tx, container, &config, devices)
+                .await
+                .expect("failed to create cgroup")
+        };
+
+        // Delegate the cgroup to the (mapped) container uid and gid
+        if manifest.cgroups.as_ref().map(|c| c.delegate) == Some(true) {
+            let (uid, gid) = match &user_namespace {
+                Some(user_namespace) => (
+                    user_namespace.uid + manifest.uid as u32,
+                    user_namespace.gid + manifest.gid as u32,
+                ),
+                None => (manifest.uid.into(), manifest.gid.into()),
+            };
+            if let Err(e) = cgroups.delegate(uid, gid) {
+                warn!("Failed to delegate cgroup of {}: {}", container, e);
+                stop.cancel();
+                cgroups.destroy().await;
+                return Err(Error::StartContainerFailed(
+                    container.clone(),
+                    e.to_string(),
+                ));
+            }
+        }
+
+        // Restrict the device access to the nodes of the container
+        if !manifest.devices.is_empty() {
+            if let Err(e) = cgroups.devices(&manifest.devices) {
+                warn!(
+                    "Failed to configure device access of {}: {:#}",
+                    container, e
+                );
+                stop.cancel();
+                cgroups.destroy().await;
+                return Err(Error::StartContainerFailed(
+                    container.clone(),
+                    format!("{:#}", e),
+                ));
+            }
+        }
+
+        // Network interface of containers attached to the bridge
+        let interface = match self.interface(&manifest) {
+            Ok(interface) => interface,
+            Err(e) => {
+                stop.cancel();
+                cgroups.destroy().await;
+                return Err(e);
+            }
+        };
+
+        // Mount the image of a size limited persist mount
+        let persist_size = manifest.mounts.values().find_map(|mount| match mount {
+            Mount::Persist(Persist { size }) => *size,
+            _ => None,
+        });
+        let volume = match persist_size {
+            Some(size) => {
+                let events_tx = self.events_tx.clone();
+                match Volume::new(
+                    &self.config,
+                    &self.mount_control,
+                    container,
+                    size,
+                    events_tx,
+                )
+                .await
+                {
+                    Ok(volume) => Some(volume),
+                    Err(e) => {
+                        warn!("Failed to mount persist image of {}: {:#}", container, e);
+                        stop.cancel();
+                        cgroups.destroy().await;
+                        self.release_interface(interface);
+                        return Err(Error::StartContainerFailed(
+                            container.clone(),
+                            format!("{:#}", e),
+                        ));
+                    }
+                }
+            }
+            None => None,
+        };
+
+        // Prepare the backing directories of the named volumes
+        if let Err(e) = self
+            .prepare_volumes(&volumes, user_namespace.as_ref())
             .await
-            .expect("IO setup error");
+        {
+            warn!("Failed to prepare volumes of {}: {:#}", container, e);
+            stop.cancel();
+            cgroups.destroy().await;
+            if let Some(volume) = volume {
+                volume.destroy().await;
+            }
+            self.release_interface(interface);
+            self.release_volumes().await;
+            return Err(Error::StartContainerFailed(
+                container.clone(),
+                format!("{:#}", e),
+            ));
+        }
 
         // Create container
         let config = &self.config;
         let containers = self.containers.iter().map(|(c, _)| c);
-        let pid = self
+        let pid = match self
             .forker
-            .create(container, config, &manifest, io, console_fd, containers)
-            .await?;
+            .create(
+                container,
+                config,
+                &manifest,
+                io,
+                console_fd,
+                containers,
+                user_namespace,
+                cgroups.procs(),
+                interface.clone(),
+            )
+            .await
+        {
+            Ok(pid) => pid,
+            Err(e) => {
+                stop.cancel();
+                cgroups.destroy().await;
+                if let Some(volume) = volume {
+                    volume.destroy().await;
+                }
+                self.release_interface(interface);
+                self.release_volumes().await;
+                self.remove_run_dirs(container).await;
+                return Err(e);
+            }
+        };
+
+        // Report the pid in syslog entries of the container output
+        pid_tx.send(Some(pid)).ok();
 
         // Debug
         let debug = super::debug::Debug::new(&self.config, &manifest, pid).await?;
 
-        // CGroups
-        let cgroups = {
-            let config = manifest.cgroups.clone().unwrap_or_default();
-            let events_tx = self.events_tx.clone();
-
-            // Creating a cgroup is a northstar internal thing. If it fails it's not recoverable.
-            cgroups::CGroups::new(&self.config.cgroup, events_tx, container, &config, pid)
-                .await
-                .expect("failed to create cgroup")
-        };
-
         // Binary arguments
         let mut args = Vec::with_capacity(
             1 + if args_extra.is_empty() {
@@ -573,9 +793,28 @@ impl State {
 
             debug.destroy().await.expect("failed to destroy debug");
             cgroups.destroy().await;
+            if let Some(volume) = volume {
+                volume.destroy().await;
+            }
+            self.release_interface(interface);
+            self.release_volumes().await;
+            self.remove_run_dirs(container).await;
             return Err(e);
         }
 
+        // Stop the container when it exceeds its deadline
+        if let Some(deadline) = deadline.or(manifest.max_runtime) {
+            debug!("Stopping {} after {}", container, format_duration(deadline));
+            task::spawn(stop_after_deadline(
+                container.clone(),
+                pid,
+                deadline,
+                self.config.stop_timeout,
+                self.events_tx.clone(),
+                stop.clone(),
+            ));
+        }
+
         // Get a mutable reference to the container state in order to update the process field
         let container_state = self.containers.get_mut(container).expect("Internal error");
 
@@ -586,8 +825,13 @@ impl State {
             started,
             debug,
             cgroups,
+            interface,
+            volume,
             stop,
             resources,
+            volumes,
+            tty,
+            deadline_exceeded: false,
         });
 
         let duration = start.elapsed().as_secs_f32();
@@ -599,6 +843,205 @@ impl State {
         Ok(())
     }
 
+    /// Create the backing directories of the named volumes in `names` and mount the
+    /// images of volumes with a size that are not mounted yet. The owner and mode of a
+    /// backing directory are set when the directory or image is created. The ids are
+    /// mapped into `user_namespace` if the container has one.
+    async fn prepare_volumes(
+        &mut self,
+        names: &HashSet<Name>,
+        user_namespace: Option<&UserNamespace>,
+    ) -> Result<()> {
+        for name in names {
+            let volume = &self.config.volumes[name];
+            let dir = &volume.dir;
+
+            let created = match volume.size {
+                Some(size) if !self.volumes.contains_key(name) => {
+                    let created = !volume::shared_image(&self.config, name.as_ref()).exists();
+                    debug!("Mounting volume {} on {}", name, dir.display());
+                    let mounted =
+                        Volume::shared(&self.config, &self.mount_control, name.as_ref(), dir, size)
+                            .await?;
+                    self.volumes.insert(name.clone(), mounted);
+                    created
+                }
+                Some(_) => false,
+                None if !dir.exists() => {
+                    debug!("Creating {}", dir.display());
+                    fs::create_dir_all(dir)
+                        .await
+                        .with_context(|| format!("failed to create {}", dir.display()))?;
+                    true
+                }
+                None => false,
+            };
+
+            if created {
+                let (uid, gid) = match user_namespace {
+                    Some(user_namespace) => {
+                        if volume.uid >= user_namespace.size || volume.gid >= user_namespace.size {
+                            bail!(
+                                "uid {} or gid {} of volume {} exceeds the user namespace size {}",
+                                volume.uid,
+                                volume.gid,
+                                name,
+                                user_namespace.size
+                            );
+                        }
+                        (
+                            user_namespace.uid + volume.uid,
+                            user_namespace.gid + volume.gid,
+                        )
+                    }
+                    None => (volume.uid, volume.gid),
+                };
+                debug!("Chowning {} to {}:{}", dir.display(), uid, gid);
+                let uid = Some(unistd::Uid::from_raw(uid));
+                let gid = Some(unistd::Gid::from_raw(gid));
+                unistd::chown(dir, uid, gid)
+                    .with_context(|| format!("failed to chown {}", dir.display()))?;
+                fs::set_permissions(dir, Permissions::from_mode(0o770))
+                    .await
+                    .with_context(|| format!("failed to set permissions of {}", dir.display()))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Umount the images of named volumes that are not used by any running container
+    async fn release_volumes(&mut self) {
+        let used = self
+            .containers
+            .values()
+            .filter_map(|state| state.process.as_ref())
+            .flat_map(|process| process.volumes.iter())
+            .collect::<HashSet<_>>();
+        let unused = self
+            .volumes
+            .keys()
+            .filter(|name| !used.contains(name))
+            .cloned()
+            .collect::<Vec<_>>();
+        for name in unused {
+            debug!("Volume {} is not used anymore", name);
+            if let Some(volume) = self.volumes.remove(&name) {
+                volume.destroy().await;
+            }
+        }
+    }
+
+    /// Remove the directories in the run dir that are created for `container` when it
+    /// is started
+    async fn remove_run_dirs(&self, container: &Container) {
+        for suffix in ["etc", "overlay"] {
+            let dir = self
+                .config
+                .run_dir
+                .join(format!("{}:{}", container, suffix));
+            if !dir.exists() {
+                continue;
+            }
+            debug!("Removing {}", dir.display());
+            if let Err(e) = fs::remove_dir_all(&dir).await {
+                warn!("Failed to remove {}: {}", dir.display(), e);
+            }
+        }
+    }
+
+    /// Allocate an interface on the bridge if the container in `manifest` uses bridge networking
+    fn interface(&mut self, manifest: &Manifest) -> Result<Option<Interface>, Error> {
+        if manifest.network != Some(Network::Bridge) {
+            return Ok(None);
+        }
+        let container = manifest.container();
+        let bridge = self.bridge.as_mut().ok_or_else(|| {
+            Error::StartContainerFailed(container.clone(), "no bridge configured".into())
+        })?;
+        bridge
+            .allocate()
+            .map(Some)
+            .map_err(|e| Error::StartContainerFailed(container, e.to_string()))
+    }
+
+    /// Remove the veth pair of `interface` and release its address
+    fn release_interface(&mut self, interface: Option<Interface>) {
+        if let (Some(bridge), Some(interface)) = (self.bridge.as_mut(), interface) {
+            bridge.release(&interface);
+        }
+    }
+
+    /// Assign a slot of the configured id ranges to the user namespace of the container
+    /// in `manifest`. A container keeps its slot until the slot is needed for another
+    /// container. Without an assigned slot the slot that owns the persist directory of
+    /// the container is preferred in order to avoid shifting the ownership of its content.
+    async fn user_namespace(
+        &mut self,
+        manifest: &Manifest,
+    ) -> Result<Option<UserNamespace>, Error> {
+        if !manifest.user_namespace {
+            return Ok(None);
+        }
+
+        let container = manifest.container();
+        let failed = |e: String| Error::StartContainerFailed(container.clone(), e);
+        let config = self
+            .config
+            .user_namespace
+            .as_ref()
+            .ok_or_else(|| failed("user namespaces are not configured".into()))?;
+
+        if manifest.uid as u32 >= config.size || manifest.gid as u32 >= config.size {
+            return Err(failed(format!(
+                "uid {} or gid {} exceeds the user namespace size {}",
+                manifest.uid, manifest.gid, config.size
+            )));
+        }
+
+        // Slots of other running containers
+        let used = self
+            .containers
+            .iter()
+            .filter(|(c, state)| state.process.is_some() && c.name() != &manifest.name)
+            .filter_map(|(c, _)| self.user_namespaces.get(c.name()).copied())
+            .collect::<HashSet<_>>();
+
+        // Slot owning the persist directory
+        let persist = fs::metadata(self.config.data_dir.join(manifest.name.to_string()))
+            .await
+            .ok()
+            .and_then(|metadata| metadata.uid().checked_sub(config.uid + manifest.uid as u32))
+            .filter(|offset| offset % config.size == 0)
+            .map(|offset| offset / config.size);
+
+        let slot = self
+            .user_namespaces
+            .get(&manifest.name)
+            .cloned()
+            .into_iter()
+            .chain(persist)
+            .chain(0..config.slots())
+            .find(|slot| *slot < config.slots() && !used.contains(slot))
+            .ok_or_else(|| failed("no free user namespace id range".into()))?;
+
+        // Take the slot away from a stopped container
+        self.user_namespaces.retain(|_, s| *s != slot);
+        self.user_namespaces.insert(manifest.name.clone(), slot);
+
+        let user_namespace = UserNamespace {
+            uid: config.uid + slot * config.size,
+            gid: config.gid + slot * config.size,
+            size: config.size,
+        };
+        debug!(
+            "Using user namespace slot {} of {} for {}",
+            slot,
+            config.slots(),
+            container
+        );
+        Ok(Some(user_namespace))
+    }
+
     /// Send signal `signal` to container if running
     pub(super) async fn kill(
         &mut self,
@@ -610,6 +1053,8 @@ impl State {
         match &mut container_state.process {
             Some(context) => {
                 info!("Killing {} with {}", container, signal.as_str());
+                // The pid of init is the pid in the namespace of the runtime. Init is
+                // the session and process group leader regardless of a pid namespace.
                 let pid = context.pid;
                 let process_group = nix::unistd::Pid::from_raw(-(pid as i32));
                 match nix::sys::signal::kill(process_group, Some(signal)) {
@@ -648,8 +1093,12 @@ impl State {
             .values()
             .any(|state| state.process.is_some())
         {
-            if let Some(Event::Container(container, event)) = event_rx.next().await {
-                self.on_event(&container, &event, true).await?;
+            match event_rx.next().await {
+                Some(Event::Container(container, event)) => {
+                    self.on_event(&container, &event, true).await?
+                }
+                Some(Event::Seccomp(container, syscalls)) => self.on_seccomp(&container, syscalls),
+                _ => (),
             }
         }
 
@@ -662,6 +1111,13 @@ impl State {
             .collect::<Vec<_>>();
         self.umount_all(&to_umount).await;
 
+        // Remove the bridge if it was created by the runtime
+        if let Some(bridge) = self.bridge.take() {
+            if let Err(e) = bridge.shutdown() {
+                warn!("{:#}", e);
+            }
+        }
+
         Ok(())
     }
 
@@ -677,8 +1133,11 @@ impl State {
             .get_mut(id)
             .ok_or_else(|| Error::InvalidRepository(id.to_string()))?;
 
-        // Add the npk to the repository
-        let container = repository.insert(rx).await?;
+        // Add the npk to the repository. Compatibility errors are passed through.
+        let container = repository
+            .insert(rx)
+            .await
+            .map_err(|e| e.downcast::<Error>().unwrap_or_else(Error::Unexpected))?;
 
         // Check if container is already known and remove newly installed one if so
         let already_installed = self
@@ -738,24 +1197,28 @@ impl State {
             .remove(container)
             .await?;
 
-        // Wipe persistent dir if present
+        // Wipe persistent dir, image and root overlay if present
         if wipe {
             let name: &str = container.name().as_ref();
-            let dir = self.config.data_dir.join(name);
-            if dir.exists() {
-                info!(
-                    "Wiping persistent data dir {} of {}",
-                    dir.display(),
-                    container
-                );
-                if let Err(e) = fs::remove_dir_all(&dir)
-                    .await
-                    .with_context(|| format!("failed to remove {}", dir.display()))
+            let paths = [
+                self.config.data_dir.join(name),
+                volume::image(&self.config, name),
+                self.config.data_dir.join(format!("{}:overlay", name)),
+            ];
+            for path in paths.iter().filter(|path| path.exists()) {
+                info!("Wiping persistent data {} of {}", path.display(), container);
+                let result = if path.is_dir() {
+                    fs::remove_dir_all(path).await
+                } else {
+                    fs::remove_file(path).await
+                };
+                if let Err(e) =
+                    result.with_context(|| format!("failed to remove {}", path.display()))
                 {
                     // If the runtime fails to remove the data dir leave it behind.
                     // This cannot be handled.
                     // In theory this should never happen with the cap_dac capability.
-                    warn!("Failed to remove {}: {}", dir.display(), e);
+                    warn!("Failed to remove {}: {}", path.display(), e);
                 }
             }
         }
@@ -781,9 +1244,10 @@ impl State {
             .and_then(|manfiest| manfiest.autostart.clone());
 
         if let Ok(state) = self.state_mut(container) {
-            if let Some(process) = state.process.take() {
+            if let Some(mut process) = state.process.take() {
+                // Containers stopped for exceeding their deadline exit as expected
                 let is_critical = autostart == Some(Autostart::Critical);
-                let is_critical = is_critical && !is_shutdown;
+                let is_critical = is_critical && !is_shutdown && !process.deadline_exceeded;
                 let duration = process.started.elapsed();
                 if is_critical {
                     error!(
@@ -801,9 +1265,19 @@ impl State {
                     );
                 }
 
+                let deadline_exceeded = process.deadline_exceeded;
+                let interface = process.interface.take();
                 process.destroy().await;
+                self.release_interface(interface);
+                self.release_volumes().await;
+                self.remove_run_dirs(container).await;
 
-                self.container_event(container, ContainerEvent::Exit(exit_status.clone()));
+                let event = if deadline_exceeded {
+                    ContainerEvent::DeadlineExit(exit_status.clone())
+                } else {
+                    ContainerEvent::Exit(exit_status.clone())
+                };
+                self.container_event(container, event);
 
                 info!("Container {} exited with status {}", container, exit_status);
 
@@ -831,16 +1305,56 @@ impl State {
             ContainerEvent::Exit(exit_status) => {
                 self.on_exit(container, exit_status, is_shutdown).await?;
             }
+            ContainerEvent::DeadlineExit(_) => (),
             ContainerEvent::Installed => (),
             ContainerEvent::Uninstalled => (),
             ContainerEvent::CGroup(CGroupEvent::Memory(_)) => {
                 warn!("Process {} is out of memory", container);
             }
+            ContainerEvent::Persist(_) => self.container_event(container, event.clone()),
         }
 
         Ok(())
     }
 
+    /// Stop `container` with `signal` if it is still the process `pid` that exceeded
+    /// its deadline
+    pub(super) async fn on_deadline(
+        &mut self,
+        container: &Container,
+        pid: Pid,
+        signal: Signal,
+    ) -> Result<(), Error> {
+        match self
+            .state_mut(container)
+            .map(|state| state.process.as_mut())
+        {
+            Ok(Some(process)) if process.pid == pid => {
+                if !process.deadline_exceeded {
+                    warn!("Container {} exceeded its deadline", container);
+                    process.deadline_exceeded = true;
+                }
+            }
+            _ => return Ok(()),
+        }
+        self.kill(container, signal).await
+    }
+
+    /// Record the system calls of `container` reported in seccomp learn mode
+    pub(super) fn on_seccomp(&mut self, container: &Container, syscalls: Vec<u32>) {
+        if let Ok(state) = self.state_mut(container) {
+            debug!(
+                "Recorded {} syscalls of {} in seccomp learn mode",
+                syscalls.len(),
+                container
+            );
+            state
+                .seccomp
+                .get_or_insert_with(BTreeSet::new)
+                .extend(syscalls);
+        }
+    }
+
     /// Process console events
     pub(super) async fn on_request(
         &mut self,
@@ -850,14 +1364,23 @@ impl State {
         match request {
             Request::Request(ref request) => {
                 let payload = match request {
-                    model::Request::List => model::Response::List(self.list_containers()),
+                    model::Request::List { selector } => {
+                        model::Response::List(self.list_containers(selector.as_ref()))
+                    }
                     model::Request::Install { .. } => unreachable!(),
-                    model::Request::Mount { containers } => {
+                    model::Request::Mount {
+                        containers,
+                        selector,
+                    } => {
+                        // Containers selected by labels that are already mounted are skipped
+                        let containers = self.select(containers, selector.as_ref(), |state, _| {
+                            !state.is_mounted()
+                        });
                         let result = self
-                            .mount_all(containers)
+                            .mount_all(&containers)
                             .await
                             .drain(..)
-                            .zip(containers)
+                            .zip(&containers)
                             .map(|(r, c)| match r {
                                 Ok(r) => model::MountResult::Ok { container: r },
                                 Err(e) => model::MountResult::Error {
@@ -868,12 +1391,18 @@ impl State {
                             .collect();
                         model::Response::Mount(result)
                     }
-                    model::Request::Umount { containers } => {
+                    model::Request::Umount {
+                        containers,
+                        selector,
+                    } => {
+                        // Containers selected by labels that are not mounted are skipped
+                        let containers = self
+                            .select(containers, selector.as_ref(), |state, _| state.is_mounted());
                         let result = self
-                            .umount_all(containers)
+                            .umount_all(&containers)
                             .await
                             .drain(..)
-                            .zip(containers)
+                            .zip(&containers)
                             .map(|(r, c)| match r {
                                 Ok(r) => model::UmountResult::Ok { container: r },
                                 Err(e) => model::UmountResult::Error {
@@ -896,50 +1425,74 @@ impl State {
                         model::Response::Shutdown
                     }
                     model::Request::Start {
-                        container,
+                        containers,
+                        selector,
                         arguments,
                         environment,
+                        deadline,
                     } => {
-                        let result = match self.start(container, arguments, environment).await {
-                            Ok(_) => model::StartResult::Ok {
-                                container: container.clone(),
-                            },
-                            Err(e) => {
-                                warn!("failed to start {}: {}", container, e);
-                                model::StartResult::Error {
-                                    container: container.clone(),
-                                    error: e.into(),
+                        let deadline = deadline.map(time::Duration::from_millis);
+                        // Containers selected by labels that are started or resource containers
+                        // are skipped
+                        let containers =
+                            self.select(containers, selector.as_ref(), |state, manifest| {
+                                state.process.is_none() && manifest.init.is_some()
+                            });
+                        let mut results = Vec::with_capacity(containers.len());
+                        for container in containers {
+                            let result = match self
+                                .start(&container, arguments, environment, deadline)
+                                .await
+                            {
+                                Ok(_) => model::StartResult::Ok { container },
+                                Err(e) => {
+                                    warn!("failed to start {}: {}", container, e);
+                                    model::StartResult::Error {
+                                        container,
+                                        error: e.into(),
+                                    }
                                 }
-                            }
-                        };
-                        model::Response::Start(result)
+                            };
+                            results.push(result);
+                        }
+                        model::Response::Start(results)
                     }
-                    model::Request::Kill { container, signal } => {
-                        let result = match Signal::try_from(*signal) {
-                            Ok(signal) => match self.kill(container, signal).await {
-                                Ok(_) => model::KillResult::Ok {
-                                    container: container.clone(),
+                    model::Request::Kill {
+                        containers,
+                        selector,
+                        signal,
+                    } => {
+                        // Containers selected by labels that are not started are skipped
+                        let containers = self.select(containers, selector.as_ref(), |state, _| {
+                            state.process.is_some()
+                        });
+                        let mut results = Vec::with_capacity(containers.len());
+                        for container in containers {
+                            let result = match Signal::try_from(*signal) {
+                                Ok(signal) => match self.kill(&container, signal).await {
+                                    Ok(_) => model::KillResult::Ok { container },
+                                    Err(e) => {
+                                        error!(
+                                            "failed to kill {} with {}: {}",
+                                            container, signal, e
+                                        );
+                                        model::KillResult::Error {
+                                            container,
+                                            error: e.into(),
+                                        }
+                                    }
                                 },
                                 Err(e) => {
                                     error!("failed to kill {} with {}: {}", container, signal, e);
-                                    model::KillResult::Error {
-                                        container: container.clone(),
-                                        error: e.into(),
-                                    }
+                                    let error = model::Error::Unexpected {
+                                        error: e.to_string(),
+                                    };
+                                    model::KillResult::Error { container, error }
                                 }
-                            },
-                            Err(e) => {
-                                error!("failed to kill {} with {}: {}", container, signal, e);
-                                let error = model::Error::Unexpected {
-                                    error: e.to_string(),
-                                };
-                                model::KillResult::Error {
-                                    container: container.clone(),
-                                    error,
-                                }
-                            }
-                        };
-                        model::Response::Kill(result)
+                            };
+                            results.push(result);
+                        }
+                        model::Response::Kill(results)
                     }
                     model::Request::Uninstall { container, wipe } => {
                         let result = match self.uninstall(container, *wipe).await {
@@ -966,7 +1519,31 @@ impl State {
                             error: e.into(),
                         }),
                     },
-                    model::Request::Ident => unreachable!(), // handled in module console
+                    model::Request::Resize {
+                        container,
+                        rows,
+                        cols,
+                    } => match self.resize(container, *rows, *cols) {
+                        Ok(_) => model::Response::Resize(model::ResizeResult::Ok {
+                            container: container.clone(),
+                        }),
+                        Err(e) => model::Response::Resize(model::ResizeResult::Error {
+                            container: container.clone(),
+                            error: e.into(),
+                        }),
+                    },
+                    model::Request::Seccomp { container } => match self.seccomp(container) {
+                        Ok(seccomp) => model::Response::Seccomp(model::SeccompResult::Ok {
+                            container: container.clone(),
+                            seccomp,
+                        }),
+                        Err(e) => model::Response::Seccomp(model::SeccompResult::Error {
+                            container: container.clone(),
+                            error: e.into(),
+                        }),
+                    },
+                    model::Request::Attach { .. } => unreachable!(), // handled in module console
+                    model::Request::Ident => unreachable!(),         // handled in module console
                     model::Request::TokenCreate { .. } => unreachable!(), // handled in module console
                     model::Request::TokenVerify { .. } => unreachable!(), // handled in module console
                 };
@@ -985,6 +1562,19 @@ impl State {
                     }
                 };
 
+                // A error on the response_tx means that the connection
+                // was closed in the meantime. Ignore it.
+                response.send(payload).ok();
+            }
+            Request::Attach(container, input, output) => {
+                let payload = match self.attach(&container, input, output) {
+                    Ok(_) => model::Response::Attach(model::AttachResult::Ok { container }),
+                    Err(e) => model::Response::Attach(model::AttachResult::Error {
+                        container,
+                        error: e.into(),
+                    }),
+                };
+
                 // A error on the response_tx means that the connection
                 // was closed in the meantime. Ignore it.
                 response.send(payload).ok();
@@ -1184,6 +1774,13 @@ impl State {
             uptime: context.started.elapsed().as_nanos() as u64,
             statistics: context.cgroups.stats(),
         });
+        let persist = runtime_info
+            .and_then(|context| context.volume.as_ref())
+            .and_then(|volume| volume.usage().ok())
+            .map(|usage| api::model::PersistUsage {
+                size: usage.size,
+                used: usage.used,
+            });
         let repository = state.repository.clone();
         let mounted = state.is_mounted();
 
@@ -1192,11 +1789,112 @@ impl State {
             repository,
             mounted,
             process,
+            persist,
         })
     }
 
-    fn list_containers(&self) -> Vec<api::model::Container> {
-        self.containers.keys().cloned().collect()
+    /// Pseudo terminal of a started container
+    fn tty(&self, container: &Container) -> Result<&io::Tty, Error> {
+        let process = self
+            .state(container)?
+            .process
+            .as_ref()
+            .ok_or_else(|| Error::ContainerNotStarted(container.clone()))?;
+        process
+            .tty
+            .as_ref()
+            .ok_or_else(|| Error::InvalidArguments(format!("{} has no terminal", container)))
+    }
+
+    /// Attach a console connection to the terminal of `container`
+    fn attach(
+        &self,
+        container: &Container,
+        input: mpsc::Receiver<Bytes>,
+        output: mpsc::Sender<Bytes>,
+    ) -> Result<(), Error> {
+        info!("Attaching to the terminal of {}", container);
+        self.tty(container)?
+            .attach(input, output)
+            .with_context(|| format!("failed to attach to {}", container))
+            .map_err(Error::from)
+    }
+
+    /// Set the window size of the terminal of `container`
+    fn resize(&self, container: &Container, rows: u16, cols: u16) -> Result<(), Error> {
+        debug!(
+            "Resizing the terminal of {} to {}x{}",
+            container, cols, rows
+        );
+        self.tty(container)?
+            .resize(rows, cols)
+            .with_context(|| format!("failed to resize the terminal of {}", container))
+            .map_err(Error::from)
+    }
+
+    /// Seccomp configuration of `container` extended with the system calls recorded in
+    /// seccomp learn mode
+    fn seccomp(&self, container: &Container) -> Result<api::model::Seccomp, Error> {
+        let state = self.state(container)?;
+        let syscalls = state.seccomp.as_ref().ok_or_else(|| {
+            Error::InvalidArguments(format!("no seccomp learn data for {}", container))
+        })?;
+        let manifest = self.manifest(container)?;
+        Ok(seccomp::learned(
+            manifest.seccomp.as_ref(),
+            syscalls.iter().cloned(),
+        ))
+    }
+
+    /// List installed containers and their labels. If `selector` is set only containers
+    /// with matching labels are returned.
+    fn list_containers(&self, selector: Option<&Selector>) -> Vec<api::model::ListEntry> {
+        self.containers
+            .keys()
+            .filter_map(|container| {
+                let labels = &self.manifest(container).ok()?.labels;
+                match selector {
+                    Some(selector) if !selector.matches(labels) => None,
+                    _ => Some(api::model::ListEntry {
+                        container: container.clone(),
+                        labels: labels.clone(),
+                    }),
+                }
+            })
+            .collect()
+    }
+
+    /// Resolve the containers addressed by a request. The result contains the explicitly
+    /// listed `containers` followed by the installed containers whose labels match `selector`
+    /// and that pass `filter`. Duplicates are removed.
+    fn select<F>(
+        &self,
+        containers: &[Container],
+        selector: Option<&Selector>,
+        filter: F,
+    ) -> Vec<Container>
+    where
+        F: Fn(&ContainerState, &Manifest) -> bool,
+    {
+        let selected = self
+            .containers
+            .iter()
+            .filter(
+                |(container, state)| match (selector, self.manifest(container)) {
+                    (Some(selector), Ok(manifest)) => {
+                        selector.matches(&manifest.labels) && filter(state, manifest)
+                    }
+                    _ => false,
+                },
+            )
+            .map(|(container, _)| container)
+            .sorted();
+        containers
+            .iter()
+            .chain(selected)
+            .unique()
+            .cloned()
+            .collect()
     }
 
     /// Send a container event to all subscriber consoles
@@ -1238,6 +1936,29 @@ impl State {
     }
 }
 
+/// Send SIGTERM to `container` after `deadline` and SIGKILL if it is still running
+/// after `stop_timeout`. The stop sequence is aborted when `stop` is cancelled.
+async fn stop_after_deadline(
+    container: Container,
+    pid: Pid,
+    deadline: time::Duration,
+    stop_timeout: time::Duration,
+    events_tx: EventTx,
+    stop: CancellationToken,
+) {
+    for (duration, signal) in [(deadline, Signal::SIGTERM), (stop_timeout, Signal::SIGKILL)] {
+        tokio::select! {
+            _ = stop.cancelled() => break,
+            _ = time::sleep(duration) => {
+                let event = Event::Deadline(container.clone(), pid, signal);
+                if events_tx.send(event).await.is_err() {
+                    break;
+                }
+            }
+        }
+    }
+}
+
 #[test]
 #[allow(clippy::unwrap_used)]
 fn find_newest_resource() {
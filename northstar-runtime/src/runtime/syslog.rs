@@ -0,0 +1,297 @@
+use super::{
+    config::{self, SyslogFormat},
+    Pid,
+};
+use crate::{common::container::Container, npk::manifest::io::Level};
+use std::{fmt::Write, path::PathBuf, time::SystemTime};
+use tokio::{io, net::UnixDatagram};
+
+/// Facility of container output: user-level messages
+const FACILITY: u8 = 1;
+
+/// SD-ID of the structured data element with the container fields
+const SD_ID: &str = "northstar@32473";
+
+/// Line of container output with the fields sent to the syslog socket
+pub(super) struct Entry<'a> {
+    pub container: &'a Container,
+    pub pid: Option<Pid>,
+    /// stdout or stderr
+    pub stream: &'a str,
+    pub level: Level,
+    /// Identifier of the entry
+    pub tag: &'a str,
+    pub message: &'a str,
+}
+
+/// Unbound datagram socket that sends entries to the configured syslog socket
+pub(super) struct Syslog {
+    socket: UnixDatagram,
+    path: PathBuf,
+    format: SyslogFormat,
+}
+
+impl Syslog {
+    pub fn new(config: &config::Syslog) -> io::Result<Syslog> {
+        Ok(Syslog {
+            socket: UnixDatagram::unbound()?,
+            path: config.path.clone(),
+            format: config.format,
+        })
+    }
+
+    /// Send `entry` in the configured format
+    pub async fn send(&self, entry: &Entry<'_>) -> io::Result<()> {
+        let message = match self.format {
+            SyslogFormat::Rfc3164 => rfc3164(entry, SystemTime::now()).into_bytes(),
+            SyslogFormat::Rfc5424 => rfc5424(entry, SystemTime::now()).into_bytes(),
+            SyslogFormat::Journald => journald(entry),
+        };
+        self.socket.send_to(&message, &self.path).await.map(drop)
+    }
+}
+
+/// Syslog severity of `level`
+fn severity(level: Level) -> u8 {
+    match level {
+        Level::Error => 3,
+        Level::Warn => 4,
+        Level::Info => 6,
+        Level::Debug | Level::Trace => 7,
+    }
+}
+
+/// Format `entry` as RFC 3164 message in the format of syslog(3) without hostname. The
+/// timestamp is in UTC.
+fn rfc3164(entry: &Entry<'_>, time: SystemTime) -> String {
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+
+    // The tag is limited to 32 characters and terminated by a colon or bracket
+    let tag = entry
+        .tag
+        .chars()
+        .map(|c| match c {
+            ':' | '[' | ']' => '_',
+            c if c.is_ascii_graphic() => c,
+            _ => '_',
+        })
+        .take(32)
+        .collect::<String>();
+
+    // Timestamp "Mmm dd hh:mm:ss" from "yyyy-mm-ddThh:mm:ssZ"
+    let rfc3339 = humantime::format_rfc3339_seconds(time).to_string();
+    let month = rfc3339[5..7]
+        .parse::<usize>()
+        .ok()
+        .and_then(|month| MONTHS.get(month.wrapping_sub(1)))
+        .unwrap_or(&MONTHS[0]);
+    let day = rfc3339[8..10].trim_start_matches('0');
+
+    let mut message = format!(
+        "<{}>{} {:>2} {} {}",
+        FACILITY * 8 + severity(entry.level),
+        month,
+        day,
+        &rfc3339[11..19],
+        tag
+    );
+    if let Some(pid) = entry.pid {
+        write!(message, "[{}]", pid).ok();
+    }
+    write!(message, ": {}", entry.message).ok();
+    message
+}
+
+/// Format `entry` as RFC 5424 message. The container fields are added as structured data.
+fn rfc5424(entry: &Entry<'_>, time: SystemTime) -> String {
+    // APP-NAME is limited to 48 printable characters
+    let app_name = entry
+        .tag
+        .chars()
+        .map(|c| if c.is_ascii_graphic() { c } else { '_' })
+        .take(48)
+        .collect::<String>();
+    let proc_id = entry
+        .pid
+        .map(|pid| pid.to_string())
+        .unwrap_or_else(|| "-".into());
+
+    let mut message = format!(
+        "<{}>1 {} - {} {} {} [{}",
+        FACILITY * 8 + severity(entry.level),
+        humantime::format_rfc3339_micros(time),
+        app_name,
+        proc_id,
+        entry.stream,
+        SD_ID,
+    );
+    let container = entry.container.to_string();
+    let name = entry.container.name().to_string();
+    let version = entry.container.version().to_string();
+    let mut params = vec![
+        ("container", container.as_str()),
+        ("name", name.as_str()),
+        ("version", version.as_str()),
+        ("stream", entry.stream),
+    ];
+    if entry.pid.is_some() {
+        params.push(("pid", proc_id.as_str()));
+    }
+    for (key, value) in params {
+        let value = value
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace(']', "\\]");
+        write!(message, " {}=\"{}\"", key, value).ok();
+    }
+    write!(message, "] {}", entry.message).ok();
+    message
+}
+
+/// Format `entry` in the native journald protocol. The container fields are added as
+/// journal fields.
+fn journald(entry: &Entry<'_>) -> Vec<u8> {
+    let mut message = Vec::new();
+    let mut field = |key: &str, value: &str| {
+        message.extend_from_slice(key.as_bytes());
+        // Values with newlines need the binary format with a little endian length
+        if value.contains('\n') {
+            message.push(b'\n');
+            message.extend_from_slice(&(value.len() as u64).to_le_bytes());
+        } else {
+            message.push(b'=');
+        }
+        message.extend_from_slice(value.as_bytes());
+        message.push(b'\n');
+    };
+    field("MESSAGE", entry.message);
+    field("PRIORITY", &severity(entry.level).to_string());
+    field("SYSLOG_FACILITY", &FACILITY.to_string());
+    field("SYSLOG_IDENTIFIER", entry.tag);
+    if let Some(pid) = entry.pid {
+        field("SYSLOG_PID", &pid.to_string());
+    }
+    field("NORTHSTAR_CONTAINER", &entry.container.to_string());
+    field("NORTHSTAR_NAME", entry.container.name().as_ref());
+    field("NORTHSTAR_VERSION", &entry.container.version().to_string());
+    field("NORTHSTAR_STREAM", entry.stream);
+    message
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod test {
+    use super::*;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    fn entry(container: &Container) -> Entry<'_> {
+        Entry {
+            container,
+            pid: Some(42),
+            stream: "stderr",
+            level: Level::Warn,
+            tag: "hello",
+            message: "hello \"world\"",
+        }
+    }
+
+    #[test]
+    fn format_rfc3164() {
+        let container = Container::try_from("hello:0.0.1").unwrap();
+        let time = UNIX_EPOCH + Duration::from_secs(1);
+        assert_eq!(
+            rfc3164(&entry(&container), time),
+            "<12>Jan  1 00:00:01 hello[42]: hello \"world\""
+        );
+
+        let mut entry = entry(&container);
+        entry.pid = None;
+        entry.tag = "hello: world";
+        entry.level = Level::Trace;
+        let time = UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+        assert_eq!(
+            rfc3164(&entry, time),
+            "<15>Sep  9 01:46:40 hello__world: hello \"world\""
+        );
+    }
+
+    #[test]
+    fn format_rfc5424() {
+        let container = Container::try_from("hello:0.0.1").unwrap();
+        let time = UNIX_EPOCH + Duration::from_secs(1);
+        assert_eq!(
+            rfc5424(&entry(&container), time),
+            "<12>1 1970-01-01T00:00:01.000000Z - hello 42 stderr [northstar@32473 \
+             container=\"hello:0.0.1\" name=\"hello\" version=\"0.0.1\" stream=\"stderr\" \
+             pid=\"42\"] hello \"world\""
+        );
+
+        let mut entry = entry(&container);
+        entry.pid = None;
+        entry.tag = "hello world";
+        entry.level = Level::Trace;
+        assert!(rfc5424(&entry, time)
+            .starts_with("<15>1 1970-01-01T00:00:01.000000Z - hello_world - stderr"));
+    }
+
+    #[test]
+    fn format_journald() {
+        let container = Container::try_from("hello:0.0.1").unwrap();
+        assert_eq!(
+            journald(&entry(&container)),
+            b"MESSAGE=hello \"world\"\nPRIORITY=4\nSYSLOG_FACILITY=1\nSYSLOG_IDENTIFIER=hello\n\
+             SYSLOG_PID=42\nNORTHSTAR_CONTAINER=hello:0.0.1\nNORTHSTAR_NAME=hello\n\
+             NORTHSTAR_VERSION=0.0.1\nNORTHSTAR_STREAM=stderr\n"
+        );
+
+        let mut entry = entry(&container);
+        entry.tag = "hello\nworld";
+        let expected = [
+            b"SYSLOG_IDENTIFIER\n".as_ref(),
+            &11u64.to_le_bytes(),
+            b"hello\nworld\n",
+        ]
+        .concat();
+        assert!(journald(&entry)
+            .windows(expected.len())
+            .any(|w| w == expected));
+    }
+
+    /// Send entries to a local datagram socket that stands in for the syslog daemon
+    #[tokio::test]
+    async fn send() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("log");
+        let server = UnixDatagram::bind(&path).unwrap();
+        let container = Container::try_from("hello:0.0.1").unwrap();
+        let mut buffer = [0u8; 1024];
+
+        let config = config::Syslog {
+            path: path.clone(),
+            format: SyslogFormat::Rfc5424,
+        };
+        Syslog::new(&config)
+            .unwrap()
+            .send(&entry(&container))
+            .await
+            .unwrap();
+        let n = server.recv(&mut buffer).await.unwrap();
+        let message = std::str::from_utf8(&buffer[..n]).unwrap();
+        assert!(message.starts_with("<12>1 "));
+        assert!(message.ends_with("] hello \"world\""));
+
+        let config = config::Syslog {
+            path,
+            format: SyslogFormat::Journald,
+        };
+        Syslog::new(&config)
+            .unwrap()
+            .send(&entry(&container))
+            .await
+            .unwrap();
+        let n = server.recv(&mut buffer).await.unwrap();
+        assert_eq!(&buffer[..n], journald(&entry(&container)));
+    }
+}
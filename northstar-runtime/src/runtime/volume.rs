@@ -0,0 +1,251 @@
+//! Size limited persist mounts and named volumes. The persist directory of a container
+//! with a size limit is a filesystem image in the data dir that is loop mounted while the
+//! container runs. Named volumes with a size are mounted the same way while at least one
+//! container uses them.
+
+use super::{config::Config, mount::MountControl, Container, ContainerEvent, Event, EventTx};
+use anyhow::{bail, Context, Result};
+use log::{debug, info, warn};
+use nix::sys::statvfs::statvfs;
+use std::path::{Path, PathBuf};
+use tokio::{
+    fs,
+    process::Command,
+    select,
+    task::{self, JoinHandle},
+    time,
+};
+use tokio_util::sync::CancellationToken;
+
+/// Usage of a volume
+#[derive(Clone, Copy, Debug)]
+pub(super) struct Usage {
+    /// Size in bytes
+    pub size: u64,
+    /// Used bytes
+    pub used: u64,
+}
+
+/// Mounted volume image
+#[derive(Debug)]
+pub(super) struct Volume {
+    /// Mount point of the image
+    dir: PathBuf,
+    /// Usage monitor of persist volumes
+    monitor: Option<(CancellationToken, JoinHandle<()>)>,
+}
+
+impl Volume {
+    /// Create the image of `container` with `size` if it does not exist and mount it on the
+    /// persist directory. The usage is checked periodically and an event is sent when it
+    /// crosses the configured threshold.
+    pub async fn new(
+        config: &Config,
+        mount_control: &MountControl,
+        container: &Container,
+        size: u64,
+        tx: EventTx,
+    ) -> Result<Volume> {
+        let name = container.name().to_string();
+        let dir = config.data_dir.join(&name);
+        mount(config, mount_control, &image(config, &name), &dir, size).await?;
+
+        let token = CancellationToken::new();
+        let task = monitor(
+            container.clone(),
+            dir.clone(),
+            config.persist.usage_threshold,
+            config.persist.usage_interval,
+            tx,
+            token.clone(),
+        );
+
+        Ok(Volume {
+            dir,
+            monitor: Some((token, task)),
+        })
+    }
+
+    /// Create the image of the named volume `name` with `size` if it does not exist and
+    /// mount it on `dir`
+    pub async fn shared(
+        config: &Config,
+        mount_control: &MountControl,
+        name: &str,
+        dir: &Path,
+        size: u64,
+    ) -> Result<Volume> {
+        let image = shared_image(config, name);
+        mount(config, mount_control, &image, dir, size).await?;
+
+        Ok(Volume {
+            dir: dir.to_owned(),
+            monitor: None,
+        })
+    }
+
+    /// Current usage of the volume
+    pub fn usage(&self) -> Result<Usage> {
+        usage(&self.dir)
+    }
+
+    /// Stop the usage monitor and umount the image
+    pub async fn destroy(self) {
+        if let Some((token, task)) = self.monitor {
+            token.cancel();
+            if let Err(e) = task.await {
+                warn!(
+                    "Failed to join usage monitor of {}: {}",
+                    self.dir.display(),
+                    e
+                );
+            }
+        }
+
+        debug!("Unmounting {}", self.dir.display());
+        let dir = self.dir.clone();
+        let result = task::spawn_blocking(move || nix::mount::umount(&dir))
+            .await
+            .context("failed to join umount task")
+            .and_then(|result| result.context("failed to umount"));
+        if let Err(e) = result {
+            warn!("Failed to umount {}: {:#}", self.dir.display(), e);
+        }
+    }
+}
+
+/// Path of the image of container `name`. Container and volume names cannot contain a
+/// colon and do not clash with the persist directories or the images of named volumes.
+pub(super) fn image(config: &Config, name: &str) -> PathBuf {
+    config.data_dir.join(format!("{}:persist", name))
+}
+
+/// Path of the image of the named volume `name`
+pub(super) fn shared_image(config: &Config, name: &str) -> PathBuf {
+    config.data_dir.join(format!("{}:volume", name))
+}
+
+/// Create `image` with `size` if it does not exist and mount it on `dir`
+async fn mount(
+    config: &Config,
+    mount_control: &MountControl,
+    image: &Path,
+    dir: &Path,
+    size: u64,
+) -> Result<()> {
+    // Existing images are not resized. Resizing depends on the filesystem and shrinking
+    // an image can lose data.
+    match fs::metadata(image).await {
+        Ok(metadata) if metadata.len() != size => bail!(
+            "size of {} is {} instead of {}",
+            image.display(),
+            metadata.len(),
+            size
+        ),
+        Ok(_) => (),
+        Err(_) => create(config, image, size).await?,
+    }
+
+    if !dir.exists() {
+        debug!("Creating {}", dir.display());
+        fs::create_dir_all(dir)
+            .await
+            .with_context(|| format!("failed to create directory {}", dir.display()))?;
+    }
+
+    mount_control
+        .mount_image(image, dir, &config.persist.fstype)
+        .await
+        .with_context(|| format!("failed to mount {}", image.display()))
+}
+
+/// Create a sparse image with `size` and format it with the configured mkfs
+async fn create(config: &Config, image: &Path, size: u64) -> Result<()> {
+    info!("Creating {} with size {}", image.display(), size);
+    let file = fs::File::create(image)
+        .await
+        .with_context(|| format!("failed to create {}", image.display()))?;
+    file.set_len(size)
+        .await
+        .with_context(|| format!("failed to resize {}", image.display()))?;
+    drop(file);
+
+    let mkfs = &config.persist.mkfs;
+    let mut cmd = Command::new(mkfs);
+    if let Some(flags) = &config.persist.mkfs_flags {
+        cmd.args(flags.split_whitespace());
+    }
+    cmd.arg(image);
+    debug!("Running {:?}", cmd);
+    let output = cmd.output().await;
+
+    match output {
+        Ok(output) if output.status.success() => Ok(()),
+        Ok(output) => {
+            fs::remove_file(image).await.ok();
+            bail!(
+                "{} failed with {}: {}",
+                mkfs.display(),
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )
+        }
+        Err(e) => {
+            fs::remove_file(image).await.ok();
+            Err(e).with_context(|| format!("failed to run {}", mkfs.display()))
+        }
+    }
+}
+
+/// Usage of the filesystem mounted on `dir`
+fn usage(dir: &Path) -> Result<Usage> {
+    let stat = statvfs(dir).with_context(|| format!("failed to stat {}", dir.display()))?;
+    let fragment_size = stat.fragment_size() as u64;
+    let size = stat.blocks() as u64 * fragment_size;
+    let used = (stat.blocks() - stat.blocks_free()) as u64 * fragment_size;
+    Ok(Usage { size, used })
+}
+
+/// Check the usage of `dir` every `interval` and send an event when the usage crosses
+/// `threshold` percent of the size
+fn monitor(
+    container: Container,
+    dir: PathBuf,
+    threshold: u8,
+    interval: time::Duration,
+    tx: EventTx,
+    token: CancellationToken,
+) -> JoinHandle<()> {
+    task::spawn(async move {
+        let mut interval = time::interval(interval);
+        let mut above = false;
+
+        loop {
+            select! {
+                _ = token.cancelled() => break,
+                _ = tx.closed() => break,
+                _ = interval.tick() => {
+                    let usage = match usage(&dir) {
+                        Ok(usage) => usage,
+                        Err(e) => {
+                            warn!("Failed to get persist usage of {}: {:#}", container, e);
+                            continue;
+                        }
+                    };
+                    let crossed = usage.used * 100 >= usage.size * threshold as u64;
+                    if crossed && !above {
+                        warn!(
+                            "Persist mount of {} uses {} of {} bytes",
+                            container, usage.used, usage.size
+                        );
+                        let event = Event::Container(container.clone(), ContainerEvent::Persist(usage));
+                        if tx.send(event).await.is_err() {
+                            break;
+                        }
+                    }
+                    above = crossed;
+                }
+            }
+        }
+    })
+}
@@ -1,12 +1,16 @@
 use crate::{
     common::non_nul_string::NonNulString,
     npk::manifest::capabilities::Capability,
-    seccomp::{profiles::default, Profile, SyscallArgRule, SyscallRule},
+    seccomp::{
+        profiles::default, Action, Profile, Seccomp, SyscallArgCondition, SyscallArgOp,
+        SyscallArgRule, SyscallRule,
+    },
 };
 use anyhow::{bail, Result};
 use bindings::{
-    seccomp_data, sock_filter, sock_fprog, BPF_ABS, BPF_ALU, BPF_AND, BPF_IMM, BPF_JEQ, BPF_JMP,
-    BPF_K, BPF_LD, BPF_MAXINSNS, BPF_MEM, BPF_NEG, BPF_OR, BPF_RET, BPF_ST, BPF_W, SYSCALL_MAP,
+    seccomp_data, sock_filter, sock_fprog, BPF_ABS, BPF_ALU, BPF_AND, BPF_IMM, BPF_JEQ, BPF_JGE,
+    BPF_JGT, BPF_JMP, BPF_K, BPF_LD, BPF_MAXINSNS, BPF_MEM, BPF_NEG, BPF_OR, BPF_RET, BPF_ST,
+    BPF_W, SYSCALL_MAP,
 };
 use log::trace;
 use nix::errno::Errno;
@@ -14,6 +18,7 @@ use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::{
     collections::{HashMap, HashSet},
     mem::size_of,
+    os::unix::prelude::RawFd,
 };
 
 #[allow(unused, non_snake_case, non_camel_case_types, non_upper_case_globals)]
@@ -30,38 +35,79 @@ const AUDIT_ARCH: u32 = bindings::AUDIT_ARCH_X86_64;
 /// Syscalls used by northstar after the seccomp rules are applied and before the actual execve is done.
 const REQUIRED_SYSCALLS: &[u32] = &[bindings::SYS_execve];
 
+/// Return value of the filter that notifies the listener of the filter
+const SECCOMP_RET_USER_NOTIF: u32 = 0x7fc00000;
+/// Operation of the seccomp system call that installs a filter
+const SECCOMP_SET_MODE_FILTER: nix::libc::c_ulong = 1;
+/// Flag of the seccomp system call that returns a listener fd for the filter
+const SECCOMP_FILTER_FLAG_NEW_LISTENER: nix::libc::c_ulong = 1 << 3;
+
 /// Jump to next instruction and execute
 const EVAL_NEXT: u8 = 0;
 /// Skip next instruction
 const SKIP_NEXT: u8 = 1;
 
-/// Construct a allowlist syscall filter that is applied post clone.
+/// Construct a allowlist syscall filter that is applied post clone. With `learn` set the
+/// filter reports system calls that are not allowed to the listener of the filter instead
+/// of applying the default action. Named profiles must be merged into `seccomp` before.
 pub fn seccomp_filter(
-    profile: Option<&Profile>,
-    rules: Option<&HashMap<NonNulString, SyscallRule>>,
+    seccomp: &Seccomp,
     caps: &HashSet<Capability>,
-) -> AllowList {
+    learn: bool,
+) -> Result<AllowList> {
     check_platform_requirements();
 
     let mut builder = Builder::new();
-    if let Some(profile) = profile {
-        builder.extend(builder_from_profile(profile, caps));
+    if learn {
+        builder.learn();
+    }
+    match &seccomp.profile {
+        Some(Profile::Named(name)) => bail!("unresolved seccomp profile {}", name),
+        Some(profile) => {
+            builder.extend(builder_from_profile(profile, caps));
+        }
+        None => (),
     }
-    if let Some(rules) = rules {
+    if let Some(rules) = &seccomp.allow {
         builder.extend(builder_from_rules(rules));
     }
-    builder.build()
+    if let Some(deny) = &seccomp.deny {
+        for (name, action) in deny {
+            // Unknown syscalls cannot be called and do not need to be denied
+            if let Err(e) = builder.deny_syscall_name(name, *action) {
+                trace!("failed to deny syscall {}: {}", &name.to_string(), e);
+            }
+        }
+    }
+    if let Some(action) = seccomp.default_action {
+        builder.default_action(action);
+    }
+    Ok(builder.build())
+}
+
+/// Extend `seccomp` with the system calls recorded in learn mode. Recorded system calls
+/// without a rule are allowed with any arguments. Existing rules are kept. Unknown system
+/// call numbers are ignored.
+pub fn learned(seccomp: Option<&Seccomp>, syscalls: impl IntoIterator<Item = u32>) -> Seccomp {
+    let mut seccomp = seccomp.cloned().unwrap_or_default();
+    let allow = seccomp.allow.get_or_insert_with(HashMap::new);
+    for nr in syscalls {
+        match syscall_name(nr) {
+            Some(name) => {
+                let name = NonNulString::try_from(name).expect("invalid syscall name");
+                allow.entry(name).or_insert(SyscallRule::Any);
+            }
+            None => trace!("Ignoring unknown syscall {}", nr),
+        }
+    }
+    seccomp
 }
 
 /// Create an AllowList Builder from a list of syscall names
 pub(crate) fn builder_from_rules(rules: &HashMap<NonNulString, SyscallRule>) -> Builder {
     let mut builder = Builder::new();
     for (name, call_rule) in rules {
-        let arg_rule = match call_rule {
-            SyscallRule::Any => None,
-            SyscallRule::Args(a) => Some(a),
-        };
-        if let Err(e) = builder.allow_syscall_name(name, arg_rule.cloned()) {
+        if let Err(e) = builder.allow_syscall_name(name, call_rule.clone()) {
             // Only issue a warning as a missing syscall on the allow list does not lead to insecure behaviour
             trace!("failed to allow syscall {}: {}", &name.to_string(), e);
         }
@@ -154,6 +200,7 @@ fn builder_from_profile(profile: &Profile, caps: &HashSet<Capability>) -> Builde
             }
             builder
         }
+        Profile::Named(_) => unreachable!("named profiles are resolved by the runtime"),
     }
 }
 
@@ -216,6 +263,9 @@ impl From<&SockFilter> for sock_filter {
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct AllowList {
     list: Vec<SockFilter>,
+    /// System calls that are not allowed are reported to the listener of the filter
+    #[serde(default)]
+    learn: bool,
 }
 
 impl AllowList {
@@ -229,17 +279,7 @@ impl AllowList {
         #[cfg(not(target_os = "android"))]
         use nix::libc::{PR_SET_SECCOMP, SECCOMP_MODE_FILTER};
 
-        if self.list.len() > BPF_MAXINSNS as usize {
-            bail!("seccomp filter list exceeds maximum number of BPF statements");
-        }
-
-        // Convert the list of instructions into the bindings sock_filter
-        let list = self
-            .list
-            .iter()
-            .map(Into::into)
-            .collect::<Vec<sock_filter>>();
-
+        let list = self.program()?;
         let sf_prog = sock_fprog {
             len: list.len() as u16,
             filter: list.as_ptr() as *mut bindings::sock_filter,
@@ -249,21 +289,57 @@ impl AllowList {
         Errno::result(result)?;
         Ok(())
     }
+
+    /// Apply this seccomp filter settings to the current thread and return the listener
+    /// fd that receives the notifications of the filter. The fd is the lowest free fd
+    /// of the process.
+    pub fn apply_with_listener(&self) -> Result<RawFd> {
+        let list = self.program()?;
+        let sf_prog = sock_fprog {
+            len: list.len() as u16,
+            filter: list.as_ptr() as *mut bindings::sock_filter,
+        };
+        let sf_prog_ptr = &sf_prog as *const sock_fprog;
+        let result = unsafe {
+            nix::libc::syscall(
+                nix::libc::SYS_seccomp,
+                SECCOMP_SET_MODE_FILTER,
+                SECCOMP_FILTER_FLAG_NEW_LISTENER,
+                sf_prog_ptr,
+            )
+        };
+        Ok(Errno::result(result)? as RawFd)
+    }
+
+    /// True if this filter reports system calls that are not allowed to its listener
+    pub fn learn(&self) -> bool {
+        self.learn
+    }
+
+    /// Convert the list of instructions into the bindings sock_filter
+    fn program(&self) -> Result<Vec<sock_filter>> {
+        if self.list.len() > BPF_MAXINSNS as usize {
+            bail!("seccomp filter list exceeds maximum number of BPF statements");
+        }
+        Ok(self.list.iter().map(Into::into).collect())
+    }
 }
 
 #[derive(Clone, Eq, PartialEq, Debug)]
 struct NumericSyscallRule {
     /// Number of syscall
     nr: u32,
-    /// Allowed argument values. If no values are defined, the syscall is allowed unconditionally.
-    arg_rule: Option<SyscallArgRule>,
+    /// Allowed arguments. `SyscallRule::Any` allows the syscall unconditionally.
+    rule: SyscallRule,
 }
 
 /// Builder for AllowList struct
 #[derive(Default, Clone)]
 pub struct Builder {
     allowlist: Vec<NumericSyscallRule>,
-    log_only: bool,
+    denylist: Vec<(u32, Action)>,
+    default_action: Option<Action>,
+    learn: bool,
 }
 
 impl Builder {
@@ -273,18 +349,14 @@ impl Builder {
 
         // Add required syscalls (e.g. for execve)
         for syscall in REQUIRED_SYSCALLS {
-            builder.allow_syscall_nr(*syscall as u32, None);
+            builder.allow_syscall_nr(*syscall as u32, SyscallRule::Any);
         }
         builder
     }
 
     /// Add syscall to allowlist by number
-    pub(crate) fn allow_syscall_nr(
-        &mut self,
-        nr: u32,
-        arg_rule: Option<SyscallArgRule>,
-    ) -> &mut Builder {
-        self.allowlist.push(NumericSyscallRule { nr, arg_rule });
+    pub(crate) fn allow_syscall_nr(&mut self, nr: u32, rule: SyscallRule) -> &mut Builder {
+        self.allowlist.push(NumericSyscallRule { nr, rule });
         self
     }
 
@@ -292,26 +364,46 @@ impl Builder {
     pub(crate) fn allow_syscall_name(
         &mut self,
         name: &str,
-        arg_rule: Option<SyscallArgRule>,
+        rule: SyscallRule,
     ) -> Result<&mut Builder> {
         match translate_syscall(name) {
-            Some(nr) => Ok(self.allow_syscall_nr(nr, arg_rule)),
+            Some(nr) => Ok(self.allow_syscall_nr(nr, rule)),
             None => bail!("unknown system call {}", name),
         }
     }
 
-    /// Log syscall violations instead of aborting the program
-    #[allow(unused)]
-    pub(crate) fn log_only(&mut self) -> &mut Builder {
-        self.log_only = true;
+    /// Deny syscall by name with `action`
+    pub(crate) fn deny_syscall_name(&mut self, name: &str, action: Action) -> Result<&mut Builder> {
+        match translate_syscall(name) {
+            Some(nr) => {
+                self.denylist.push((nr, action));
+                Ok(self)
+            }
+            None => bail!("unknown system call {}", name),
+        }
+    }
+
+    /// Action for syscalls that are not allowed. Defaults to killing the process.
+    pub(crate) fn default_action(&mut self, action: Action) -> &mut Builder {
+        self.default_action = Some(action);
+        self
+    }
+
+    /// Report syscall violations to the listener of the filter. The listener is also
+    /// notified about `execve` in order to tell the system calls of the runtime from the
+    /// ones of the application.
+    pub(crate) fn learn(&mut self) -> &mut Builder {
+        self.learn = true;
         self
     }
 
     /// Extend one builder with another builder.
-    /// Note: The 'log_only' property of the extended builder is only set to true if it was true in both original builders.
+    /// Note: The default action of this builder takes precedence over the one of `other`.
     pub(crate) fn extend(&mut self, other: Builder) -> &mut Builder {
         self.allowlist.extend(other.allowlist);
-        self.log_only &= other.log_only;
+        self.denylist.extend(other.denylist);
+        self.default_action = self.default_action.or(other.default_action);
+        self.learn |= other.learn;
         self
     }
 
@@ -320,8 +412,15 @@ impl Builder {
         // sort and dedup syscall numbers to check common syscalls first
         self.allowlist.sort_unstable_by_key(|rule| rule.nr);
         self.allowlist.dedup();
+        if self.learn {
+            self.allowlist
+                .retain(|rule| rule.nr != bindings::SYS_execve);
+        }
 
-        let mut filter = AllowList { list: vec![] };
+        let mut filter = AllowList {
+            list: vec![],
+            learn: self.learn,
+        };
 
         // Load architecture into accumulator
         load_arch_into_acc(&mut filter);
@@ -335,9 +434,25 @@ impl Builder {
         // Load syscall number into accumulator for subsequent filtering
         load_syscall_nr_into_acc(&mut filter);
 
+        // Deny rules take precedence over allowed syscalls. The first rule of a syscall wins.
+        let mut denied = HashSet::new();
+        for (nr, action) in &self.denylist {
+            if denied.insert(*nr) {
+                trace!("Adding seccomp deny block (nr={})", nr);
+                jump_if_acc_is_equal(&mut filter, *nr, EVAL_NEXT, SKIP_NEXT);
+                return_fail(&mut filter, *action);
+            }
+        }
+
         // Add filter block for every allowed syscall
         for rule in &self.allowlist {
-            if let Some(arg_rule) = &rule.arg_rule {
+            if let SyscallRule::AllOf(conditions) = &rule.rule {
+                trace!("Adding seccomp all of block (nr={})", rule.nr);
+                conditions_block(&mut filter, rule.nr, conditions, true);
+            } else if let SyscallRule::AnyOf(conditions) = &rule.rule {
+                trace!("Adding seccomp any of block (nr={})", rule.nr);
+                conditions_block(&mut filter, rule.nr, conditions, false);
+            } else if let SyscallRule::Args(arg_rule) = &rule.rule {
                 if let Some(values) = &arg_rule.values {
                     trace!("Adding seccomp argument block (nr={})", rule.nr);
 
@@ -403,7 +518,14 @@ impl Builder {
         }
 
         // Fall through consequence if not filter rule matched
-        return_fail(&mut filter, self.log_only);
+        if self.learn {
+            filter.list.push(bpf_ret(SECCOMP_RET_USER_NOTIF));
+        } else {
+            return_fail(
+                &mut filter,
+                self.default_action.unwrap_or(Action::KillProcess),
+            );
+        }
 
         filter
     }
@@ -414,6 +536,14 @@ fn translate_syscall(name: &str) -> Option<u32> {
     SYSCALL_MAP.get(name).cloned()
 }
 
+/// Get syscall name by number
+pub(crate) fn syscall_name(nr: u32) -> Option<&'static str> {
+    SYSCALL_MAP
+        .iter()
+        .find(|(_, n)| **n == nr)
+        .map(|(name, _)| *name)
+}
+
 /// Load architecture identifier number into accumulator
 fn load_arch_into_acc(filter: &mut AllowList) -> u32 {
     filter.list.push(bpf_stmt(
@@ -432,6 +562,162 @@ fn load_syscall_nr_into_acc(filter: &mut AllowList) -> u32 {
     1
 }
 
+/// Jump target of an instruction in a condition block
+#[derive(Clone, Copy, Debug)]
+enum Label {
+    /// The following instruction
+    Next,
+    /// First instruction of the condition with the given index
+    Condition(usize),
+    /// Instruction that allows the syscall
+    Allow,
+    /// End of the block
+    End,
+}
+
+/// Instruction of a condition block with symbolic jump targets
+enum Instruction {
+    Statement(SockFilter),
+    Jump {
+        code: u32,
+        k: u32,
+        jt: Label,
+        jf: Label,
+    },
+}
+
+/// Add a filter block that allows the syscall `nr` if all (`all` is true) or any of the
+/// `conditions` match. The syscall number is restored in the accumulator at the end of
+/// the block.
+fn conditions_block(
+    filter: &mut AllowList,
+    nr: u32,
+    conditions: &[SyscallArgCondition],
+    all: bool,
+) {
+    // No condition of an empty list matches
+    if !all && conditions.is_empty() {
+        return;
+    }
+
+    let mut block = vec![Instruction::Jump {
+        code: BPF_JMP | BPF_JEQ | BPF_K,
+        k: nr,
+        jt: Label::Next,
+        jf: Label::End,
+    }];
+    let mut starts = Vec::with_capacity(conditions.len());
+    for (index, condition) in conditions.iter().enumerate() {
+        let next = if index + 1 < conditions.len() {
+            Label::Condition(index + 1)
+        } else if all {
+            Label::Allow
+        } else {
+            Label::End
+        };
+        let (jump_true, jump_false) = if all {
+            (next, Label::End)
+        } else {
+            (Label::Allow, next)
+        };
+        starts.push(block.len());
+        condition_instructions(&mut block, condition, jump_true, jump_false);
+    }
+    let allow = block.len();
+    block.push(Instruction::Statement(bpf_ret(
+        nix::libc::SECCOMP_RET_ALLOW,
+    )));
+    let end = block.len();
+
+    // Resolve the labels into relative jump offsets
+    for (position, instruction) in block.into_iter().enumerate() {
+        match instruction {
+            Instruction::Statement(statement) => filter.list.push(statement),
+            Instruction::Jump { code, k, jt, jf } => {
+                let offset = |label| {
+                    let target = match label {
+                        Label::Next => position + 1,
+                        Label::Condition(index) => starts[index],
+                        Label::Allow => allow,
+                        Label::End => end,
+                    };
+                    u8::try_from(target - position - 1).expect("BPF offset overflow")
+                };
+                filter.list.push(bpf_jump(code, k, offset(jt), offset(jf)));
+            }
+        }
+    }
+
+    load_syscall_nr_into_acc(filter);
+}
+
+/// Append the instructions that jump to `jump_true` if `condition` matches and to
+/// `jump_false` otherwise. The high and low 32 bits of the argument are compared
+/// separately.
+fn condition_instructions(
+    block: &mut Vec<Instruction>,
+    condition: &SyscallArgCondition,
+    jump_true: Label,
+    jump_false: Label,
+) {
+    let load_low = Instruction::Statement(bpf_stmt(
+        BPF_LD | BPF_W | BPF_ABS,
+        arg_low_array_offset(condition.index) as u32,
+    ));
+    let load_high = Instruction::Statement(bpf_stmt(
+        BPF_LD | BPF_W | BPF_ABS,
+        arg_high_array_offset(condition.index) as u32,
+    ));
+    let low = condition.value as u32;
+    let high = (condition.value >> 32) as u32;
+    let jump = |code: u32, k: u32, jt: Label, jf: Label| Instruction::Jump {
+        code: BPF_JMP | code | BPF_K,
+        k,
+        jt,
+        jf,
+    };
+
+    match condition.op {
+        SyscallArgOp::Eq | SyscallArgOp::Ne => {
+            let (jt, jf) = if condition.op == SyscallArgOp::Eq {
+                (jump_true, jump_false)
+            } else {
+                (jump_false, jump_true)
+            };
+            block.push(load_high);
+            block.push(jump(BPF_JEQ, high, Label::Next, jf));
+            block.push(load_low);
+            block.push(jump(BPF_JEQ, low, jt, jf));
+        }
+        SyscallArgOp::MaskedEq => {
+            let mask = condition.mask.unwrap_or(u64::MAX);
+            block.push(load_high);
+            block.push(Instruction::Statement(bpf_and((mask >> 32) as u32)));
+            block.push(jump(BPF_JEQ, high, Label::Next, jump_false));
+            block.push(load_low);
+            block.push(Instruction::Statement(bpf_and(mask as u32)));
+            block.push(jump(BPF_JEQ, low, jump_true, jump_false));
+        }
+        SyscallArgOp::Gt | SyscallArgOp::Le | SyscallArgOp::Ge | SyscallArgOp::Lt => {
+            // Le and Lt are the negation of Gt and Ge
+            let (jt, jf) = match condition.op {
+                SyscallArgOp::Gt | SyscallArgOp::Ge => (jump_true, jump_false),
+                _ => (jump_false, jump_true),
+            };
+            let low_code = match condition.op {
+                SyscallArgOp::Gt | SyscallArgOp::Le => BPF_JGT,
+                _ => BPF_JGE,
+            };
+            // The high part decides unless it is equal
+            block.push(load_high);
+            block.push(jump(BPF_JGT, high, jt, Label::Next));
+            block.push(jump(BPF_JEQ, high, Label::Next, jf));
+            block.push(load_low);
+            block.push(jump(low_code, low, jt, jf));
+        }
+    }
+}
+
 /// Load syscall argument into the first two 32-bit registers of scratch memory
 fn load_syscall_arg_into_scratch(filter: &mut AllowList, arg_rule: &SyscallArgRule) -> u32 {
     // Load high and low parts into scratch memory separately
@@ -617,15 +903,16 @@ fn jump_if_scratch_matches_mask(
     insts
 }
 
-/// Add statement that causes the BPF program return and prohibit the syscall
-fn return_fail(filter: &mut AllowList, log_only: bool) -> u32 {
-    if log_only {
-        filter.list.push(bpf_ret(nix::libc::SECCOMP_RET_LOG));
-    } else {
-        filter
-            .list
-            .push(bpf_ret(nix::libc::SECCOMP_RET_KILL_PROCESS));
-    }
+/// Add statement that causes the BPF program return with `action` for the syscall
+fn return_fail(filter: &mut AllowList, action: Action) -> u32 {
+    let ret = match action {
+        Action::KillProcess => nix::libc::SECCOMP_RET_KILL_PROCESS,
+        Action::KillThread => nix::libc::SECCOMP_RET_KILL_THREAD,
+        Action::Errno(errno) => nix::libc::SECCOMP_RET_ERRNO | errno as u32,
+        Action::Trap => nix::libc::SECCOMP_RET_TRAP,
+        Action::Log => nix::libc::SECCOMP_RET_LOG,
+    };
+    filter.list.push(bpf_ret(ret));
     1
 }
 
@@ -680,10 +967,385 @@ fn bpf_jump(code: u32, k: u32, jt: u8, jf: u8) -> SockFilter {
 #[cfg(test)]
 #[allow(clippy::unwrap_used)]
 mod test {
-    use super::SockFilter;
+    use super::{
+        bindings, bpf_ret, Action, AllowList, Builder, Profile, Seccomp, SockFilter,
+        SyscallArgCondition, SyscallArgOp, SyscallRule, AUDIT_ARCH, BPF_ABS, BPF_ALU, BPF_AND,
+        BPF_JEQ, BPF_JGE, BPF_JGT, BPF_JMP, BPF_K, BPF_LD, BPF_RET, BPF_W, SECCOMP_RET_USER_NOTIF,
+    };
+    use nix::libc::{
+        SECCOMP_RET_ALLOW, SECCOMP_RET_ERRNO, SECCOMP_RET_KILL_PROCESS, SECCOMP_RET_TRAP,
+    };
     use proptest::prelude::*;
+    use std::collections::{HashMap, HashSet};
+
+    const OPS: [SyscallArgOp; 7] = [
+        SyscallArgOp::Eq,
+        SyscallArgOp::Ne,
+        SyscallArgOp::Lt,
+        SyscallArgOp::Le,
+        SyscallArgOp::Gt,
+        SyscallArgOp::Ge,
+        SyscallArgOp::MaskedEq,
+    ];
+
+    /// Run `filter` for syscall `nr` with `args` and return the action
+    fn run(filter: &AllowList, nr: u32, args: [u64; 6]) -> u32 {
+        // Load a 32 bit word of struct seccomp_data
+        let load = |offset: u32| match offset {
+            0 => nr,
+            4 => AUDIT_ARCH,
+            offset if offset >= 16 => {
+                let arg = args[(offset as usize - 16) / 8];
+                if offset % 8 == 0 {
+                    arg as u32
+                } else {
+                    (arg >> 32) as u32
+                }
+            }
+            _ => panic!("invalid load at offset {}", offset),
+        };
+
+        let mut acc = 0u32;
+        let mut pc = 0;
+        loop {
+            let i = &filter.list[pc];
+            let code = i.code as u32;
+            pc += 1;
+            match code {
+                c if c == BPF_LD | BPF_W | BPF_ABS => acc = load(i.k),
+                c if c == BPF_ALU | BPF_AND | BPF_K => acc &= i.k,
+                c if c == BPF_RET | BPF_K => break i.k,
+                c if c & 0x07 == BPF_JMP => {
+                    let result = match c & 0xf0 {
+                        j if j == BPF_JEQ => acc == i.k,
+                        j if j == BPF_JGT => acc > i.k,
+                        j if j == BPF_JGE => acc >= i.k,
+                        _ => panic!("unsupported jump {}", c),
+                    };
+                    pc += if result { i.jt } else { i.jf } as usize;
+                }
+                _ => panic!("unsupported instruction {}", code),
+            }
+        }
+    }
+
+    fn condition(
+        index: usize,
+        op: SyscallArgOp,
+        value: u64,
+        mask: Option<u64>,
+    ) -> SyscallArgCondition {
+        SyscallArgCondition {
+            index,
+            op,
+            value,
+            mask,
+        }
+    }
+
+    fn build(nr: u32, rule: SyscallRule) -> AllowList {
+        let mut builder = Builder::new();
+        builder.allow_syscall_nr(nr, rule);
+        builder.build()
+    }
+
+    /// Reference implementation of a condition
+    fn matches(op: SyscallArgOp, arg: u64, value: u64, mask: u64) -> bool {
+        match op {
+            SyscallArgOp::Eq => arg == value,
+            SyscallArgOp::Ne => arg != value,
+            SyscallArgOp::Lt => arg < value,
+            SyscallArgOp::Le => arg <= value,
+            SyscallArgOp::Gt => arg > value,
+            SyscallArgOp::Ge => arg >= value,
+            SyscallArgOp::MaskedEq => arg & mask == value,
+        }
+    }
+
+    #[test]
+    fn condition_boundaries() {
+        let values = [
+            0,
+            1,
+            0xffff_ffff,
+            1 << 32,
+            (1 << 32) + 1,
+            u64::MAX - 1,
+            u64::MAX,
+        ];
+        let mask = 0xffff_0000_ffff_0000;
+        for op in OPS {
+            for value in values {
+                let rule = SyscallRule::AllOf(vec![condition(3, op, value, Some(mask))]);
+                let filter = build(1, rule);
+                for arg in values {
+                    let expected = if matches(op, arg, value, mask) {
+                        SECCOMP_RET_ALLOW
+                    } else {
+                        SECCOMP_RET_KILL_PROCESS
+                    };
+                    let args = [0, 0, 0, arg, 0, 0];
+                    assert_eq!(
+                        run(&filter, 1, args),
+                        expected,
+                        "{:?} {} {}",
+                        op,
+                        arg,
+                        value
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn all_of() {
+        let filter = build(
+            9,
+            SyscallRule::AllOf(vec![
+                condition(2, SyscallArgOp::MaskedEq, 0, Some(0x4)),
+                condition(1, SyscallArgOp::Le, 0x1000, None),
+            ]),
+        );
+        assert_eq!(
+            run(&filter, 9, [0, 0x1000, 0x3, 0, 0, 0]),
+            SECCOMP_RET_ALLOW
+        );
+        assert_eq!(
+            run(&filter, 9, [0, 0x1001, 0x3, 0, 0, 0]),
+            SECCOMP_RET_KILL_PROCESS
+        );
+        assert_eq!(
+            run(&filter, 9, [0, 0x1000, 0x7, 0, 0, 0]),
+            SECCOMP_RET_KILL_PROCESS
+        );
+        assert_eq!(
+            run(&filter, 10, [0, 0x1000, 0x3, 0, 0, 0]),
+            SECCOMP_RET_KILL_PROCESS
+        );
+    }
+
+    #[test]
+    fn any_of() {
+        let filter = build(
+            9,
+            SyscallRule::AnyOf(vec![
+                condition(0, SyscallArgOp::Eq, 1, None),
+                condition(5, SyscallArgOp::Gt, 1 << 33, None),
+            ]),
+        );
+        assert_eq!(run(&filter, 9, [1, 0, 0, 0, 0, 0]), SECCOMP_RET_ALLOW);
+        assert_eq!(
+            run(&filter, 9, [0, 0, 0, 0, 0, u64::MAX]),
+            SECCOMP_RET_ALLOW
+        );
+        assert_eq!(
+            run(&filter, 9, [2, 0, 0, 0, 0, 1 << 33]),
+            SECCOMP_RET_KILL_PROCESS
+        );
+        assert_eq!(
+            run(&filter, 8, [1, 0, 0, 0, 0, 0]),
+            SECCOMP_RET_KILL_PROCESS
+        );
+
+        // An empty list never matches
+        let filter = build(9, SyscallRule::AnyOf(vec![]));
+        assert_eq!(run(&filter, 9, [0; 6]), SECCOMP_RET_KILL_PROCESS);
+    }
+
+    #[test]
+    fn multiple_rules() {
+        // Rules of other syscalls are evaluated after a condition block
+        let mut builder = Builder::new();
+        builder.allow_syscall_nr(
+            3,
+            SyscallRule::AllOf(vec![condition(0, SyscallArgOp::Lt, 10, None)]),
+        );
+        builder.allow_syscall_nr(4, SyscallRule::Any);
+        let filter = builder.build();
+        assert_eq!(run(&filter, 3, [9, 0, 0, 0, 0, 0]), SECCOMP_RET_ALLOW);
+        assert_eq!(
+            run(&filter, 3, [10, 0, 0, 0, 0, 0]),
+            SECCOMP_RET_KILL_PROCESS
+        );
+        assert_eq!(run(&filter, 4, [10, 0, 0, 0, 0, 0]), SECCOMP_RET_ALLOW);
+        assert_eq!(run(&filter, 5, [0; 6]), SECCOMP_RET_KILL_PROCESS);
+    }
+
+    /// True if the filter contains a jump on `nr`
+    fn allows(filter: &super::AllowList, nr: u32) -> bool {
+        filter
+            .list
+            .iter()
+            .any(|i| i.code == (BPF_JMP | BPF_JEQ | BPF_K) as u16 && i.k == nr)
+    }
+
+    #[test]
+    fn learn() {
+        let filter = Builder::new().build();
+        assert!(!filter.learn());
+        assert!(allows(&filter, bindings::SYS_execve));
+        assert_ne!(filter.list.last(), Some(&bpf_ret(SECCOMP_RET_USER_NOTIF)));
+
+        let mut builder = Builder::new();
+        builder.learn();
+        let filter = builder.build();
+        assert!(filter.learn());
+        assert!(!allows(&filter, bindings::SYS_execve));
+        assert_eq!(filter.list.last(), Some(&bpf_ret(SECCOMP_RET_USER_NOTIF)));
+    }
+
+    #[test]
+    fn learned() {
+        let seccomp = super::learned(None, [bindings::SYS_execve, u32::MAX]);
+        assert_eq!(seccomp.profile, None);
+        let allow = seccomp.allow.unwrap();
+        assert_eq!(allow.len(), 1);
+        assert_eq!(
+            allow.get(&"execve".try_into().unwrap()),
+            Some(&super::SyscallRule::Any)
+        );
+
+        // Existing rules are not widened
+        let rule = SyscallRule::Args(SyscallArgRule {
+            index: 0,
+            values: Some(vec![1]),
+            mask: None,
+        });
+        let seccomp = Seccomp {
+            allow: Some(HashMap::from([(
+                "execve".try_into().unwrap(),
+                rule.clone(),
+            )])),
+            ..Default::default()
+        };
+        let seccomp = super::learned(Some(&seccomp), [bindings::SYS_execve, bindings::SYS_read]);
+        let allow = seccomp.allow.unwrap();
+        assert_eq!(allow.get(&"execve".try_into().unwrap()), Some(&rule));
+        assert_eq!(
+            allow.get(&"read".try_into().unwrap()),
+            Some(&SyscallRule::Any)
+        );
+    }
+
+    #[test]
+    fn actions() {
+        let seccomp = Seccomp {
+            default_action: Some(Action::Trap),
+            allow: Some(HashMap::from([
+                ("read".try_into().unwrap(), SyscallRule::Any),
+                ("ptrace".try_into().unwrap(), SyscallRule::Any),
+            ])),
+            deny: Some(HashMap::from([(
+                "ptrace".try_into().unwrap(),
+                Action::Errno(1),
+            )])),
+            ..Default::default()
+        };
+        let filter = super::seccomp_filter(&seccomp, &HashSet::new(), false).unwrap();
+        assert_eq!(run(&filter, bindings::SYS_read, [0; 6]), SECCOMP_RET_ALLOW);
+        // Deny rules take precedence over allowed syscalls
+        assert_eq!(
+            run(&filter, bindings::SYS_ptrace, [0; 6]),
+            SECCOMP_RET_ERRNO | 1
+        );
+        assert_eq!(run(&filter, bindings::SYS_write, [0; 6]), SECCOMP_RET_TRAP);
+
+        // Kill the process by default
+        let seccomp = Seccomp {
+            default_action: None,
+            ..seccomp
+        };
+        let filter = super::seccomp_filter(&seccomp, &HashSet::new(), false).unwrap();
+        assert_eq!(
+            run(&filter, bindings::SYS_write, [0; 6]),
+            SECCOMP_RET_KILL_PROCESS
+        );
+
+        // Named profiles must be resolved
+        let seccomp = Seccomp {
+            profile: Some(Profile::Named("platform".try_into().unwrap())),
+            ..Default::default()
+        };
+        assert!(super::seccomp_filter(&seccomp, &HashSet::new(), false).is_err());
+    }
+
+    #[test]
+    fn merge() {
+        let profile = Seccomp {
+            profile: Some(Profile::Default),
+            default_action: Some(Action::Log),
+            allow: Some(HashMap::from([
+                ("read".try_into().unwrap(), SyscallRule::Any),
+                ("write".try_into().unwrap(), SyscallRule::Any),
+            ])),
+            deny: Some(HashMap::from([(
+                "ptrace".try_into().unwrap(),
+                Action::KillThread,
+            )])),
+        };
+        let seccomp = Seccomp {
+            profile: Some(Profile::Named("platform".try_into().unwrap())),
+            default_action: Some(Action::Errno(1)),
+            allow: Some(HashMap::from([(
+                "write".try_into().unwrap(),
+                SyscallRule::AnyOf(vec![condition(0, SyscallArgOp::Eq, 1, None)]),
+            )])),
+            deny: None,
+        };
+        let merged = seccomp.clone().merge(profile.clone());
+        assert_eq!(merged.profile, Some(Profile::Default));
+        // The stricter default action applies
+        assert_eq!(merged.default_action, Some(Action::Errno(1)));
+        // Rules of the profile are kept
+        let allow = merged.allow.unwrap();
+        assert_eq!(allow.len(), 2);
+        assert_eq!(
+            allow.get(&"write".try_into().unwrap()),
+            Some(&SyscallRule::Any)
+        );
+        assert_eq!(merged.deny.unwrap().len(), 1);
+
+        // Manifests cannot weaken deny entries or the default action of the profile
+        let seccomp = Seccomp {
+            default_action: Some(Action::Log),
+            deny: Some(HashMap::from([
+                ("ptrace".try_into().unwrap(), Action::Log),
+                ("kexec_load".try_into().unwrap(), Action::Errno(1)),
+            ])),
+            ..seccomp
+        };
+        let merged = seccomp.clone().merge(profile.clone());
+        assert_eq!(merged.default_action, Some(Action::Log));
+        let deny = merged.deny.unwrap();
+        assert_eq!(
+            deny.get(&"ptrace".try_into().unwrap()),
+            Some(&Action::KillThread)
+        );
+        assert_eq!(
+            deny.get(&"kexec_load".try_into().unwrap()),
+            Some(&Action::Errno(1))
+        );
+        let profile = Seccomp {
+            default_action: None,
+            ..profile
+        };
+        assert_eq!(seccomp.merge(profile).default_action, None);
+    }
 
     proptest! {
+        #[test]
+        fn condition_matches(op in 0..OPS.len(), arg: u64, value: u64, mask: u64) {
+            let op = OPS[op];
+            let filter = build(1, SyscallRule::AllOf(vec![condition(0, op, value, Some(mask))]));
+            let expected = if matches(op, arg, value, mask) {
+                SECCOMP_RET_ALLOW
+            } else {
+                SECCOMP_RET_KILL_PROCESS
+            };
+            prop_assert_eq!(run(&filter, 1, [arg, 0, 0, 0, 0, 0]), expected);
+        }
+
         #[test]
         fn sock_filter_serialize_deserialize(a in 0..100, b in 0i32..10) {
             let filter = SockFilter {
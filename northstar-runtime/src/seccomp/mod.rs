@@ -1,10 +1,12 @@
 // Write Berkeley Packet Filter (BPF) programs
 mod bpf;
-pub use bpf::{seccomp_filter, AllowList};
+pub use bpf::{learned, seccomp_filter, AllowList};
 
 /// Predefined seccomp profiles
 pub mod profiles;
 
 // internal types
 mod types;
-pub use types::{Profile, Seccomp, SyscallArgRule, SyscallRule};
+pub use types::{
+    Action, Profile, Seccomp, SyscallArgCondition, SyscallArgOp, SyscallArgRule, SyscallRule,
+};
@@ -1,5 +1,5 @@
-use crate::common::non_nul_string::NonNulString;
-use serde::{Deserialize, Serialize};
+use crate::common::{non_nul_string::NonNulString, ordered};
+use serde::{Deserialize, Serialize, Serializer};
 use std::collections::HashMap;
 
 /// Predefined seccomp profile
@@ -8,18 +8,125 @@ use std::collections::HashMap;
 pub enum Profile {
     /// Default seccomp filter similar to docker's default profile
     Default,
+    /// Profile loaded from the file configured for this name in the runtime configuration
+    Named(NonNulString),
+}
+
+/// Action of the seccomp filter for a syscall that is not allowed
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    /// Kill the process
+    KillProcess,
+    /// Kill the thread
+    KillThread,
+    /// Fail the syscall with the errno value
+    Errno(u16),
+    /// Send a SIGSYS to the thread
+    Trap,
+    /// Log and execute the syscall
+    Log,
+}
+
+impl Action {
+    /// Rank of the action. Higher ranks are stricter.
+    fn strictness(&self) -> u8 {
+        match self {
+            Action::Log => 0,
+            Action::Errno(_) => 1,
+            Action::Trap => 2,
+            Action::KillThread => 3,
+            Action::KillProcess => 4,
+        }
+    }
+
+    /// The stricter of `self` and `other`. `other` is returned if both are equally strict.
+    fn stricter(self, other: Action) -> Action {
+        if self.strictness() > other.strictness() {
+            self
+        } else {
+            other
+        }
+    }
 }
 
 /// Seccomp configuration
-#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+#[derive(Clone, Default, Eq, PartialEq, Debug, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct Seccomp {
     /// Pre-defined seccomp profile
     #[serde(skip_serializing_if = "Option::is_none")]
     pub profile: Option<Profile>,
-    /// Explicit list of allowed syscalls
+    /// Action for syscalls that are not allowed. Defaults to `kill_process`.
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_action: Option<Action>,
+    /// Explicit list of allowed syscalls
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_map"
+    )]
     pub allow: Option<HashMap<NonNulString, SyscallRule>>,
+    /// Syscalls that are denied with the given action. Deny rules take precedence over
+    /// allowed syscalls.
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_map"
+    )]
+    pub deny: Option<HashMap<NonNulString, Action>>,
+}
+
+impl Seccomp {
+    /// Merge this configuration into the named `profile`. The configuration can only tighten
+    /// the profile: rules and deny entries of the profile are kept and the stricter action
+    /// applies if both deny a syscall or set a default action. Syscalls the profile does not
+    /// list can be allowed or denied.
+    pub fn merge(self, profile: Seccomp) -> Seccomp {
+        let allow = match (self.allow, profile.allow) {
+            (Some(mut allow), Some(profile)) => {
+                allow.extend(profile);
+                Some(allow)
+            }
+            (allow, profile) => allow.or(profile),
+        };
+
+        let deny = match (self.deny, profile.deny) {
+            (Some(deny), Some(mut profile)) => {
+                for (syscall, action) in deny {
+                    profile
+                        .entry(syscall)
+                        .and_modify(|profile| *profile = action.stricter(*profile))
+                        .or_insert(action);
+                }
+                Some(profile)
+            }
+            (deny, profile) => deny.or(profile),
+        };
+
+        // No default action is the strictest one (kill_process)
+        let default_action = match (self.default_action, profile.default_action) {
+            (Some(action), Some(profile)) => Some(action.stricter(profile)),
+            (_, None) => None,
+            (None, profile) => profile,
+        };
+
+        Seccomp {
+            profile: profile.profile,
+            default_action,
+            allow,
+            deny,
+        }
+    }
+}
+
+/// Serialize the syscall map sorted by name
+fn serialize_map<S: Serializer, V: Serialize>(
+    map: &Option<HashMap<NonNulString, V>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match map {
+        Some(map) => ordered::map(map, serializer),
+        None => serializer.serialize_none(),
+    }
 }
 
 /// Syscall rule
@@ -30,6 +137,10 @@ pub enum SyscallRule {
     Any,
     /// Explicit list of allowed syscalls arguments
     Args(SyscallArgRule),
+    /// All argument conditions must match
+    AllOf(Vec<SyscallArgCondition>),
+    /// At least one argument condition must match
+    AnyOf(Vec<SyscallArgCondition>),
 }
 
 /// Syscall argument rule
@@ -45,3 +156,38 @@ pub struct SyscallArgRule {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub mask: Option<u64>,
 }
+
+/// Condition on a syscall argument
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SyscallArgCondition {
+    /// Index of syscall argument
+    pub index: usize,
+    /// Comparison of the argument with `value`
+    pub op: SyscallArgOp,
+    /// Value the argument is compared with
+    pub value: u64,
+    /// Mask applied to the argument before the comparison. Required for `masked_eq`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mask: Option<u64>,
+}
+
+/// Comparison of a syscall argument. Arguments are compared as unsigned 64 bit values.
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyscallArgOp {
+    /// Argument equals value
+    Eq,
+    /// Argument does not equal value
+    Ne,
+    /// Argument is less than value
+    Lt,
+    /// Argument is less than or equal to value
+    Le,
+    /// Argument is greater than value
+    Gt,
+    /// Argument is greater than or equal to value
+    Ge,
+    /// Argument and mask equals value
+    MaskedEq,
+}
@@ -0,0 +1,334 @@
+use anyhow::{anyhow, bail, Context, Result};
+use northstar_runtime::npk::{
+    manifest::Manifest,
+    npk::{self, PackOptions},
+};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+    str::FromStr,
+};
+use tempfile::tempdir;
+use toml::Value;
+
+/// Default uid and gid if not set in the package metadata
+const DEFAULT_ID: i64 = 1000;
+
+/// Options for building the package binary
+#[derive(Debug, Clone)]
+pub(crate) struct BuildOptions {
+    /// Target triple passed to cargo
+    pub target: Option<String>,
+    /// Use `cross` instead of `cargo`
+    pub cross: bool,
+    /// Do not build but use an existing binary from the target directory
+    pub no_build: bool,
+}
+
+/// Build the binary of the Cargo package in `dir` and pack it into a NPK. The manifest
+/// is derived from the package name and version and the optional `[package.metadata.northstar]`
+/// table. If `root` is set, its contents are added to the container root.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn pack(
+    dir: &Path,
+    root: Option<&Path>,
+    out: &Path,
+    key: Option<&Path>,
+    build_options: &BuildOptions,
+    options: PackOptions,
+    clones: Option<u32>,
+) -> Result<()> {
+    let cargo_toml = dir.join("Cargo.toml");
+    let cargo_toml = fs::read_to_string(&cargo_toml)
+        .with_context(|| format!("failed to read {}", cargo_toml.display()))?;
+    let metadata = Metadata::locate(dir)?;
+    let manifest = manifest(&cargo_toml, &metadata)?;
+    let binary = build(dir, &metadata, build_options)?;
+
+    let tmp = tempdir().context("failed to create temporary directory")?;
+    let container_root = tmp.path().join("root");
+    fs::create_dir(&container_root).context("failed to create root")?;
+    if let Some(root) = root {
+        npk::copy_dir(root, &container_root)?;
+    }
+
+    // Place the binary where init points to
+    let init = manifest
+        .init
+        .as_ref()
+        .ok_or_else(|| anyhow!("missing init"))?;
+    let init = container_root.join(init.trim_start_matches('/'));
+    if let Some(parent) = init.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("failed to create {}", parent.display()))?;
+    }
+    fs::copy(&binary, &init).with_context(|| format!("failed to copy {}", binary.display()))?;
+
+    let manifest_path = tmp.path().join("manifest.yaml");
+    fs::write(&manifest_path, manifest.to_string()).context("failed to write manifest")?;
+
+    super::pack::pack(&manifest_path, &container_root, out, key, options, clones)
+}
+
+/// Derive a manifest from the content of a `Cargo.toml`. Name and version are taken from the
+/// package as resolved by cargo (e.g. `version.workspace = true`) unless overwritten in
+/// `[package.metadata.northstar]`. `init` defaults to the binary name in the container root
+/// and `uid` and `gid` default to 1000.
+fn manifest(cargo_toml: &str, metadata: &Metadata) -> Result<Manifest> {
+    let cargo_toml = Value::from_str(cargo_toml).context("failed to parse Cargo.toml")?;
+    let package = cargo_toml
+        .get("package")
+        .ok_or_else(|| anyhow!("missing package section"))?;
+
+    let mut table = package
+        .get("metadata")
+        .and_then(|m| m.get("northstar"))
+        .map(|n| {
+            n.as_table()
+                .cloned()
+                .ok_or_else(|| anyhow!("package.metadata.northstar is not a table"))
+        })
+        .transpose()?
+        .unwrap_or_default();
+
+    table
+        .entry("name")
+        .or_insert_with(|| Value::String(metadata.name.clone()));
+    table
+        .entry("version")
+        .or_insert_with(|| Value::String(metadata.version.clone()));
+    table
+        .entry("init")
+        .or_insert_with(|| Value::String(format!("/{}", metadata.bin)));
+    table.entry("uid").or_insert(Value::Integer(DEFAULT_ID));
+    table.entry("gid").or_insert(Value::Integer(DEFAULT_ID));
+
+    // Round trip through the yaml representation in order to validate the manifest
+    let manifest: Manifest = Value::Table(table)
+        .try_into()
+        .context("failed to parse manifest from package metadata")?;
+    Manifest::from_str(&manifest.to_string()).context("invalid manifest")
+}
+
+/// Information about the package obtained from `cargo metadata`
+#[derive(Debug)]
+struct Metadata {
+    /// Package name
+    name: String,
+    /// Package version with inherited workspace values resolved
+    version: String,
+    /// Name of the binary target
+    bin: String,
+    /// Target directory
+    target_directory: PathBuf,
+}
+
+impl Metadata {
+    /// Query `cargo metadata` for the binary target and the target directory of the package in `dir`
+    fn locate(dir: &Path) -> Result<Metadata> {
+        let output = Command::new("cargo")
+            .arg("metadata")
+            .arg("--format-version=1")
+            .arg("--no-deps")
+            .arg("--manifest-path")
+            .arg(dir.join("Cargo.toml"))
+            .output()
+            .context("failed to execute cargo metadata")?;
+        if !output.status.success() {
+            bail!(
+                "cargo metadata failed: {}",
+                String::from_utf8_lossy(&output.stderr)
+            );
+        }
+        let metadata: serde_json::Value =
+            serde_json::from_slice(&output.stdout).context("failed to parse cargo metadata")?;
+
+        let target_directory = metadata["target_directory"]
+            .as_str()
+            .map(PathBuf::from)
+            .ok_or_else(|| anyhow!("missing target directory in cargo metadata"))?;
+
+        // Find the package that lives in `dir`
+        let manifest_path = fs::canonicalize(dir.join("Cargo.toml"))
+            .context("failed to canonicalize manifest path")?;
+        let package = metadata["packages"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .find(|p| {
+                p["manifest_path"]
+                    .as_str()
+                    .map(|m| Path::new(m) == manifest_path)
+                    .unwrap_or(false)
+            })
+            .ok_or_else(|| anyhow!("failed to find package in {}", dir.display()))?;
+
+        let bins = package["targets"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter(|t| {
+                t["kind"]
+                    .as_array()
+                    .map(|k| k.iter().any(|k| k == "bin"))
+                    .unwrap_or(false)
+            })
+            .filter_map(|t| t["name"].as_str())
+            .collect::<Vec<_>>();
+
+        let bin = match bins.as_slice() {
+            [] => bail!("package {} has no binary target", dir.display()),
+            [bin] => bin.to_string(),
+            _ => {
+                // Prefer the binary named like the package if there are multiple
+                let name = package["name"].as_str().unwrap_or_default();
+                bins.iter()
+                    .find(|b| **b == name)
+                    .map(ToString::to_string)
+                    .ok_or_else(|| {
+                        anyhow!("package {} has multiple binary targets", dir.display())
+                    })?
+            }
+        };
+
+        let name = package["name"]
+            .as_str()
+            .ok_or_else(|| anyhow!("missing package name in cargo metadata"))?
+            .to_string();
+        let version = package["version"]
+            .as_str()
+            .ok_or_else(|| anyhow!("missing package version in cargo metadata"))?
+            .to_string();
+
+        Ok(Metadata {
+            name,
+            version,
+            bin,
+            target_directory,
+        })
+    }
+}
+
+/// Build the binary in release mode and return its path
+fn build(dir: &Path, metadata: &Metadata, options: &BuildOptions) -> Result<PathBuf> {
+    if !options.no_build {
+        let cargo = if options.cross { "cross" } else { "cargo" };
+        let mut command = Command::new(cargo);
+        command
+            .arg("build")
+            .arg("--release")
+            .arg("--manifest-path")
+            .arg(dir.join("Cargo.toml"))
+            .arg("--bin")
+            .arg(&metadata.bin);
+        if let Some(target) = &options.target {
+            command.arg("--target").arg(target);
+        }
+        let status = command
+            .status()
+            .with_context(|| format!("failed to execute {}", cargo))?;
+        if !status.success() {
+            bail!("failed to build {}: {}", metadata.bin, status);
+        }
+    }
+
+    let mut binary = metadata.target_directory.clone();
+    if let Some(target) = &options.target {
+        binary.push(target);
+    }
+    binary.push("release");
+    binary.push(&metadata.bin);
+
+    if !binary.is_file() {
+        bail!("failed to locate binary {}", binary.display());
+    }
+    Ok(binary)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{manifest, Metadata};
+    use anyhow::Result;
+
+    fn metadata(name: &str, version: &str) -> Metadata {
+        Metadata {
+            name: name.into(),
+            version: version.into(),
+            bin: name.into(),
+            target_directory: "target".into(),
+        }
+    }
+
+    #[test]
+    fn manifest_from_package() -> Result<()> {
+        let cargo_toml = r#"
+[package]
+name = "hello-world"
+version = "0.0.1"
+edition = "2021"
+"#;
+        let manifest = manifest(cargo_toml, &metadata("hello-world", "0.0.1"))?;
+        assert_eq!(manifest.name.to_string(), "hello-world");
+        assert_eq!(manifest.version.to_string(), "0.0.1");
+        assert_eq!(manifest.init.as_deref(), Some("/hello-world"));
+        assert_eq!(manifest.uid, 1000);
+        assert_eq!(manifest.gid, 1000);
+        Ok(())
+    }
+
+    #[test]
+    fn manifest_from_metadata() -> Result<()> {
+        let cargo_toml = r#"
+[package]
+name = "hello-world"
+version = "0.0.1"
+edition = "2021"
+
+[package.metadata.northstar]
+name = "hello"
+init = "/bin/hello"
+uid = 1001
+gid = 1002
+args = ["--verbose"]
+
+[package.metadata.northstar.mounts."/proc"]
+type = "proc"
+"#;
+        let manifest = manifest(cargo_toml, &metadata("hello-world", "0.0.1"))?;
+        assert_eq!(manifest.name.to_string(), "hello");
+        assert_eq!(manifest.init.as_deref(), Some("/bin/hello"));
+        assert_eq!(manifest.uid, 1001);
+        assert_eq!(manifest.gid, 1002);
+        assert_eq!(manifest.args.len(), 1);
+        assert_eq!(manifest.mounts.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn manifest_inherited_version() -> Result<()> {
+        let cargo_toml = r#"
+[package]
+name = "hello-world"
+version.workspace = true
+edition.workspace = true
+"#;
+        let manifest = manifest(cargo_toml, &metadata("hello-world", "0.2.0"))?;
+        assert_eq!(manifest.name.to_string(), "hello-world");
+        assert_eq!(manifest.version.to_string(), "0.2.0");
+        Ok(())
+    }
+
+    #[test]
+    fn manifest_unknown_field() {
+        let cargo_toml = r#"
+[package]
+name = "hello-world"
+version = "0.0.1"
+
+[package.metadata.northstar]
+foo = "bar"
+"#;
+        assert!(manifest(cargo_toml, &metadata("hello-world", "0.0.1")).is_err());
+    }
+}
@@ -8,7 +8,7 @@ use std::{
 use anyhow::{Context, Result};
 use colored::Colorize;
 
-use northstar_runtime::npk::npk::{Npk, FS_IMG_NAME, MANIFEST_NAME, SIGNATURE_NAME};
+use northstar_runtime::npk::npk::{FsType, Npk, FS_IMG_NAME, MANIFEST_NAME, SIGNATURE_NAME};
 use zip::ZipArchive;
 
 pub(crate) fn inspect(npk: &Path, short: bool, unsquashfs: &Path) -> Result<()> {
@@ -42,6 +42,7 @@ fn open(path: &Path) -> Result<ZipArchive<BufReader<std::fs::File>>> {
 }
 
 pub(crate) fn inspect_long(npk: &Path, unsquashfs: &Path) -> Result<()> {
+    let fs = Npk::<BufReader<File>>::from_path(npk, None)?.meta().fs;
     let mut zip = open(npk)?;
     let mut print_buf: String = String::new();
     println!(
@@ -78,6 +79,12 @@ pub(crate) fn inspect_long(npk: &Path, unsquashfs: &Path) -> Result<()> {
         _ => println!("No signature found"),
     }
 
+    // The file listing is only available for squashfs images
+    if fs != FsType::Squashfs {
+        println!("{}", format!("## {} image", fs).green());
+        return Ok(());
+    }
+
     // print squashfs listing
     println!("{}", "## SquashFS listing".green());
     let mut dest_fsimage = tempfile::NamedTempFile::new().context("failed to create tmp file")?;
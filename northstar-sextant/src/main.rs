@@ -7,10 +7,14 @@ use anyhow::Result;
 use clap::Parser;
 use northstar_runtime::npk::{
     self,
-    npk::{CompressionAlgorithm, SquashfsOptions},
+    npk::{
+        CompressionAlgorithm, ErofsCompressionAlgorithm, ErofsOptions, FsOptions, FsType,
+        PackOptions, SquashfsOptions,
+    },
 };
 use std::path::PathBuf;
 
+mod cargo;
 mod inspect;
 mod pack;
 
@@ -21,29 +25,55 @@ enum Opt {
     /// Pack Northstar containers
     Pack {
         /// Manifest path
-        #[clap(short, long)]
-        manifest: PathBuf,
-        /// Container source directory
-        #[clap(short, long)]
-        root: PathBuf,
+        #[clap(short, long, required_unless_present = "cargo")]
+        manifest: Option<PathBuf>,
+        /// Container source directory. Added to the root of the binary if used with --cargo
+        #[clap(short, long, required_unless_present = "cargo")]
+        root: Option<PathBuf>,
+        /// Cargo package directory. Build the package binary and derive the manifest from
+        /// the package and the optional `[package.metadata.northstar]` section
+        #[clap(long, conflicts_with = "manifest")]
+        cargo: Option<PathBuf>,
+        /// Target triple used to build the Cargo package
+        #[clap(long, requires = "cargo")]
+        target: Option<String>,
+        /// Build the Cargo package with cross instead of cargo
+        #[clap(long, requires = "cargo")]
+        cross: bool,
+        /// Do not build the Cargo package but use an existing release binary
+        #[clap(long, requires = "cargo")]
+        no_build: bool,
         /// Key file
         #[clap(short, long)]
         key: Option<PathBuf>,
         /// Output directory
         #[clap(short, long)]
         out: PathBuf,
+        /// Filesystem type of the image: squashfs or erofs
+        #[clap(long, default_value = "squashfs")]
+        fs: FsType,
         /// Compression algorithm to use in squashfs (default gzip)
         #[clap(short, long, default_value = "gzip")]
         compression_algorithm: CompressionAlgorithm,
         /// mksqushfs binary
         #[clap(long, default_value = "mksquashfs")]
         mksquashfs: PathBuf,
+        /// Compression algorithm to use in erofs: lz4, lz4hc or lzma (default uncompressed)
+        #[clap(long)]
+        erofs_compression_algorithm: Option<ErofsCompressionAlgorithm>,
+        /// mkfs.erofs binary
+        #[clap(long, default_value = "mkfs.erofs")]
+        mkfs_erofs: PathBuf,
         /// Block size used by squashfs (default 128 KiB)
         #[clap(short, long)]
         block_size: Option<u32>,
         /// Create n clones of the container
         #[clap(long)]
         clones: Option<u32>,
+        /// Pack reproducible: normalize timestamps (SOURCE_DATE_EPOCH or 0), ownership and
+        /// archive metadata. Fails if the root contains extended attributes.
+        #[clap(long)]
+        reproducible: bool,
     },
     /// Unpack Northstar containers
     Unpack {
@@ -56,6 +86,9 @@ enum Opt {
         /// unsquashfs binary
         #[clap(long, default_value = "unsquashfs")]
         unsquashfs: PathBuf,
+        /// fsck.erofs binary
+        #[clap(long, default_value = "fsck.erofs")]
+        fsck_erofs: PathBuf,
     },
     /// Print information about a Northstar container
     Inspect {
@@ -84,29 +117,59 @@ fn main() -> Result<()> {
         Opt::Pack {
             manifest,
             root,
+            cargo,
+            target,
+            cross,
+            no_build,
             out,
             key,
+            fs,
             compression_algorithm,
             mksquashfs,
+            erofs_compression_algorithm,
+            mkfs_erofs,
             block_size,
             clones,
-        } => pack::pack(
-            &manifest,
-            &root,
-            &out,
-            key.as_deref(),
-            SquashfsOptions {
-                compression_algorithm,
-                mksquashfs,
-                block_size,
-            },
-            clones,
-        )?,
+            reproducible,
+        } => {
+            let fs = match fs {
+                FsType::Squashfs => FsOptions::Squashfs(SquashfsOptions {
+                    compression_algorithm,
+                    mksquashfs,
+                    block_size,
+                }),
+                FsType::Erofs => FsOptions::Erofs(ErofsOptions {
+                    mkfs_erofs,
+                    compression_algorithm: erofs_compression_algorithm,
+                }),
+            };
+            let options = PackOptions { fs, reproducible };
+            match (cargo, manifest, root) {
+                (Some(cargo), _, root) => cargo::pack(
+                    &cargo,
+                    root.as_deref(),
+                    &out,
+                    key.as_deref(),
+                    &cargo::BuildOptions {
+                        target,
+                        cross,
+                        no_build,
+                    },
+                    options,
+                    clones,
+                )?,
+                (None, Some(manifest), Some(root)) => {
+                    pack::pack(&manifest, &root, &out, key.as_deref(), options, clones)?
+                }
+                _ => unreachable!("manifest and root are required without cargo"),
+            }
+        }
         Opt::Unpack {
             npk,
             out,
             unsquashfs,
-        } => npk::npk::unpack_with(&npk, &out, &unsquashfs)?,
+            fsck_erofs,
+        } => npk::npk::unpack_with(&npk, &out, &unsquashfs, &fsck_erofs)?,
         Opt::Inspect {
             npk,
             short,
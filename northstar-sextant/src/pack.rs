@@ -1,9 +1,9 @@
 use anyhow::{Context, Result};
 use northstar_runtime::npk::{
-    manifest::Manifest,
-    npk::{pack_with, SquashfsOptions},
+    manifest::{arch::Arch, Manifest},
+    npk::{pack_with, PackOptions},
 };
-use std::{convert::TryInto, fs, path::Path};
+use std::{convert::TryInto, fs, io::Read, path::Path};
 use tempfile::tempdir;
 
 #[allow(clippy::too_many_arguments)]
@@ -12,18 +12,23 @@ pub(crate) fn pack(
     root: &Path,
     out: &Path,
     key: Option<&Path>,
-    squashfs_options: SquashfsOptions,
+    options: PackOptions,
     clones: Option<u32>,
 ) -> Result<()> {
-    // Create npk clones with the number appended to the name
-    if let Some(clones) = clones {
-        let manifest_file = manifest;
-        let reader = fs::File::open(&manifest_file).context("failed to open manifest")?;
-        let mut manifest = Manifest::from_reader(reader).context("failed to read manifest")?;
-
-        // Only clone non-resource containers
-        if manifest.init.is_some() {
-            let tmp = tempdir().context("failed to create temporary directory")?;
+    let reader = fs::File::open(manifest).context("failed to open manifest")?;
+    let mut manifest = Manifest::from_reader(reader).context("failed to read manifest")?;
+
+    // Fill the architecture from the init binary if not set in the manifest
+    if manifest.arch.is_none() {
+        if let Some(init) = &manifest.init {
+            manifest.arch = elf_arch(&root.join(init.trim_start_matches('/')));
+        }
+    }
+
+    let tmp = tempdir().context("failed to create temporary directory")?;
+    match clones {
+        // Create npk clones with the number appended to the name. Only clone non-resource containers
+        Some(clones) if manifest.init.is_some() => {
             let name = manifest.name.clone();
             let num = clones.to_string().chars().count();
             for n in 0..clones {
@@ -32,14 +37,84 @@ pub(crate) fn pack(
                     .context("failed to parse name")?;
                 let m = tmp.path().join(n.to_string());
                 fs::write(&m, manifest.to_string()).context("failed to write manifest")?;
-                pack_with(&m, root, out, key, squashfs_options.clone())?;
+                pack_with(&m, root, out, key, options.clone())?;
             }
-        } else {
-            pack_with(manifest_file, root, out, key, squashfs_options)?;
         }
-    } else {
-        pack_with(manifest, root, out, key, squashfs_options)?;
+        _ => {
+            let m = tmp.path().join("manifest.yaml");
+            fs::write(&m, manifest.to_string()).context("failed to write manifest")?;
+            pack_with(&m, root, out, key, options)?;
+        }
     }
 
     Ok(())
 }
+
+/// Determine the architecture of the ELF file at `path`. Returns `None` if the file
+/// cannot be read, is not an ELF file (e.g. a script) or the architecture is unknown.
+fn elf_arch(path: &Path) -> Option<Arch> {
+    let mut header = [0u8; 20];
+    fs::File::open(path).ok()?.read_exact(&mut header).ok()?;
+    if &header[0..4] != b"\x7fELF" {
+        return None;
+    }
+
+    // EI_CLASS: 1 = 32 bit, 2 = 64 bit. EI_DATA: 1 = little endian, 2 = big endian.
+    let class64 = header[4] == 2;
+    let machine = [header[18], header[19]];
+    let machine = match header[5] {
+        2 => u16::from_be_bytes(machine),
+        _ => u16::from_le_bytes(machine),
+    };
+
+    Arch::from_elf(machine, class64)
+}
+
+#[cfg(test)]
+mod test {
+    use super::elf_arch;
+    use anyhow::Result;
+    use northstar_runtime::npk::manifest::arch::Arch;
+    use std::fs;
+    use tempfile::tempdir;
+
+    /// Minimal ELF identification and header prefix up to e_machine
+    fn header(class: u8, data: u8, machine: u16) -> Vec<u8> {
+        let mut header = vec![0x7f, b'E', b'L', b'F', class, data, 1];
+        header.resize(16, 0);
+        header.extend_from_slice(&2u16.to_le_bytes()); // e_type
+        if data == 2 {
+            header.extend_from_slice(&machine.to_be_bytes());
+        } else {
+            header.extend_from_slice(&machine.to_le_bytes());
+        }
+        header.resize(64, 0);
+        header
+    }
+
+    #[test]
+    fn arch_from_elf() -> Result<()> {
+        let tmp = tempdir()?;
+        let file = tmp.path().join("init");
+
+        fs::write(&file, header(2, 1, 62))?;
+        assert_eq!(elf_arch(&file), Some(Arch::X86_64));
+        fs::write(&file, header(2, 1, 183))?;
+        assert_eq!(elf_arch(&file), Some(Arch::Aarch64));
+        fs::write(&file, header(1, 1, 40))?;
+        assert_eq!(elf_arch(&file), Some(Arch::Arm));
+        fs::write(&file, header(2, 2, 22))?;
+        assert_eq!(elf_arch(&file), Some(Arch::S390x));
+        Ok(())
+    }
+
+    #[test]
+    fn arch_from_non_elf() -> Result<()> {
+        let tmp = tempdir()?;
+        let file = tmp.path().join("init");
+        fs::write(&file, "#!/bin/sh\necho hello\n")?;
+        assert_eq!(elf_arch(&file), None);
+        assert_eq!(elf_arch(&tmp.path().join("missing")), None);
+        Ok(())
+    }
+}
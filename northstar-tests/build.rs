@@ -1,5 +1,6 @@
 use escargot::CargoBuild;
 use rayon::prelude::*;
+use serde_yaml::Value;
 use std::{
     env, fs,
     path::{Path, PathBuf},
@@ -77,24 +78,73 @@ fn main() {
             }
         };
 
-        Command::new(&sextant)
-            .arg("pack")
-            .arg("-o")
-            .arg(out_dir)
-            .arg("-m")
-            .arg(&northstar_manifest)
-            .args(["-k", KEY])
-            .arg("-r")
-            .arg(root)
-            .spawn()
-            .unwrap_or_else(|_| panic!("failed to spawn sextant for {}", npk.display()))
-            .wait()
-            .expect("failed to pack");
+        // Additional manifests named manifest-*.yaml are overrides of manifest.yaml and
+        // are packed with the same root
+        let variants = fs::read_dir(npk)
+            .expect("failed to read npk dir")
+            .map(|entry| entry.expect("failed to read dir entry").path())
+            .filter(|path| {
+                let name = path.file_name().unwrap().to_str().unwrap();
+                name.starts_with("manifest-") && name.ends_with(".yaml")
+            })
+            .map(|path| {
+                let manifest = tmpdir.path().join(format!(
+                    "{}-{}",
+                    binary_name,
+                    path.file_name().unwrap().to_str().unwrap()
+                ));
+                let mut base = read_yaml(&northstar_manifest);
+                merge(&mut base, read_yaml(&path));
+                let base = serde_yaml::to_string(&base).expect("failed to serialize manifest");
+                fs::write(&manifest, base).expect("failed to write manifest");
+                manifest
+            })
+            .collect::<Vec<_>>();
+
+        for manifest in std::iter::once(northstar_manifest).chain(variants) {
+            Command::new(&sextant)
+                .arg("pack")
+                .arg("-o")
+                .arg(out_dir)
+                .arg("-m")
+                .arg(&manifest)
+                .args(["-k", KEY])
+                .arg("-r")
+                .arg(&root)
+                .spawn()
+                .unwrap_or_else(|_| panic!("failed to spawn sextant for {}", npk.display()))
+                .wait()
+                .expect("failed to pack");
+        }
     });
 
     tmpdir.close().expect("failed to remove tmpdir");
 }
 
+fn read_yaml(path: &Path) -> Value {
+    let file = fs::File::open(path).unwrap_or_else(|_| panic!("failed to open {}", path.display()));
+    serde_yaml::from_reader(file).unwrap_or_else(|_| panic!("failed to parse {}", path.display()))
+}
+
+/// Merge `patch` into `manifest`. Mappings are merged recursively, null values remove
+/// the key and all other values replace the value in `manifest`.
+fn merge(manifest: &mut Value, patch: Value) {
+    match (manifest, patch) {
+        (Value::Mapping(manifest), Value::Mapping(patch)) => {
+            for (key, value) in patch {
+                if value.is_null() {
+                    manifest.remove(&key);
+                } else if let Some(existing) = manifest.get_mut(&key) {
+                    merge(existing, value);
+                } else {
+                    manifest.insert(key, value);
+                }
+            }
+        }
+        (manifest, patch) => *manifest = patch,
+    }
+}
+
 /// Find a sextant binary in the current target directory tree or built is if not present.
 fn find_or_build_sextant() -> PathBuf {
     println!("cargo:rerun-if-env-changed={}", ENV_FORCE_BUILD);
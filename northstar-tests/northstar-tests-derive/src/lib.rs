@@ -3,7 +3,7 @@ use proc_macro2::Span;
 use quote::{quote, quote_spanned, ToTokens};
 
 #[proc_macro_attribute]
-pub fn runtime_test(_args: TokenStream, mut item: TokenStream) -> TokenStream {
+pub fn runtime_test(args: TokenStream, mut item: TokenStream) -> TokenStream {
     // If any of the steps for this macro fail, we still want to expand to an item that is as close
     // to the expected output as possible. This helps out IDEs such that completions and other
     // related features keep working.
@@ -17,6 +17,19 @@ pub fn runtime_test(_args: TokenStream, mut item: TokenStream) -> TokenStream {
 
     input.sig.asyncness = None;
 
+    // Optional path of a function that adjusts the runtime configuration of the test
+    let configure = if args.is_empty() {
+        quote!(|_| ())
+    } else {
+        match syn::parse::<syn::Path>(args) {
+            Ok(path) => path.into_token_stream(),
+            Err(e) => {
+                item.extend(TokenStream::from(e.into_compile_error()));
+                return item;
+            }
+        }
+    };
+
     // If type mismatch occurs, the current rustc points to the last statement.
     let (_, last_stmt_end_span) = {
         let mut last_stmt = input
@@ -77,7 +90,7 @@ pub fn runtime_test(_args: TokenStream, mut item: TokenStream) -> TokenStream {
 
         // Initialize the runtime. The part without the Tokio runtime.
         log::debug!("Starting runtime");
-        let runtime = northstar_tests::runtime::Runtime::new().expect("failed to start runtime");
+        let runtime = northstar_tests::runtime::Runtime::new(#configure).expect("failed to start runtime");
 
         // The test code within the async context
         let body = async {
@@ -16,6 +16,12 @@ pub const EXAMPLE_SECCOMP: &str = "seccomp:0.0.1";
 pub const EXAMPLE_TOKEN_CLIENT: &str = "token-client:0.0.1";
 pub const EXAMPLE_TOKEN_SERVER: &str = "token-server:0.0.1";
 pub const TEST_CONTAINER: &str = "test-container:0.0.1";
+pub const TEST_CONTAINER_BRIDGE: &str = "test-container-bridge:0.0.1";
+pub const TEST_CONTAINER_CGROUP: &str = "test-container-cgroup:0.0.1";
+pub const TEST_CONTAINER_CWD_UMASK: &str = "test-container-cwd-umask:0.0.1";
+pub const TEST_CONTAINER_NO_PIDNS: &str = "test-container-no-pidns:0.0.1";
+pub const TEST_CONTAINER_SELINUX: &str = "test-container-selinux:0.0.1";
+pub const TEST_CONTAINER_USERNS: &str = "test-container-userns:0.0.1";
 pub const TEST_RESOURCE: &str = "test-resource:0.0.1";
 
 pub static EXAMPLE_CONSOLE_NPK: &[u8] =
@@ -52,5 +58,23 @@ pub static EXAMPLE_TOKEN_SERVER_NPK: &[u8] =
     include_bytes!(concat!(env!("OUT_DIR"), "/token-server-0.0.1.npk"));
 pub static TEST_CONTAINER_NPK: &[u8] =
     include_bytes!(concat!(env!("OUT_DIR"), "/test-container-0.0.1.npk"));
+pub static TEST_CONTAINER_BRIDGE_NPK: &[u8] =
+    include_bytes!(concat!(env!("OUT_DIR"), "/test-container-bridge-0.0.1.npk"));
+pub static TEST_CONTAINER_CGROUP_NPK: &[u8] =
+    include_bytes!(concat!(env!("OUT_DIR"), "/test-container-cgroup-0.0.1.npk"));
+pub static TEST_CONTAINER_CWD_UMASK_NPK: &[u8] = include_bytes!(concat!(
+    env!("OUT_DIR"),
+    "/test-container-cwd-umask-0.0.1.npk"
+));
+pub static TEST_CONTAINER_NO_PIDNS_NPK: &[u8] = include_bytes!(concat!(
+    env!("OUT_DIR"),
+    "/test-container-no-pidns-0.0.1.npk"
+));
+pub static TEST_CONTAINER_SELINUX_NPK: &[u8] = include_bytes!(concat!(
+    env!("OUT_DIR"),
+    "/test-container-selinux-0.0.1.npk"
+));
+pub static TEST_CONTAINER_USERNS_NPK: &[u8] =
+    include_bytes!(concat!(env!("OUT_DIR"), "/test-container-userns-0.0.1.npk"));
 pub static TEST_RESOURCE_NPK: &[u8] =
     include_bytes!(concat!(env!("OUT_DIR"), "/test-resource-0.0.1.npk"));
@@ -12,7 +12,10 @@ use northstar_runtime::{
         Runtime as Northstar,
     },
 };
-use std::convert::{TryFrom, TryInto};
+use std::{
+    collections::HashMap,
+    convert::{TryFrom, TryInto},
+};
 use tempfile::TempDir;
 use tokio::{fs::remove_file, net::UnixStream, pin, select, time};
 
@@ -32,8 +35,16 @@ pub enum Runtime {
     Started(Northstar, TempDir),
 }
 
+/// Enable seccomp learn mode in the runtime configuration
+pub fn seccomp_learn(config: &mut config::Config) {
+    if let Some(debug) = config.debug.as_mut() {
+        debug.seccomp = Some(config::debug::Seccomp { learn: true });
+    }
+}
+
 impl Runtime {
-    pub fn new() -> Result<Runtime> {
+    /// Create a runtime with the test configuration adjusted by `configure`
+    pub fn new(configure: impl FnOnce(&mut config::Config)) -> Result<Runtime> {
         let tmpdir = tempfile::Builder::new().prefix("northstar-").tempdir()?;
         let run_dir = tmpdir.path().join("run");
         std::fs::create_dir(&run_dir)?;
@@ -68,7 +79,7 @@ impl Runtime {
         ]
         .into();
 
-        let config = config::Config {
+        let mut config = config::Config {
             run_dir,
             data_dir,
             log_dir,
@@ -77,14 +88,32 @@ impl Runtime {
             device_mapper_device_timeout: time::Duration::from_secs(10),
             loop_device_timeout: time::Duration::from_secs(10),
             token_validity: time::Duration::from_secs(60),
+            stop_timeout: time::Duration::from_secs(5),
             cgroup: NonNulString::try_from(format!("northstar-{}", nanoid!())).unwrap(),
             repositories,
+            user_namespace: Some(config::UserNamespace {
+                uid: 100000,
+                gid: 100000,
+                count: 65536 * 4,
+                size: 65536,
+            }),
+            bridge: Some(config::Bridge {
+                name: "northstar0".into(),
+                subnet: "172.30.0.0/24".parse().unwrap(),
+            }),
+            seccomp_profiles: HashMap::new(),
+            persist: config::Persist::default(),
+            volumes: HashMap::new(),
+            syslog: config::Syslog::default(),
+            log: config::Log::default(),
             debug: Some(config::Debug {
                 console: console_url(),
                 strace: None,
                 perf: None,
+                seccomp: None,
             }),
         };
+        configure(&mut config);
         let runtime = Northstar::new(config)?;
 
         Ok(Runtime::Created(runtime, tmpdir))
@@ -2,6 +2,7 @@ use anyhow::{Context, Result};
 use clap::Parser;
 use nix::{
     libc,
+    sys::stat,
     unistd::{self, Gid},
 };
 use std::{
@@ -43,10 +44,14 @@ enum Command {
         path: PathBuf,
     },
     Crash,
+    Ps,
     Exit {
         code: i32,
     },
     Inspect,
+    Mkdir {
+        path: PathBuf,
+    },
     Print {
         message: String,
         #[clap(short, long, parse(from_str), default_value = "stdout")]
@@ -74,7 +79,9 @@ fn main() -> Result<()> {
         Command::Crash => crash(),
         Command::Exit { code } => exit(code),
         Command::Inspect => inspect(),
+        Command::Mkdir { path } => mkdir(&path)?,
         Command::Print { message, io } => print(&message, &io),
+        Command::Ps => ps()?,
         Command::Sleep => (),
         Command::Touch { path } => touch(&path)?,
         Command::Write { message, path } => write(&message, path.as_path())?,
@@ -108,6 +115,18 @@ fn cat(path: &Path) -> Result<()> {
     writeln!(output).context("failed to write to stdout")
 }
 
+/// Print the pids visible in /proc
+fn ps() -> Result<()> {
+    let mut pids = fs::read_dir("/proc")
+        .context("failed to read /proc")?
+        .filter_map(|e| e.ok()?.file_name().to_str()?.parse::<u32>().ok())
+        .collect::<Vec<_>>();
+    pids.sort_unstable();
+    println!("processes: {}", pids.len());
+    println!("pids: {:?}", pids);
+    Ok(())
+}
+
 fn crash() {
     panic!("witness me!");
 }
@@ -128,6 +147,10 @@ fn write(input: &str, path: &Path) -> Result<()> {
         .with_context(|| format!("failed to write \"{}\" to {}", input, path.display()))
 }
 
+fn mkdir(path: &Path) -> Result<()> {
+    fs::create_dir(path).with_context(|| format!("failed to create {}", path.display()))
+}
+
 fn touch(path: &Path) -> Result<()> {
     fs::File::create(path)?;
     Ok(())
@@ -167,6 +190,14 @@ fn inspect() {
         env::current_exe().expect("current_exe").display()
     );
 
+    // Read the umask by setting and restoring it
+    let umask = stat::umask(stat::Mode::empty());
+    stat::umask(umask);
+    println!("umask: {:04o}", umask.bits());
+    println!("no_new_privs: {}", unsafe {
+        libc::prctl(libc::PR_GET_NO_NEW_PRIVS, 0, 0, 0, 0)
+    });
+
     for set in &[
         caps::CapSet::Ambient,
         caps::CapSet::Bounding,
@@ -105,7 +105,14 @@ async fn too_long_line() -> Result<()> {
     let container = Container::try_from("hello-world:0.0.1").unwrap();
     let containers = iter::repeat(container).take(100000).collect();
 
-    match client.request(model::Request::Mount { containers }).await {
+    let selector = None;
+    match client
+        .request(model::Request::Mount {
+            containers,
+            selector,
+        })
+        .await
+    {
         Ok(_) => panic!("expected IO error"),
         Err(_) => Ok(()),
     }
@@ -1,7 +1,12 @@
+use nix::sys::{
+    stat::utimes,
+    time::{TimeVal, TimeValLike},
+};
 use northstar_runtime::npk::npk;
 use std::{
     fs::{self, File},
-    io::Write,
+    io::{BufReader, Write},
+    os::unix::fs::{FileTypeExt, MetadataExt},
     path::{Path, PathBuf},
 };
 use tempfile::TempDir;
@@ -68,6 +73,17 @@ fn pack_with_manifest() {
     create(dest.path(), Some("different_manifest_name"));
 }
 
+// Squashfs NPKs keep the format version that runtimes without EROFS support accept
+#[test]
+fn pack_squashfs_version() {
+    let dest = tmpdir();
+    create(dest.path(), None);
+    let npk = npk::Npk::<BufReader<File>>::from_path(&dest.path().join(TEST_CONTAINER_NAME), None)
+        .expect("failed to read NPK");
+    assert_eq!(npk.meta().fs, npk::FsType::Squashfs);
+    assert_eq!(npk.meta().version, northstar_runtime::npk::VERSION_MIN);
+}
+
 #[test]
 fn pack_missing_manifest() {
     let src = tmpdir();
@@ -94,6 +110,50 @@ fn pack_invalid_key() {
     npk::pack(&manifest, src.path(), dest.path(), Some(private)).expect_err("invalid key dir");
 }
 
+#[test]
+fn pack_reproducible() {
+    let src = tmpdir();
+    let key_dir = tmpdir();
+    let manifest = create_test_manifest(src.path(), None);
+    fs::write(src.path().join("hello"), "hello").expect("failed to write file");
+    let (_, prv_key) = generate_test_key(key_dir.path());
+
+    let pack = || {
+        let dest = tmpdir();
+        let npk = dest.path().join(TEST_CONTAINER_NAME);
+        npk::pack_with(
+            &manifest,
+            src.path(),
+            &npk,
+            Some(&prv_key),
+            npk::PackOptions {
+                reproducible: true,
+                ..Default::default()
+            },
+        )
+        .expect("Pack NPK");
+        fs::read(&npk).expect("failed to read npk")
+    };
+
+    // Set distinct timestamps of the inputs before each pack
+    let touch = |seconds| {
+        let time = TimeVal::seconds(seconds);
+        for path in [
+            src.path().join("hello"),
+            manifest.clone(),
+            src.path().into(),
+        ] {
+            utimes(&path, &time, &time).expect("failed to set timestamps");
+        }
+    };
+
+    touch(1_000_000);
+    let first = pack();
+    touch(2_000_000);
+    let second = pack();
+    assert!(first == second, "npks differ");
+}
+
 #[test]
 fn unpack() {
     let npk_dest = tmpdir();
@@ -109,6 +169,121 @@ fn unpack() {
     assert_eq!(TEST_MANIFEST_UNPACKED, manifest);
 }
 
+#[test]
+fn pack_unpack_erofs() {
+    let src = tmpdir();
+    let manifest = create_test_manifest(src.path(), None);
+    fs::write(src.path().join("hello"), "hello").expect("failed to write file");
+    let npk_dest = tmpdir();
+    let npk = npk_dest.path().join(TEST_CONTAINER_NAME);
+    npk::pack_with(
+        &manifest,
+        src.path(),
+        &npk,
+        None,
+        npk::FsOptions::Erofs(npk::ErofsOptions::default()).into(),
+    )
+    .expect("Pack NPK");
+
+    let npk_file = npk::Npk::<BufReader<File>>::from_path(&npk, None).expect("failed to read NPK");
+    assert_eq!(npk_file.meta().fs, npk::FsType::Erofs);
+    assert_eq!(npk_file.meta().version, northstar_runtime::npk::VERSION);
+
+    let unpack_dest = tmpdir();
+    npk::unpack(&npk, unpack_dest.path()).expect("Unpack NPK");
+    let manifest = fs::read_to_string(unpack_dest.path().join("manifest.yaml"))
+        .expect("failed to read manifest");
+    assert_eq!(TEST_MANIFEST_UNPACKED, manifest);
+    let hello = fs::read_to_string(unpack_dest.path().join("erofs-root").join("hello"))
+        .expect("failed to read file from image");
+    assert_eq!(hello, "hello");
+}
+
+#[test]
+fn pack_unpack_erofs_device_modes() {
+    // Creating device nodes requires CAP_MKNOD
+    if !nix::unistd::geteuid().is_root() {
+        return;
+    }
+
+    let src = tmpdir();
+    let manifest = src.path().join("manifest.yaml");
+    fs::write(
+        &manifest,
+        "name: hello
+version: 0.0.2
+init: /hello
+uid: 100
+gid: 1
+mounts:
+  /dev:
+    type: dev
+devices:
+  - path: /dev/ttyS1
+    type: char
+    major: 4
+    minor: 65
+    permissions: r
+  - path: /dev/loop9
+    type: block
+    major: 7
+    minor: 9
+",
+    )
+    .expect("failed to write manifest");
+    fs::write(src.path().join("hello"), "hello").expect("failed to write file");
+    let npk_dest = tmpdir();
+    let npk = npk_dest.path().join(TEST_CONTAINER_NAME);
+    npk::pack_with(
+        &manifest,
+        src.path(),
+        &npk,
+        None,
+        npk::FsOptions::Erofs(npk::ErofsOptions::default()).into(),
+    )
+    .expect("Pack NPK");
+
+    let unpack_dest = tmpdir();
+    npk::unpack(&npk, unpack_dest.path()).expect("Unpack NPK");
+    let dev = unpack_dest.path().join("erofs-root").join("dev");
+    for (node, mode) in [
+        ("null", 0o666),
+        ("zero", 0o666),
+        ("tty", 0o666),
+        ("ttyS1", 0o440),
+        ("loop9", 0o660),
+    ] {
+        let metadata = fs::symlink_metadata(dev.join(node)).expect("failed to stat device node");
+        assert_eq!(metadata.mode() & 0o7777, mode, "mode of {}", node);
+    }
+}
+
+#[test]
+fn copy_dir_special_files() {
+    let src = tmpdir();
+    fs::create_dir(src.path().join("dir")).expect("failed to create dir");
+    fs::write(src.path().join("dir").join("file"), "hello").expect("failed to write file");
+    nix::unistd::mkfifo(
+        &src.path().join("fifo"),
+        nix::sys::stat::Mode::from_bits_truncate(0o640),
+    )
+    .expect("failed to create fifo");
+    let dst = tmpdir();
+    let dst = dst.path().join("copy");
+    npk::copy_dir(src.path(), &dst).expect("failed to copy dir");
+    assert_eq!(
+        fs::read_to_string(dst.join("dir").join("file")).expect("failed to read file"),
+        "hello"
+    );
+    let fifo = fs::symlink_metadata(dst.join("fifo")).expect("failed to stat fifo");
+    assert!(fifo.file_type().is_fifo());
+    assert_eq!(fifo.mode() & 0o7777, 0o640);
+
+    let _socket = std::os::unix::net::UnixListener::bind(src.path().join("socket"))
+        .expect("failed to bind socket");
+    assert!(npk::copy_dir(src.path(), &dst.with_extension("socket")).is_err());
+}
+
 #[test]
 fn generate_key_pair() {
     let dest = tmpdir();
@@ -1,10 +1,18 @@
-use std::path::{Path, PathBuf};
+use std::{
+    iter::empty,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
 use anyhow::Result;
 use log::debug;
-use northstar_runtime::api::{
-    self,
-    model::{self, ExitStatus, Notification},
+use northstar_runtime::{
+    api::{
+        self,
+        model::{self, ExitStatus, Notification},
+    },
+    common::non_nul_string::NonNulString,
+    seccomp::{Profile, SyscallRule},
 };
 use northstar_tests::{containers::*, logger::assume, runtime::client, runtime_test};
 
@@ -161,6 +169,37 @@ async fn mount_umount() -> Result<()> {
     Ok(())
 }
 
+// List, mount and kill containers selected by labels
+#[runtime_test]
+async fn label_selector() -> Result<()> {
+    client().install_test_container().await?;
+    client().install_test_resource().await?;
+
+    let containers = client().list_selector("tier=test").await?;
+    assert_eq!(containers.len(), 1);
+    assert_eq!(containers[0].container.to_string(), TEST_CONTAINER);
+    assert_eq!(client().list_selector("!tier").await?.len(), 1);
+    assert!(client().list_selector("tier=background").await?.is_empty());
+
+    let result = client().mount_selector("tier=test").await?;
+    assert!(matches!(result.as_slice(), [model::MountResult::Ok { .. }]));
+    // Mounted containers are skipped
+    assert!(client().mount_selector("tier=test").await?.is_empty());
+
+    client().start_with_args(TEST_CONTAINER, ["sleep"]).await?;
+    assume("Sleeping", 5).await?;
+    let result = client().kill_selector("tier=test", 15).await?;
+    assert!(matches!(result.as_slice(), [model::KillResult::Ok { .. }]));
+    assume("Process test-container:0.0.1 exited", 5).await?;
+
+    let result = client().umount_selector("tier=test").await?;
+    assert!(matches!(
+        result.as_slice(),
+        [model::UmountResult::Ok { .. }]
+    ));
+    Ok(())
+}
+
 // Try to mount a unknown container
 #[runtime_test]
 async fn try_to_mount_unknown_container() -> Result<()> {
@@ -326,6 +365,25 @@ async fn container_crash_exit() -> Result<()> {
     client().uninstall_test_resource().await
 }
 
+// Stop a container that exceeds the deadline passed with the start request
+#[runtime_test]
+async fn start_deadline() -> Result<()> {
+    client().install_test_container().await?;
+    client().install_test_resource().await?;
+
+    let deadline = Some(Duration::from_secs(1));
+    client()
+        .start_with_args_env_deadline(TEST_CONTAINER, ["sleep"], empty(), deadline)
+        .await?;
+    assume("Sleeping...", 5u64).await?;
+
+    let n = |n: &Notification| matches!(n, Notification::Exit(_, ExitStatus::Deadline { .. }));
+    client().assume_notification(n, 10).await?;
+
+    client().uninstall_test_container().await?;
+    client().uninstall_test_resource().await
+}
+
 // Check uid. In the manifest of the test container the uid
 // is set to 1000
 #[runtime_test]
@@ -352,6 +410,54 @@ async fn container_uses_correct_gid() -> Result<()> {
     client().stop(TEST_CONTAINER, 5).await
 }
 
+// Check the working directory. In the manifest of the cwd-umask test container the
+// cwd is set to /tmpfs
+#[runtime_test]
+async fn container_uses_correct_cwd() -> Result<()> {
+    client()
+        .install(TEST_CONTAINER_CWD_UMASK_NPK, "mem")
+        .await?;
+    client().install_test_resource().await?;
+    client()
+        .start_with_args(TEST_CONTAINER_CWD_UMASK, ["inspect"])
+        .await?;
+    assume("pwd: /tmpfs", 5).await?;
+    client().stop(TEST_CONTAINER_CWD_UMASK, 5).await?;
+    assume("Container test-container-cwd-umask:0.0.1 exited", 5).await?;
+    client().uninstall(TEST_CONTAINER_CWD_UMASK, true).await?;
+    client().uninstall_test_resource().await
+}
+
+// Check umask. In the manifest of the cwd-umask test container the umask
+// is set to 0027
+#[runtime_test]
+async fn container_uses_correct_umask() -> Result<()> {
+    client()
+        .install(TEST_CONTAINER_CWD_UMASK_NPK, "mem")
+        .await?;
+    client().install_test_resource().await?;
+    client()
+        .start_with_args(TEST_CONTAINER_CWD_UMASK, ["inspect"])
+        .await?;
+    assume("umask: 0027", 5).await?;
+    client().stop(TEST_CONTAINER_CWD_UMASK, 5).await?;
+    assume("Container test-container-cwd-umask:0.0.1 exited", 5).await?;
+    client().uninstall(TEST_CONTAINER_CWD_UMASK, true).await?;
+    client().uninstall_test_resource().await
+}
+
+// The no new privileges flag is set by default
+#[runtime_test]
+async fn container_no_new_privs() -> Result<()> {
+    client().install_test_container().await?;
+    client().install_test_resource().await?;
+    client()
+        .start_with_args(TEST_CONTAINER, ["inspect"])
+        .await?;
+    assume("no_new_privs: 1", 5).await?;
+    client().stop(TEST_CONTAINER, 5).await
+}
+
 // Check parent pid. Northstar starts an init process which must have pid 1.
 #[runtime_test]
 async fn container_ppid_must_be_init() -> Result<()> {
@@ -364,6 +470,100 @@ async fn container_ppid_must_be_init() -> Result<()> {
     client().stop(TEST_CONTAINER, 5).await
 }
 
+// Only init and the container process are visible in the pid namespace
+#[runtime_test]
+async fn container_pid_namespace() -> Result<()> {
+    client().install_test_container().await?;
+    client().install_test_resource().await?;
+    client().start_with_args(TEST_CONTAINER, ["ps"]).await?;
+    assume(r"pids: \[1, 2\]", 5).await?;
+    client().stop(TEST_CONTAINER, 5).await
+}
+
+// Containers with bridge networking get an address and a default route via the bridge.
+// The host end of the veth pair is removed when the container exits.
+#[runtime_test]
+async fn container_bridge_network() -> Result<()> {
+    client().install(TEST_CONTAINER_BRIDGE_NPK, "mem").await?;
+    client().install_test_resource().await?;
+
+    client()
+        .start_with_args(TEST_CONTAINER_BRIDGE, ["cat", "/proc/net/fib_trie"])
+        .await?;
+    assume(r"172\.30\.0\.2", 5).await?;
+    client().stop(TEST_CONTAINER_BRIDGE, 5).await?;
+    assume("Container test-container-bridge:0.0.1 exited", 5).await?;
+
+    // The gateway 172.30.0.1 is printed in host byte order (little endian)
+    client()
+        .start_with_args(TEST_CONTAINER_BRIDGE, ["cat", "/proc/net/route"])
+        .await?;
+    assume(r"eth0\s+00000000\s+01001EAC", 5).await?;
+    client().stop(TEST_CONTAINER_BRIDGE, 5).await?;
+    assume("Container test-container-bridge:0.0.1 exited", 5).await?;
+
+    let interfaces = std::fs::read_to_string("/proc/net/dev")?;
+    assert!(!interfaces.contains("nsv2:"));
+
+    client().uninstall(TEST_CONTAINER_BRIDGE, true).await?;
+    client().uninstall_test_resource().await
+}
+
+// Without a pid namespace the host processes are visible and the container can
+// still be stopped by signalling the process group of init
+#[runtime_test]
+async fn container_without_pid_namespace() -> Result<()> {
+    client().install(TEST_CONTAINER_NO_PIDNS_NPK, "mem").await?;
+    client().install_test_resource().await?;
+
+    client()
+        .start_with_args(TEST_CONTAINER_NO_PIDNS, ["ps"])
+        .await?;
+    assume(r"processes: ([3-9]|[1-9][0-9]+)", 5).await?;
+    client().stop(TEST_CONTAINER_NO_PIDNS, 5).await?;
+
+    client()
+        .start_with_args(TEST_CONTAINER_NO_PIDNS, ["inspect"])
+        .await?;
+    assume(r"getppid: ([2-9]|[1-9][0-9]+)", 5).await?;
+    client().stop(TEST_CONTAINER_NO_PIDNS, 5).await?;
+
+    client().uninstall(TEST_CONTAINER_NO_PIDNS, true).await?;
+    client().uninstall_test_resource().await
+}
+
+// The cgroup namespace of a container is rooted at the container cgroup
+#[runtime_test]
+async fn container_cgroup_namespace() -> Result<()> {
+    client().install_test_container().await?;
+    client().install_test_resource().await?;
+    let args = ["cat", "/proc/self/cgroup"];
+    client().start_with_args(TEST_CONTAINER, args).await?;
+    assume(r"[0-9]+:[a-z,_=]*:/$", 5).await?;
+    client().stop(TEST_CONTAINER, 5).await
+}
+
+// A container with a delegated cgroup can create child cgroups. Delegation
+// requires cgroup v2.
+#[runtime_test]
+async fn container_delegated_cgroup() -> Result<()> {
+    if Path::new("/sys/fs/cgroup/cgroup.controllers").exists() {
+        client().install(TEST_CONTAINER_CGROUP_NPK, "mem").await?;
+        client().install_test_resource().await?;
+
+        let args = ["mkdir", "/sys/fs/cgroup/worker"];
+        client()
+            .start_with_args(TEST_CONTAINER_CGROUP, args)
+            .await?;
+        assume("Sleeping", 5).await?;
+        client().stop(TEST_CONTAINER_CGROUP, 5).await?;
+
+        client().uninstall(TEST_CONTAINER_CGROUP, true).await?;
+        client().uninstall_test_resource().await?;
+    }
+    Ok(())
+}
+
 // Check session id which needs to be pid of init
 #[runtime_test]
 async fn container_sid_must_be_init_or_none() -> Result<()> {
@@ -507,6 +707,25 @@ async fn selinux_mounted_squasfs_has_correct_context() -> Result<()> {
     client().stop(TEST_CONTAINER, 5).await
 }
 
+// The container process runs in the exec context of the manifest
+#[runtime_test]
+async fn selinux_process_has_exec_context() -> Result<()> {
+    client().install(TEST_CONTAINER_SELINUX_NPK, "mem").await?;
+    client().install_test_resource().await?;
+    client()
+        .start_with_args(TEST_CONTAINER_SELINUX, ["cat", "/proc/self/attr/current"])
+        .await?;
+    // The exec context is ignored on hosts without SELinux
+    if Path::new("/sys/fs/selinux/enforce").exists() {
+        assume("unconfined_u:unconfined_r:unconfined_t:s0", 5).await?;
+    }
+    client().stop(TEST_CONTAINER_SELINUX, 5).await?;
+    assume("Container test-container-selinux:0.0.1 exited", 5).await?;
+
+    client().uninstall(TEST_CONTAINER_SELINUX, true).await?;
+    client().uninstall_test_resource().await
+}
+
 // Call syscall with specifically allowed argument
 #[runtime_test]
 async fn seccomp_allowed_syscall_with_allowed_arg() -> Result<()> {
@@ -550,6 +769,28 @@ async fn seccomp_allowed_syscall_with_prohibited_arg() -> Result<()> {
     client().assume_notification(n, 5).await
 }
 
+// In seccomp learn mode a prohibited system call is continued and recorded. The argument
+// rule of the manifest is kept in the learned profile.
+#[runtime_test(northstar_tests::runtime::seccomp_learn)]
+async fn seccomp_learn_mode() -> Result<()> {
+    client().install_test_container().await?;
+    client().install_test_resource().await?;
+    client()
+        .start_with_args(TEST_CONTAINER, ["call-delete-module", "7"])
+        .await?;
+    assume("delete_module syscall was successful", 5).await?;
+    client().stop(TEST_CONTAINER, 5).await?;
+
+    let seccomp = client().seccomp(TEST_CONTAINER).await?;
+    assert_eq!(seccomp.profile, Some(Profile::Default));
+    let allow = seccomp.allow.expect("missing allow list");
+    assert!(matches!(
+        allow.get(&NonNulString::try_from("delete_module")?),
+        Some(SyscallRule::Args(_))
+    ));
+    Ok(())
+}
+
 // Iterate all exit codes in the u8 range
 #[runtime_test]
 async fn exit_codes() -> Result<()> {
@@ -586,3 +827,81 @@ async fn stdout_stderr() -> Result<()> {
     assume("hello stderr", 10).await?;
     client().stop(TEST_CONTAINER, 5).await
 }
+
+// Containers without a user namespace share the uid map of the runtime. This is
+// the identity mapping unless the tests run in a user namespace.
+#[runtime_test]
+async fn container_without_user_namespace_uses_host_ids() -> Result<()> {
+    client().install_test_container().await?;
+    client().install_test_resource().await?;
+
+    let uid_map = std::fs::read_to_string("/proc/self/uid_map")?;
+    let expected = uid_map
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(r"\s+");
+
+    let args = ["cat", "/proc/self/uid_map"];
+    client().start_with_args(TEST_CONTAINER, args).await?;
+    assume(expected, 5).await?;
+    client().stop(TEST_CONTAINER, 5).await
+}
+
+// Check the uid and gid maps of a container with a user namespace. The first
+// slot of the id range configured in the test runtime starts at 100000.
+#[runtime_test]
+async fn container_user_namespace_id_maps() -> Result<()> {
+    client().install(TEST_CONTAINER_USERNS_NPK, "mem").await?;
+    client().install_test_resource().await?;
+
+    let args = ["cat", "/proc/self/uid_map"];
+    client()
+        .start_with_args(TEST_CONTAINER_USERNS, args)
+        .await?;
+    assume(r"0\s+100000\s+65536", 5).await?;
+    client().stop(TEST_CONTAINER_USERNS, 5).await?;
+
+    let args = ["cat", "/proc/self/gid_map"];
+    client()
+        .start_with_args(TEST_CONTAINER_USERNS, args)
+        .await?;
+    assume(r"0\s+100000\s+65536", 5).await?;
+    client().stop(TEST_CONTAINER_USERNS, 5).await?;
+
+    client()
+        .start_with_args(TEST_CONTAINER_USERNS, ["inspect"])
+        .await?;
+    assume("getuid: 1000", 5).await?;
+    client().stop(TEST_CONTAINER_USERNS, 5).await?;
+
+    client().uninstall(TEST_CONTAINER_USERNS, true).await?;
+    client().uninstall_test_resource().await
+}
+
+// Data written to the persist mount of a container with a user namespace must
+// be readable after a restart
+#[runtime_test]
+async fn container_user_namespace_persist() -> Result<()> {
+    client().install(TEST_CONTAINER_USERNS_NPK, "mem").await?;
+    client().install_test_resource().await?;
+
+    let args = ["write", "hello userns", "/data/file"];
+    client()
+        .start_with_args(TEST_CONTAINER_USERNS, args)
+        .await?;
+    assume("Sleeping", 5).await?;
+    client().stop(TEST_CONTAINER_USERNS, 5).await?;
+
+    let args = ["cat", "/data/file"];
+    client()
+        .start_with_args(TEST_CONTAINER_USERNS, args)
+        .await?;
+    assume("hello userns", 5).await?;
+    client().stop(TEST_CONTAINER_USERNS, 5).await?;
+
+    client().uninstall(TEST_CONTAINER_USERNS, true).await?;
+    client().uninstall_test_resource().await
+}
@@ -1,11 +1,15 @@
-/// Initialize the logger
+use northstar_runtime::runtime::config::Config;
+
+/// Initialize the logger. The log configuration is not used on Android where all
+/// output goes to logd.
 #[cfg(target_os = "android")]
-pub fn init() {
+pub fn init(_config: &Config) -> anyhow::Result<()> {
     android_logd_logger::builder()
         .tag("northstar")
         .prepend_module(false)
         .parse_filters("northstar=debug")
         .init();
+    Ok(())
 }
 
 #[cfg(not(target_os = "android"))]
@@ -13,8 +17,10 @@ static TAG_SIZE: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize
 
 /// Initialize the logger
 #[cfg(not(target_os = "android"))]
-pub fn init() {
+pub fn init(config: &Config) -> anyhow::Result<()> {
+    use anyhow::Context;
     use env_logger::fmt::Color;
+    use northstar_runtime::runtime::config::LogFormat;
     use std::{io::Write, sync::atomic::Ordering};
 
     fn color(target: &str) -> Color {
@@ -32,11 +38,33 @@ pub fn init() {
         })
     }
 
+    let format = config.log.format;
+    let file = config
+        .log
+        .file
+        .as_ref()
+        .map(|file| file::File::open(&config.log_dir.join("northstar.log"), file))
+        .transpose()
+        .context("failed to open log file")?
+        .map(std::sync::Mutex::new);
+
     let mut builder = env_logger::Builder::new();
     builder.parse_filters("debug");
 
-    builder.format(|buf, record| {
+    builder.format(move |buf, record| {
         let timestamp = buf.timestamp_millis().to_string();
+
+        if format == LogFormat::Json {
+            let line = json::format(&timestamp, record);
+            if let Some(file) = &file {
+                // There is no place to report errors of the logger
+                if let Ok(mut file) = file.lock() {
+                    file.write_line(&line).ok();
+                }
+            }
+            return writeln!(buf, "{}", line);
+        }
+
         let timestamp = timestamp.strip_suffix('Z').unwrap();
 
         let mut level = buf.default_level_style(record.metadata().level());
@@ -47,7 +75,7 @@ pub fn init() {
         let mut pid_style = buf.style();
         pid_style.set_color(color(&pid));
 
-        if let Some(target) = Option::from(record.target().is_empty())
+        let target = Option::from(record.target().is_empty())
             .map(|_| record.target())
             .or_else(|| record.module_path())
             .map(|module_path| {
@@ -59,11 +87,29 @@ pub fn init() {
                 module_path
                     .strip_prefix("northstar_runtime::")
                     .unwrap_or(module_path)
-            })
-        {
-            let mut tag_style = buf.style();
+            });
+        if let Some(target) = target {
             TAG_SIZE.fetch_max(target.len(), Ordering::SeqCst);
-            let tag_size = TAG_SIZE.load(Ordering::SeqCst);
+        }
+        let tag_size = TAG_SIZE.load(Ordering::SeqCst);
+
+        if let Some(file) = &file {
+            let line = format!(
+                "{} {:>s$} {}  {:<5}: {}",
+                timestamp,
+                target.unwrap_or_default(),
+                pid,
+                record.metadata().level(),
+                record.args(),
+                s = tag_size,
+            );
+            if let Ok(mut file) = file.lock() {
+                file.write_line(&line).ok();
+            }
+        }
+
+        if let Some(target) = target {
+            let mut tag_style = buf.style();
             tag_style.set_color(color(target));
 
             writeln!(
@@ -81,7 +127,7 @@ pub fn init() {
                 buf,
                 "{} {} {}  {:<5}: {}",
                 timestamp,
-                " ".repeat(TAG_SIZE.load(Ordering::SeqCst)),
+                " ".repeat(tag_size),
                 pid_style.value("⬤"),
                 level,
                 record.args(),
@@ -89,5 +135,242 @@ pub fn init() {
         }
     });
 
-    builder.init()
+    builder.init();
+    Ok(())
+}
+
+#[cfg(not(target_os = "android"))]
+mod json {
+    use northstar_runtime::common::container::Container;
+
+    /// Format `record` as JSON object. Output of containers that is piped into the log
+    /// has the container as module path and as target unless a tag is configured.
+    pub fn format(timestamp: &str, record: &log::Record) -> String {
+        let container = record
+            .module_path()
+            .into_iter()
+            .chain(Some(record.target()))
+            .find_map(|s| Container::try_from(s).ok())
+            .map(|container| container.to_string());
+        serde_json::json!({
+            "timestamp": timestamp,
+            "level": record.level().as_str(),
+            "target": record.target(),
+            "container": container,
+            "message": record.args().to_string(),
+        })
+        .to_string()
+    }
+
+    #[cfg(test)]
+    #[allow(clippy::unwrap_used)]
+    mod test {
+        use super::*;
+        use log::Level;
+        use serde_json::{json, Value};
+
+        #[test]
+        fn format_runtime_record() {
+            let line = format(
+                "2022-01-01T00:00:00.000Z",
+                &log::Record::builder()
+                    .args(format_args!("hello \"world\""))
+                    .level(Level::Warn)
+                    .target("northstar_runtime::runtime::state")
+                    .module_path(Some("northstar_runtime::runtime::state"))
+                    .build(),
+            );
+            assert_eq!(
+                serde_json::from_str::<Value>(&line).unwrap(),
+                json!({
+                    "timestamp": "2022-01-01T00:00:00.000Z",
+                    "level": "WARN",
+                    "target": "northstar_runtime::runtime::state",
+                    "container": null,
+                    "message": "hello \"world\"",
+                })
+            );
+            assert!(!line.contains('\n'));
+        }
+
+        #[test]
+        fn format_container_output() {
+            let line = format(
+                "2022-01-01T00:00:00.000Z",
+                &log::Record::builder()
+                    .args(format_args!("hello"))
+                    .level(Level::Info)
+                    .target("hello:0.0.1")
+                    .module_path(Some("hello:0.0.1"))
+                    .build(),
+            );
+            assert_eq!(
+                serde_json::from_str::<Value>(&line).unwrap(),
+                json!({
+                    "timestamp": "2022-01-01T00:00:00.000Z",
+                    "level": "INFO",
+                    "target": "hello:0.0.1",
+                    "container": "hello:0.0.1",
+                    "message": "hello",
+                })
+            );
+
+            // Tagged output has the tag as target and the container as module path
+            let line = format(
+                "2022-01-01T00:00:00.000Z",
+                &log::Record::builder()
+                    .args(format_args!("hello"))
+                    .level(Level::Error)
+                    .target("tag")
+                    .module_path(Some("hello:0.0.1"))
+                    .build(),
+            );
+            let value = serde_json::from_str::<Value>(&line).unwrap();
+            assert_eq!(value["target"], "tag");
+            assert_eq!(value["container"], "hello:0.0.1");
+            assert_eq!(value["level"], "ERROR");
+        }
+    }
+}
+
+#[cfg(not(target_os = "android"))]
+mod file {
+    use northstar_runtime::runtime::config::LogFile;
+    use std::{
+        fs,
+        io::{self, Write},
+        os::unix::fs::MetadataExt,
+        path::{Path, PathBuf},
+    };
+
+    /// Log file that is rotated when a write exceeds the maximum size
+    pub struct File {
+        path: PathBuf,
+        file: fs::File,
+        max_size: u64,
+        max_files: u32,
+        /// Process that opened the file and rotates it
+        pid: u32,
+    }
+
+    impl File {
+        /// Open `path` for appending
+        pub fn open(path: &Path, config: &LogFile) -> io::Result<File> {
+            Ok(File {
+                path: path.to_owned(),
+                file: open(path)?,
+                max_size: config.max_size,
+                max_files: config.max_files,
+                pid: std::process::id(),
+            })
+        }
+
+        /// Append `line`. The file is rotated first if the line would exceed the maximum size.
+        pub fn write_line(&mut self, line: &str) -> io::Result<()> {
+            let line = format!("{}\n", line);
+
+            // The forked processes of the runtime log into the same file. Reopen the
+            // file if it was rotated by the runtime.
+            let size = match fs::metadata(&self.path) {
+                Ok(metadata) if metadata.ino() == self.file.metadata()?.ino() => metadata.len(),
+                _ => {
+                    self.file = open(&self.path)?;
+                    self.file.metadata()?.len()
+                }
+            };
+
+            // Only the runtime rotates. Concurrent rotations of forked processes would
+            // shift the same files twice.
+            let rotate = std::process::id() == self.pid;
+            if rotate && size > 0 && size + line.len() as u64 > self.max_size {
+                self.rotate()?;
+            }
+            self.file.write_all(line.as_bytes())
+        }
+
+        /// Shift the rotated files and start a new file
+        fn rotate(&mut self) -> io::Result<()> {
+            for n in (1..self.max_files).rev() {
+                let from = self.rotated(n);
+                if from.exists() {
+                    fs::rename(&from, self.rotated(n + 1))?;
+                }
+            }
+            if self.max_files > 0 {
+                fs::rename(&self.path, self.rotated(1))?;
+            } else {
+                fs::remove_file(&self.path)?;
+            }
+            self.file = open(&self.path)?;
+            Ok(())
+        }
+
+        /// Path of the `n`th rotated file
+        fn rotated(&self, n: u32) -> PathBuf {
+            let mut path = self.path.clone().into_os_string();
+            path.push(format!(".{}", n));
+            path.into()
+        }
+    }
+
+    fn open(path: &Path) -> io::Result<fs::File> {
+        fs::OpenOptions::new().create(true).append(true).open(path)
+    }
+
+    #[cfg(test)]
+    #[allow(clippy::unwrap_used)]
+    mod test {
+        use super::*;
+
+        #[test]
+        fn rotate_at_max_size() {
+            let dir = tempfile::tempdir().unwrap();
+            let path = dir.path().join("northstar.log");
+            let config = LogFile {
+                max_size: 10,
+                max_files: 2,
+            };
+            let mut file = File::open(&path, &config).unwrap();
+
+            // Two lines of five bytes fill the file up to the maximum size
+            file.write_line("0000").unwrap();
+            file.write_line("1111").unwrap();
+            assert_eq!(fs::read_to_string(&path).unwrap(), "0000\n1111\n");
+            assert!(!file.rotated(1).exists());
+
+            // The next line exceeds the maximum size
+            file.write_line("2222").unwrap();
+            assert_eq!(fs::read_to_string(&path).unwrap(), "2222\n");
+            assert_eq!(fs::read_to_string(file.rotated(1)).unwrap(), "0000\n1111\n");
+
+            // Rotated files are shifted and at most `max_files` are kept
+            for line in ["3333", "4444", "5555", "6666", "7777"] {
+                file.write_line(line).unwrap();
+            }
+            assert_eq!(fs::read_to_string(&path).unwrap(), "6666\n7777\n");
+            assert_eq!(fs::read_to_string(file.rotated(1)).unwrap(), "4444\n5555\n");
+            assert_eq!(fs::read_to_string(file.rotated(2)).unwrap(), "2222\n3333\n");
+            assert!(!file.rotated(3).exists());
+        }
+
+        #[test]
+        fn line_larger_than_max_size() {
+            let dir = tempfile::tempdir().unwrap();
+            let path = dir.path().join("northstar.log");
+            let config = LogFile {
+                max_size: 4,
+                max_files: 1,
+            };
+            let mut file = File::open(&path, &config).unwrap();
+
+            // A line is never split and an empty file is not rotated
+            file.write_line("0123456789").unwrap();
+            assert_eq!(fs::read_to_string(&path).unwrap(), "0123456789\n");
+            assert!(!file.rotated(1).exists());
+
+            file.write_line("abc").unwrap();
+            assert_eq!(fs::read_to_string(&path).unwrap(), "abc\n");
+            assert_eq!(fs::read_to_string(file.rotated(1)).unwrap(), "0123456789\n");
+        }
+    }
 }
@@ -38,8 +38,12 @@ struct Opt {
 }
 
 fn main() -> Result<(), Error> {
+    // Parse command line arguments and read the configuration
+    let opt = Opt::parse();
+    let config = config(&opt)?;
+
     // Initialize logging
-    logger::init();
+    logger::init(&config)?;
 
     info!("Northstar Runtime v{}", env!("CARGO_PKG_VERSION"));
     debug!(
@@ -55,8 +59,8 @@ fn main() -> Result<(), Error> {
         exit(1);
     }));
 
-    // Parse command line arguments and prepare the environment
-    let config = init()?;
+    // Prepare the environment
+    init(&opt)?;
 
     // Create the runtime launcher. This must be done *before* spawning the tokio threadpool.
     let northstar = Northstar::new(config)?;
@@ -69,8 +73,7 @@ fn main() -> Result<(), Error> {
         .block_on(run(northstar))
 }
 
-fn init() -> Result<Config, Error> {
-    let opt = Opt::parse();
+fn config(opt: &Opt) -> Result<Config, Error> {
     let config = read_to_string(&opt.config)
         .with_context(|| format!("failed to read configuration file {}", opt.config.display()))?;
     let config: Config = toml::from_str(&config)
@@ -80,6 +83,10 @@ fn init() -> Result<Config, Error> {
     fs::create_dir_all(&config.data_dir).context("failed to create data_dir")?;
     fs::create_dir_all(&config.log_dir).context("failed to create log dir")?;
 
+    Ok(config)
+}
+
+fn init(opt: &Opt) -> Result<(), Error> {
     // Skip mount namespace setup in case it's disabled for debugging purposes
     if !opt.disable_mount_namespace {
         // Enter a mount namespace. This needs to be done before spawning the tokio threadpool.
@@ -98,7 +105,7 @@ fn init() -> Result<Config, Error> {
         debug!("Mount namespace is disabled");
     }
 
-    Ok(config)
+    Ok(())
 }
 
 async fn run(northstar: Northstar) -> Result<(), Error> {